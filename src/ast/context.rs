@@ -1,13 +1,17 @@
 // A hash map of the variables by scope level in the parser.
 
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::Range};
 
-use crate::ast::{Argument, VarType};
+use crate::{
+  ast::{Argument, VarType},
+  diagnostics::suggestions::did_you_mean,
+  lexer::{ErrorTip, LexicalError},
+};
 
 use super::{Function, Statement, Variable};
 
 // A struct to hold the context of the parser.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Context {
   // The current scope level.
   pub scope_level: usize,
@@ -62,6 +66,53 @@ impl Context {
     None
   }
 
+  /// Names of the variables in scope
+  pub fn variable_names(&self) -> impl Iterator<Item = &str> {
+    (0..=self.scope_level)
+      .filter_map(|scope| self.variables.get(&scope))
+      .flatten()
+      .map(|variable| variable.name.as_str())
+  }
+
+  /// Names of the functions that can be called, the builtins first
+  pub fn function_names(&self) -> Vec<String> {
+    self
+      .builtins()
+      .into_iter()
+      .map(|function| function.name)
+      .chain(self.functions.keys().cloned())
+      .collect()
+  }
+
+  /// The error of a variable that is not in scope, suggesting the closest names that are
+  pub fn unknown_variable(&self, name: &str, location: Range<usize>) -> LexicalError {
+    let (help, fix) = did_you_mean(name, self.variable_names(), location.clone());
+
+    LexicalError::UnknownVariable {
+      error: vec![ErrorTip {
+        message: format!("unknown variable `{name}`"),
+        location,
+      }],
+      help,
+      fix,
+    }
+  }
+
+  /// The error of a call to a function that does not exist, suggesting the closest ones
+  pub fn unknown_function(&self, name: &str, location: Range<usize>) -> LexicalError {
+    let names = self.function_names();
+    let (help, fix) = did_you_mean(name, names.iter().map(String::as_str), location.clone());
+
+    LexicalError::UnknownFunction {
+      error: vec![ErrorTip {
+        message: format!("unknown function `{name}`"),
+        location,
+      }],
+      help,
+      fix,
+    }
+  }
+
   pub fn add_function(&mut self, function: &Function) -> Result<(), String> {
     if self.functions.contains_key(&function.name) {
       return Err(format!("Function `{}` already defined", function.name));
@@ -83,7 +134,8 @@ impl Context {
       .or_else(|| self.functions.get(name).cloned())
   }
 
-  fn builtins(&self) -> Vec<Function> {
+  /// Functions provided by the runtime, which every program can call
+  pub fn builtins(&self) -> Vec<Function> {
     vec![
       Function {
         name: "write_string".to_string(),
@@ -91,6 +143,7 @@ impl Context {
         args: vec![Argument {
           name: "message".to_string(),
           var_type: VarType::Str,
+          location: 0..0,
         }],
         return_type: VarType::Void,
         location: 0..0,
@@ -102,6 +155,7 @@ impl Context {
         args: vec![Argument {
           name: "number".to_string(),
           var_type: VarType::I32,
+          location: 0..0,
         }],
         return_type: VarType::Void,
         location: 0..0,
@@ -121,6 +175,7 @@ impl Context {
         args: vec![Argument {
           name: "size".to_string(),
           var_type: VarType::U32,
+          location: 0..0,
         }],
         return_type: VarType::Str,
         location: 0..0,
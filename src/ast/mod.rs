@@ -2,7 +2,7 @@ use std::{ops::Range, str::FromStr};
 
 use lalrpop_util::ParseError;
 
-use crate::lexer::{tokens, ErrorTip, LexicalError};
+use crate::lexer::{tokens, LexicalError};
 
 pub type Location = std::ops::Range<usize>;
 pub mod context;
@@ -134,6 +134,60 @@ pub enum Statement {
   NoOperation,
 }
 
+impl Statement {
+  /// The span the parser gave the statement, `if false goto` is dropped before it gets one
+  pub fn location(&self) -> Option<&Location> {
+    match self {
+      Statement::VariableDeclaration(variable) => Some(&variable.location),
+      Statement::FunctionDefinition(function) => Some(&function.location),
+      Statement::Call(call) => Some(&call.location),
+      Statement::ConditionalJump { location, .. }
+      | Statement::UnconditionalJump { location, .. }
+      | Statement::Label { location, .. }
+      | Statement::Store { location, .. } => Some(location),
+      Statement::NoOperation => None,
+    }
+  }
+}
+
+// The statements print as canonical ETAC, which parses back to the same AST
+impl std::fmt::Display for Statement {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Statement::VariableDeclaration(variable) => write!(f, "{}", variable),
+      Statement::ConditionalJump {
+        condition, label, ..
+      } => write!(f, "if {} goto {}", condition, label),
+      Statement::UnconditionalJump { label, .. } => write!(f, "goto {}", label),
+      Statement::Label { name, .. } => write!(f, "{}:", name),
+      Statement::FunctionDefinition(function) => write!(f, "{}", function),
+      Statement::Store { at, from, .. } => write!(f, "store {} {}", at, from),
+      Statement::Call(call) => write!(f, "{}", call),
+      // The only statement that parses to nothing, its label is never checked
+      Statement::NoOperation => write!(f, "if false goto _"),
+    }
+  }
+}
+
+/// Prints a program as canonical ETAC, with functions apart from the statements around them.
+pub fn print(program: &[Statement]) -> String {
+  let mut source = String::new();
+
+  for (index, statement) in program.iter().enumerate() {
+    let function = matches!(statement, Statement::FunctionDefinition(_));
+    let after_function =
+      index > 0 && matches!(program[index - 1], Statement::FunctionDefinition(_));
+    if index > 0 && (function || after_function) {
+      source.push('\n');
+    }
+
+    source.push_str(&statement.to_string());
+    source.push('\n');
+  }
+
+  source
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct FunctionCall {
   pub name: String,
@@ -160,6 +214,51 @@ pub struct Function {
   pub is_builtin: bool,
 }
 
+impl std::fmt::Display for FunctionCall {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let params: Vec<String> = self.params.iter().map(ToString::to_string).collect();
+    write!(f, "call {}({})", self.name, params.join(" "))
+  }
+}
+
+impl std::fmt::Display for Variable {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}: {} = {}", self.name, self.var_type, self.value)
+  }
+}
+
+impl Function {
+  /// The header of the definition, such as `func add(a: i32 b: i32): i32`
+  pub fn signature(&self) -> String {
+    let args: Vec<String> = self
+      .args
+      .iter()
+      .map(|arg| format!("{}: {}", arg.name, arg.var_type))
+      .collect();
+
+    let mut signature = format!("func {}({})", self.name, args.join(" "));
+    if self.return_type != VarType::Void {
+      signature += &format!(": {}", self.return_type);
+    }
+
+    signature
+  }
+}
+
+impl std::fmt::Display for Function {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "{}", self.signature())?;
+
+    writeln!(f, "begin")?;
+    for statement in &self.body {
+      for line in statement.to_string().lines() {
+        writeln!(f, "  {}", line)?;
+      }
+    }
+    write!(f, "end")
+  }
+}
+
 // Expressions are statements that return a value, such as
 // function calls, arithmetic operations, literals, etc.
 #[derive(Clone, Debug, PartialEq)]
@@ -169,6 +268,16 @@ pub enum Expr {
   Operand(Operand),
 }
 
+impl std::fmt::Display for Expr {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Expr::BinaryOperation(operation) => write!(f, "{}", operation),
+      Expr::FunctionCall(call) => write!(f, "{}", call),
+      Expr::Operand(operand) => write!(f, "{}", operand),
+    }
+  }
+}
+
 // TODO: Impl get_type for Expr
 
 #[derive(Clone, Debug, PartialEq)]
@@ -180,6 +289,8 @@ pub struct Return {
 pub struct Argument {
   pub name: String,
   pub var_type: VarType,
+  /// Of the name and the type, builtins have none
+  pub location: Location,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -198,6 +309,22 @@ pub enum BinaryOperation {
   },
 }
 
+impl std::fmt::Display for BinaryOperation {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      BinaryOperation::Arithmetic {
+        lhs, operator, rhs, ..
+      } => write!(f, "{} {} {}", lhs, operator, rhs),
+      BinaryOperation::Conditional {
+        lhs,
+        condition,
+        rhs,
+        ..
+      } => write!(f, "{} {} {}", lhs, condition, rhs),
+    }
+  }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Operand {
   Identifier(String),
@@ -228,7 +355,7 @@ impl Operand {
     Ok(match self {
       Operand::LiteralI8(val) => *val as i32,
       Operand::LiteralI16(val) => *val as i32,
-      Operand::LiteralI32(val) => *val as i32,
+      Operand::LiteralI32(val) => *val,
       Operand::LiteralI64(val) => *val as i32,
       Operand::LiteralU8(val) => *val as i32,
       Operand::LiteralU16(val) => *val as i32,
@@ -250,14 +377,8 @@ impl Operand {
       Operand::Identifier(variable_name) => {
         let var = context
           .get_variable(variable_name.clone())
-          .ok_or(ParseError::<usize, tokens::Token, LexicalError>::User {
-            error: LexicalError::UnknownVariable {
-              error: vec![ErrorTip {
-                message: format!("unknown variable `{}`", variable_name),
-                location: loc,
-              }],
-              help: None,
-            },
+          .ok_or_else(|| ParseError::User {
+            error: context.unknown_variable(variable_name, loc),
           })?;
 
         Ok(var.var_type)
@@ -302,27 +423,36 @@ impl TryFrom<Operand> for VarType {
   }
 }
 
+// Literals are printed with the suffix of their type, unless it is the default one
 impl std::fmt::Display for Operand {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     match self {
       Operand::Identifier(name) => write!(f, "{}", name),
       Operand::LiteralStr(value) => write!(f, "{}", value),
       Operand::LiteralBool(value) => write!(f, "{}", value),
-      Operand::LiteralI8(value) => write!(f, "{}", value),
-      Operand::LiteralI16(value) => write!(f, "{}", value),
+      Operand::LiteralI8(value) => write!(f, "{}i8", value),
+      Operand::LiteralI16(value) => write!(f, "{}i16", value),
       Operand::LiteralI32(value) => write!(f, "{}", value),
-      Operand::LiteralI64(value) => write!(f, "{}", value),
-      Operand::LiteralU8(value) => write!(f, "{}", value),
-      Operand::LiteralU16(value) => write!(f, "{}", value),
-      Operand::LiteralU32(value) => write!(f, "{}", value),
-      Operand::LiteralU64(value) => write!(f, "{}", value),
-      Operand::LiteralF32(value) => write!(f, "{}", value),
-      Operand::LiteralF64(value) => write!(f, "{}", value),
+      Operand::LiteralI64(value) => write!(f, "{}i64", value),
+      Operand::LiteralU8(value) => write!(f, "{}u8", value),
+      Operand::LiteralU16(value) => write!(f, "{}u16", value),
+      Operand::LiteralU32(value) => write!(f, "{}u32", value),
+      Operand::LiteralU64(value) => write!(f, "{}u64", value),
+      Operand::LiteralF32(value) => write!(f, "{}", float_literal(value.to_string())),
+      Operand::LiteralF64(value) => write!(f, "{}f64", float_literal(value.to_string())),
       Operand::Dereference(value) => write!(f, "*{}", value),
     }
   }
 }
 
+// Whole floats need a fractional part, or they would read back as integers
+fn float_literal(value: String) -> String {
+  match value.contains('.') {
+    true => value,
+    false => format!("{value}.0"),
+  }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Operator {
   Add,
@@ -353,3 +483,18 @@ pub enum Condition {
   And,
   Or,
 }
+
+impl std::fmt::Display for Condition {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Condition::LessThan => write!(f, "<"),
+      Condition::GreaterThan => write!(f, ">"),
+      Condition::LessThanOrEqual => write!(f, "<="),
+      Condition::GreaterThanOrEqual => write!(f, ">="),
+      Condition::Equal => write!(f, "=="),
+      Condition::NotEqual => write!(f, "!="),
+      Condition::And => write!(f, "&&"),
+      Condition::Or => write!(f, "||"),
+    }
+  }
+}
@@ -0,0 +1,384 @@
+// Compiles the ETAC AST into a bytecode `Module`.
+//
+// Variables are globals shared by the whole program, as in the MIPS backend, while function
+// arguments are locals of the call frame. The top level statements become the entry function.
+
+use std::collections::HashMap;
+
+use crate::{
+  ast::{Argument, BinaryOperation, Expr, Function, Operand, Statement, VarType},
+  codegen::{
+    context::Context,
+    error::CodegenError,
+    target::{CallingConvention, TargetInfo},
+    Artifact, Codegen,
+  },
+  runtime::{Value, BUILTINS},
+};
+
+use super::{FunctionEntry, Global, Instruction, Module};
+
+pub struct BytecodeCodegen;
+
+pub static INFO: TargetInfo = TargetInfo {
+  name: "bytecode",
+  description: "Compass bytecode, runnable with `compass vm`",
+  extension: "cbc",
+  word_size: 4,
+  pointer_size: 4,
+  var_types: &[
+    VarType::I8,
+    VarType::I16,
+    VarType::I32,
+    VarType::I64,
+    VarType::U8,
+    VarType::U16,
+    VarType::U32,
+    VarType::U64,
+    VarType::Bool,
+    VarType::F32,
+    VarType::F64,
+    VarType::Str,
+    VarType::Ptr,
+    VarType::Void,
+  ],
+  calling_convention: CallingConvention::Stack,
+  max_values: None,
+  compares_strings: true,
+};
+
+impl Codegen for BytecodeCodegen {
+  fn info(&self) -> &'static TargetInfo {
+    &INFO
+  }
+
+  fn generate(
+    &self,
+    ast: Vec<Statement>,
+    _context: &mut Context,
+  ) -> Result<Artifact, CodegenError> {
+    Ok(Artifact::Bytecode(compile(&ast)?))
+  }
+}
+
+pub fn compile(ast: &[Statement]) -> Result<Module, CodegenError> {
+  let mut compiler = Compiler::default();
+
+  let functions: Vec<&Function> = ast
+    .iter()
+    .filter_map(|statement| match statement {
+      Statement::FunctionDefinition(function) => Some(function),
+      _ => None,
+    })
+    .collect();
+
+  for (index, function) in functions.iter().enumerate() {
+    if compiler
+      .function_indices
+      .insert(function.name.clone(), index as u32)
+      .is_some()
+    {
+      let error = format!("Function `{}` defined more than once", function.name);
+      return Err(CodegenError::from(error).at(Some(&function.location)));
+    }
+  }
+
+  compiler.declare(ast, &[]);
+
+  let mut entries = vec![];
+  for function in &functions {
+    let entry = compiler.function(function);
+    entries.push(entry.map_err(|error| error.at(Some(&function.location)))?);
+  }
+
+  let main = FunctionEntry {
+    name: compiler.constant("__main"),
+    return_type: VarType::Void,
+    params: vec![],
+    code: compiler.body(ast, &HashMap::new(), None)?,
+  };
+  entries.push(main);
+
+  Ok(Module {
+    entry: entries.len() as u32 - 1,
+    functions: entries,
+    constants: compiler.constants,
+    globals: compiler.globals,
+  })
+}
+
+#[derive(Default)]
+struct Compiler {
+  constants: Vec<String>,
+  globals: Vec<Global>,
+  global_indices: HashMap<String, u32>,
+  function_indices: HashMap<String, u32>,
+}
+
+impl Compiler {
+  fn constant(&mut self, value: &str) -> u32 {
+    match self.constants.iter().position(|constant| constant == value) {
+      Some(index) => index as u32,
+      None => {
+        self.constants.push(value.to_string());
+        self.constants.len() as u32 - 1
+      }
+    }
+  }
+
+  // The first declaration of a variable decides the type recorded for it
+  fn global(&mut self, name: &str, var_type: VarType) -> u32 {
+    if let Some(index) = self.global_indices.get(name) {
+      return *index;
+    }
+
+    let index = self.globals.len() as u32;
+    let name_constant = self.constant(name);
+    self.globals.push(Global {
+      name: name_constant,
+      var_type,
+    });
+    self.global_indices.insert(name.to_string(), index);
+    index
+  }
+
+  // Registers the globals up front, so functions can use variables declared further down
+  fn declare(&mut self, statements: &[Statement], args: &[Argument]) {
+    for statement in statements {
+      match statement {
+        Statement::VariableDeclaration(variable)
+          if !args.iter().any(|argument| argument.name == variable.name) =>
+        {
+          self.global(&variable.name, variable.var_type);
+        }
+        Statement::FunctionDefinition(function) => self.declare(&function.body, &function.args),
+        _ => {}
+      }
+    }
+  }
+
+  fn function(&mut self, function: &Function) -> Result<FunctionEntry, CodegenError> {
+    let mut params = vec![];
+    let mut locals = HashMap::new();
+    for (index, argument) in function.args.iter().enumerate() {
+      locals.insert(argument.name.clone(), index as u32);
+      params.push(Global {
+        name: self.constant(&argument.name),
+        var_type: argument.var_type,
+      });
+    }
+
+    Ok(FunctionEntry {
+      name: self.constant(&function.name),
+      return_type: function.return_type,
+      params,
+      code: self.body(&function.body, &locals, Some(function.return_type))?,
+    })
+  }
+
+  /// Compiles a list of statements, `return_type` is `None` for the entry function, which halts
+  /// instead of returning.
+  fn body(
+    &mut self,
+    statements: &[Statement],
+    locals: &HashMap<String, u32>,
+    return_type: Option<VarType>,
+  ) -> Result<Vec<Instruction>, CodegenError> {
+    let mut code = vec![];
+    let mut labels = HashMap::new();
+    let mut fixups = vec![];
+
+    for statement in statements {
+      match statement {
+        Statement::ConditionalJump {
+          condition,
+          label,
+          location,
+        } => {
+          self
+            .expr(condition, locals, &mut code)
+            .map_err(|error| CodegenError::from(error).at(Some(location)))?;
+          fixups.push((code.len(), label, location));
+          code.push(Instruction::JumpIf(0));
+        }
+        Statement::UnconditionalJump { label, location } => {
+          fixups.push((code.len(), label, location));
+          code.push(Instruction::Jump(0));
+        }
+        Statement::Label { name, location } => {
+          if labels.insert(name.clone(), code.len() as u32).is_some() {
+            let error = format!("Label `{name}` defined more than once");
+            return Err(CodegenError::from(error).at(Some(location)));
+          }
+        }
+        statement => self
+          .statement(statement, locals, &mut code)
+          .map_err(|error| CodegenError::from(error).at(statement.location()))?,
+      }
+    }
+
+    // Functions end with `Value::zero` of their return type
+    match return_type {
+      Some(VarType::Void) => code.push(Instruction::Return),
+      Some(VarType::Str) => {
+        code.push(Instruction::PushStr(self.constant("")));
+        code.push(Instruction::Return);
+      }
+      Some(var_type) => {
+        code.push(Instruction::Push(Value::zero(var_type)));
+        code.push(Instruction::Return);
+      }
+      None => code.push(Instruction::Halt),
+    }
+
+    for (position, label, location) in fixups {
+      let target = *labels.get(label).ok_or_else(|| {
+        CodegenError::from(format!("Label `{label}` not found")).at(Some(location))
+      })?;
+
+      match &mut code[position] {
+        Instruction::Jump(pc) | Instruction::JumpIf(pc) => *pc = target,
+        _ => unreachable!("fixup of a non jump instruction"),
+      }
+    }
+
+    Ok(code)
+  }
+
+  // A statement that is not about control flow
+  fn statement(
+    &mut self,
+    statement: &Statement,
+    locals: &HashMap<String, u32>,
+    code: &mut Vec<Instruction>,
+  ) -> Result<(), String> {
+    match statement {
+      Statement::VariableDeclaration(variable) => {
+        self.expr(&variable.value, locals, code)?;
+
+        code.push(match locals.get(&variable.name) {
+          Some(index) => Instruction::StoreLocal(*index),
+          None => Instruction::StoreGlobal(self.global(&variable.name, variable.var_type)),
+        });
+      }
+      Statement::Store { at, from, .. } => {
+        let address = match at {
+          Operand::Dereference(name) => Operand::Identifier(name.clone()),
+          _ => {
+            return Err(format!(
+              "Invalid operands for store operation {} and {}",
+              at, from
+            ));
+          }
+        };
+
+        self.operand(&address, locals, code)?;
+        self.operand(from, locals, code)?;
+        code.push(Instruction::StoreIndirect);
+      }
+      Statement::Call(call) => {
+        self.call(&call.name, &call.params, locals, code)?;
+        if call.return_type != VarType::Void {
+          code.push(Instruction::Pop);
+        }
+      }
+      Statement::ConditionalJump { .. }
+      | Statement::UnconditionalJump { .. }
+      | Statement::Label { .. }
+      | Statement::FunctionDefinition(_)
+      | Statement::NoOperation => {}
+    }
+
+    Ok(())
+  }
+
+  fn expr(
+    &mut self,
+    expr: &Expr,
+    locals: &HashMap<String, u32>,
+    code: &mut Vec<Instruction>,
+  ) -> Result<(), String> {
+    match expr {
+      Expr::Operand(operand) => self.operand(operand, locals, code),
+      Expr::FunctionCall(call) => self.call(&call.name, &call.params, locals, code),
+      Expr::BinaryOperation(BinaryOperation::Arithmetic {
+        lhs,
+        operator,
+        rhs,
+        operation_type,
+      }) => {
+        self.operand(lhs, locals, code)?;
+        self.operand(rhs, locals, code)?;
+        code.push(Instruction::Arithmetic(operator.clone(), *operation_type));
+        Ok(())
+      }
+      Expr::BinaryOperation(BinaryOperation::Conditional {
+        lhs,
+        condition,
+        rhs,
+        ..
+      }) => {
+        self.operand(lhs, locals, code)?;
+        self.operand(rhs, locals, code)?;
+        code.push(Instruction::Compare(condition.clone()));
+        Ok(())
+      }
+    }
+  }
+
+  fn operand(
+    &mut self,
+    operand: &Operand,
+    locals: &HashMap<String, u32>,
+    code: &mut Vec<Instruction>,
+  ) -> Result<(), String> {
+    match operand {
+      Operand::Identifier(name) => code.push(match locals.get(name) {
+        Some(index) => Instruction::LoadLocal(*index),
+        None => Instruction::LoadGlobal(
+          *self
+            .global_indices
+            .get(name)
+            .ok_or_else(|| format!("Variable {} not found", name))?,
+        ),
+      }),
+      Operand::Dereference(name) => {
+        self.operand(&Operand::Identifier(name.clone()), locals, code)?;
+        code.push(Instruction::LoadIndirect);
+      }
+      Operand::LiteralStr(value) => {
+        let value = crate::utils::unquote_string_literal(value);
+        code.push(Instruction::PushStr(self.constant(&value)));
+      }
+      literal => code.push(Instruction::Push(
+        Value::from_literal(literal).expect("operand is a literal"),
+      )),
+    }
+
+    Ok(())
+  }
+
+  fn call(
+    &mut self,
+    name: &str,
+    params: &[Operand],
+    locals: &HashMap<String, u32>,
+    code: &mut Vec<Instruction>,
+  ) -> Result<(), String> {
+    for param in params {
+      self.operand(param, locals, code)?;
+    }
+
+    if let Some(builtin) = BUILTINS.iter().position(|builtin| *builtin == name) {
+      code.push(Instruction::CallBuiltin(builtin as u8));
+    } else {
+      let function = self
+        .function_indices
+        .get(name)
+        .ok_or_else(|| format!("Function {} not found", name))?;
+      code.push(Instruction::Call(*function));
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,532 @@
+// Compass bytecode (`.cbc`): a portable, serialisable form of an ETAC program that the stack based
+// VM in `vm` executes directly.
+//
+// Layout of a `.cbc` file, every integer is little endian:
+//
+//   magic        "CBC\0"
+//   version      u16
+//   constants    u32 count, then (u32 length, UTF-8 bytes) for each string
+//   globals      u32 count, then (u32 name constant, u8 type) for each variable
+//   functions    u32 count, then for each function:
+//                  u32 name constant, u8 return type,
+//                  u8 parameter count, (u32 name constant, u8 type) for each parameter,
+//                  u32 instruction count, instructions
+//   entry        u32 index of the function the program starts at
+//
+// Each instruction is an opcode byte followed by its operands.
+
+use crate::{
+  ast::{Condition, Operator, VarType},
+  runtime::Value,
+};
+
+pub mod compiler;
+pub mod vm;
+
+pub const MAGIC: &[u8; 4] = b"CBC\0";
+pub const VERSION: u16 = 1;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Instruction {
+  /// Pushes a constant. Strings are pushed with `PushStr` instead.
+  Push(Value),
+  /// Pushes a string of the constant pool.
+  PushStr(u32),
+  LoadGlobal(u32),
+  StoreGlobal(u32),
+  LoadLocal(u32),
+  StoreLocal(u32),
+  /// Pops the address and pushes the word stored there.
+  LoadIndirect,
+  /// Pops a value and an address, and stores the value at the address.
+  StoreIndirect,
+  /// `BinaryOperation::Arithmetic` of the given type over the two values on top of the stack.
+  Arithmetic(Operator, VarType),
+  /// `BinaryOperation::Conditional` over the two values on top of the stack, pushes a `bool`.
+  Compare(Condition),
+  Jump(u32),
+  /// `ConditionalJump`: pops a value and jumps when it is true.
+  JumpIf(u32),
+  /// Calls a function of the function table, its arguments are on top of the stack.
+  Call(u32),
+  /// Calls a builtin by its index in `runtime::BUILTINS`.
+  CallBuiltin(u8),
+  Pop,
+  /// Leaves the function, popping the return value first when it is not `void`.
+  Return,
+  Halt,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Global {
+  pub name: u32,
+  pub var_type: VarType,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionEntry {
+  pub name: u32,
+  pub return_type: VarType,
+  pub params: Vec<Global>,
+  pub code: Vec<Instruction>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Module {
+  pub constants: Vec<String>,
+  pub globals: Vec<Global>,
+  pub functions: Vec<FunctionEntry>,
+  pub entry: u32,
+}
+
+const VAR_TYPES: [VarType; 14] = [
+  VarType::I8,
+  VarType::I16,
+  VarType::I32,
+  VarType::I64,
+  VarType::U8,
+  VarType::U16,
+  VarType::U32,
+  VarType::U64,
+  VarType::Bool,
+  VarType::F32,
+  VarType::F64,
+  VarType::Str,
+  VarType::Void,
+  VarType::Ptr,
+];
+
+const OPERATORS: [Operator; 4] = [Operator::Add, Operator::Sub, Operator::Mul, Operator::Div];
+
+const CONDITIONS: [Condition; 8] = [
+  Condition::LessThan,
+  Condition::GreaterThan,
+  Condition::LessThanOrEqual,
+  Condition::GreaterThanOrEqual,
+  Condition::Equal,
+  Condition::NotEqual,
+  Condition::And,
+  Condition::Or,
+];
+
+mod opcode {
+  pub const PUSH: u8 = 0x01;
+  pub const PUSH_STR: u8 = 0x02;
+  pub const LOAD_GLOBAL: u8 = 0x03;
+  pub const STORE_GLOBAL: u8 = 0x04;
+  pub const LOAD_LOCAL: u8 = 0x05;
+  pub const STORE_LOCAL: u8 = 0x06;
+  pub const LOAD_INDIRECT: u8 = 0x07;
+  pub const STORE_INDIRECT: u8 = 0x08;
+  pub const ARITHMETIC: u8 = 0x10;
+  pub const COMPARE: u8 = 0x11;
+  pub const JUMP: u8 = 0x20;
+  pub const JUMP_IF: u8 = 0x21;
+  pub const CALL: u8 = 0x30;
+  pub const CALL_BUILTIN: u8 = 0x31;
+  pub const POP: u8 = 0x40;
+  pub const RETURN: u8 = 0x41;
+  pub const HALT: u8 = 0x42;
+}
+
+fn tag<T: PartialEq>(table: &[T], value: &T) -> u8 {
+  table
+    .iter()
+    .position(|candidate| candidate == value)
+    .expect("value missing from its tag table") as u8
+}
+
+impl Module {
+  pub fn constant(&self, index: u32) -> Result<&str, String> {
+    self
+      .constants
+      .get(index as usize)
+      .map(String::as_str)
+      .ok_or_else(|| format!("Constant {index} out of range"))
+  }
+
+  /// Encodes the module, failing on a function with more parameters than its `u8` count holds.
+  pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+    let mut writer = Writer::default();
+
+    writer.bytes.extend(MAGIC);
+    writer.u16(VERSION);
+
+    writer.u32(self.constants.len() as u32);
+    for constant in &self.constants {
+      writer.u32(constant.len() as u32);
+      writer.bytes.extend(constant.as_bytes());
+    }
+
+    writer.u32(self.globals.len() as u32);
+    for global in &self.globals {
+      writer.global(global);
+    }
+
+    writer.u32(self.functions.len() as u32);
+    for function in &self.functions {
+      writer.u32(function.name);
+      writer.u8(tag(&VAR_TYPES, &function.return_type));
+      let params = u8::try_from(function.params.len()).map_err(|_| {
+        format!(
+          "Function `{}` has {} parameters, bytecode functions take at most {}",
+          self.constant(function.name).unwrap_or("?"),
+          function.params.len(),
+          u8::MAX
+        )
+      })?;
+      writer.u8(params);
+      for param in &function.params {
+        writer.global(param);
+      }
+
+      writer.u32(function.code.len() as u32);
+      for instruction in &function.code {
+        writer.instruction(instruction);
+      }
+    }
+
+    writer.u32(self.entry);
+
+    Ok(writer.bytes)
+  }
+
+  pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+    let mut reader = Reader { bytes, position: 0 };
+
+    if reader.take(4)? != MAGIC {
+      return Err("Not a Compass bytecode file".to_string());
+    }
+
+    let version = reader.u16()?;
+    if version != VERSION {
+      return Err(format!(
+        "Unsupported bytecode version {version}, expected {VERSION}"
+      ));
+    }
+
+    let mut module = Module::default();
+
+    for _ in 0..reader.u32()? {
+      let length = reader.u32()? as usize;
+      let constant = String::from_utf8(reader.take(length)?.to_vec())
+        .map_err(|_| "Invalid UTF-8 in the constant pool".to_string())?;
+      module.constants.push(constant);
+    }
+
+    for _ in 0..reader.u32()? {
+      module.globals.push(reader.global()?);
+    }
+
+    for _ in 0..reader.u32()? {
+      let name = reader.u32()?;
+      let return_type = reader.var_type()?;
+
+      let mut params = vec![];
+      for _ in 0..reader.u8()? {
+        params.push(reader.global()?);
+      }
+
+      let mut code = vec![];
+      for _ in 0..reader.u32()? {
+        code.push(reader.instruction()?);
+      }
+
+      module.functions.push(FunctionEntry {
+        name,
+        return_type,
+        params,
+        code,
+      });
+    }
+
+    module.entry = reader.u32()?;
+
+    if reader.position != bytes.len() {
+      return Err("Trailing bytes after the end of the module".to_string());
+    }
+
+    Ok(module)
+  }
+}
+
+#[derive(Default)]
+struct Writer {
+  bytes: Vec<u8>,
+}
+
+impl Writer {
+  fn u8(&mut self, value: u8) {
+    self.bytes.push(value);
+  }
+
+  fn u16(&mut self, value: u16) {
+    self.bytes.extend(value.to_le_bytes());
+  }
+
+  fn u32(&mut self, value: u32) {
+    self.bytes.extend(value.to_le_bytes());
+  }
+
+  fn global(&mut self, global: &Global) {
+    self.u32(global.name);
+    self.u8(tag(&VAR_TYPES, &global.var_type));
+  }
+
+  fn value(&mut self, value: &Value) {
+    self.u8(tag(&VAR_TYPES, &value.var_type()));
+
+    match value {
+      Value::I8(value) => self.bytes.extend(value.to_le_bytes()),
+      Value::I16(value) => self.bytes.extend(value.to_le_bytes()),
+      Value::I32(value) => self.bytes.extend(value.to_le_bytes()),
+      Value::I64(value) => self.bytes.extend(value.to_le_bytes()),
+      Value::U8(value) => self.bytes.extend(value.to_le_bytes()),
+      Value::U16(value) => self.bytes.extend(value.to_le_bytes()),
+      Value::U32(value) => self.bytes.extend(value.to_le_bytes()),
+      Value::U64(value) => self.bytes.extend(value.to_le_bytes()),
+      Value::Bool(value) => self.u8(*value as u8),
+      Value::F32(value) => self.bytes.extend(value.to_le_bytes()),
+      Value::F64(value) => self.bytes.extend(value.to_le_bytes()),
+      Value::Ptr(value) => self.bytes.extend(value.to_le_bytes()),
+      Value::Void => {}
+      Value::Str(_) => unreachable!("strings live in the constant pool"),
+    }
+  }
+
+  fn instruction(&mut self, instruction: &Instruction) {
+    match instruction {
+      Instruction::Push(value) => {
+        self.u8(opcode::PUSH);
+        self.value(value);
+      }
+      Instruction::PushStr(index) => {
+        self.u8(opcode::PUSH_STR);
+        self.u32(*index);
+      }
+      Instruction::LoadGlobal(index) => {
+        self.u8(opcode::LOAD_GLOBAL);
+        self.u32(*index);
+      }
+      Instruction::StoreGlobal(index) => {
+        self.u8(opcode::STORE_GLOBAL);
+        self.u32(*index);
+      }
+      Instruction::LoadLocal(index) => {
+        self.u8(opcode::LOAD_LOCAL);
+        self.u32(*index);
+      }
+      Instruction::StoreLocal(index) => {
+        self.u8(opcode::STORE_LOCAL);
+        self.u32(*index);
+      }
+      Instruction::LoadIndirect => self.u8(opcode::LOAD_INDIRECT),
+      Instruction::StoreIndirect => self.u8(opcode::STORE_INDIRECT),
+      Instruction::Arithmetic(operator, var_type) => {
+        self.u8(opcode::ARITHMETIC);
+        self.u8(tag(&OPERATORS, operator));
+        self.u8(tag(&VAR_TYPES, var_type));
+      }
+      Instruction::Compare(condition) => {
+        self.u8(opcode::COMPARE);
+        self.u8(tag(&CONDITIONS, condition));
+      }
+      Instruction::Jump(target) => {
+        self.u8(opcode::JUMP);
+        self.u32(*target);
+      }
+      Instruction::JumpIf(target) => {
+        self.u8(opcode::JUMP_IF);
+        self.u32(*target);
+      }
+      Instruction::Call(function) => {
+        self.u8(opcode::CALL);
+        self.u32(*function);
+      }
+      Instruction::CallBuiltin(builtin) => {
+        self.u8(opcode::CALL_BUILTIN);
+        self.u8(*builtin);
+      }
+      Instruction::Pop => self.u8(opcode::POP),
+      Instruction::Return => self.u8(opcode::RETURN),
+      Instruction::Halt => self.u8(opcode::HALT),
+    }
+  }
+}
+
+struct Reader<'bytes> {
+  bytes: &'bytes [u8],
+  position: usize,
+}
+
+impl<'bytes> Reader<'bytes> {
+  fn take(&mut self, count: usize) -> Result<&'bytes [u8], String> {
+    let end = self.position + count;
+    if end > self.bytes.len() {
+      return Err("Unexpected end of the bytecode".to_string());
+    }
+
+    let bytes = &self.bytes[self.position..end];
+    self.position = end;
+    Ok(bytes)
+  }
+
+  fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+    Ok(self.take(N)?.try_into().unwrap())
+  }
+
+  fn u8(&mut self) -> Result<u8, String> {
+    Ok(self.take(1)?[0])
+  }
+
+  fn u16(&mut self) -> Result<u16, String> {
+    Ok(u16::from_le_bytes(self.array()?))
+  }
+
+  fn u32(&mut self) -> Result<u32, String> {
+    Ok(u32::from_le_bytes(self.array()?))
+  }
+
+  fn tagged<T: Clone>(&mut self, table: &[T], what: &str) -> Result<T, String> {
+    let tag = self.u8()?;
+    table
+      .get(tag as usize)
+      .cloned()
+      .ok_or_else(|| format!("Invalid {what} tag {tag}"))
+  }
+
+  fn var_type(&mut self) -> Result<VarType, String> {
+    self.tagged(&VAR_TYPES, "type")
+  }
+
+  fn global(&mut self) -> Result<Global, String> {
+    Ok(Global {
+      name: self.u32()?,
+      var_type: self.var_type()?,
+    })
+  }
+
+  fn value(&mut self) -> Result<Value, String> {
+    Ok(match self.var_type()? {
+      VarType::I8 => Value::I8(i8::from_le_bytes(self.array()?)),
+      VarType::I16 => Value::I16(i16::from_le_bytes(self.array()?)),
+      VarType::I32 => Value::I32(i32::from_le_bytes(self.array()?)),
+      VarType::I64 => Value::I64(i64::from_le_bytes(self.array()?)),
+      VarType::U8 => Value::U8(self.u8()?),
+      VarType::U16 => Value::U16(self.u16()?),
+      VarType::U32 => Value::U32(self.u32()?),
+      VarType::U64 => Value::U64(u64::from_le_bytes(self.array()?)),
+      VarType::Bool => Value::Bool(self.u8()? != 0),
+      VarType::F32 => Value::F32(f32::from_le_bytes(self.array()?)),
+      VarType::F64 => Value::F64(f64::from_le_bytes(self.array()?)),
+      VarType::Ptr => Value::Ptr(self.u32()?),
+      VarType::Void => Value::Void,
+      VarType::Str => return Err("String immediates must use the constant pool".to_string()),
+    })
+  }
+
+  fn instruction(&mut self) -> Result<Instruction, String> {
+    Ok(match self.u8()? {
+      opcode::PUSH => Instruction::Push(self.value()?),
+      opcode::PUSH_STR => Instruction::PushStr(self.u32()?),
+      opcode::LOAD_GLOBAL => Instruction::LoadGlobal(self.u32()?),
+      opcode::STORE_GLOBAL => Instruction::StoreGlobal(self.u32()?),
+      opcode::LOAD_LOCAL => Instruction::LoadLocal(self.u32()?),
+      opcode::STORE_LOCAL => Instruction::StoreLocal(self.u32()?),
+      opcode::LOAD_INDIRECT => Instruction::LoadIndirect,
+      opcode::STORE_INDIRECT => Instruction::StoreIndirect,
+      opcode::ARITHMETIC => {
+        Instruction::Arithmetic(self.tagged(&OPERATORS, "operator")?, self.var_type()?)
+      }
+      opcode::COMPARE => Instruction::Compare(self.tagged(&CONDITIONS, "condition")?),
+      opcode::JUMP => Instruction::Jump(self.u32()?),
+      opcode::JUMP_IF => Instruction::JumpIf(self.u32()?),
+      opcode::CALL => Instruction::Call(self.u32()?),
+      opcode::CALL_BUILTIN => Instruction::CallBuiltin(self.u8()?),
+      opcode::POP => Instruction::Pop,
+      opcode::RETURN => Instruction::Return,
+      opcode::HALT => Instruction::Halt,
+      opcode => return Err(format!("Invalid opcode {opcode:#04x}")),
+    })
+  }
+}
+
+impl std::fmt::Display for Module {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let name = |index: u32| self.constant(index).unwrap_or("<invalid>");
+
+    writeln!(f, "; compass bytecode v{VERSION}")?;
+    for (index, global) in self.globals.iter().enumerate() {
+      writeln!(
+        f,
+        ".global {index} {}: {}",
+        name(global.name),
+        global.var_type
+      )?;
+    }
+
+    for (index, function) in self.functions.iter().enumerate() {
+      let params: Vec<String> = function
+        .params
+        .iter()
+        .map(|param| format!("{}: {}", name(param.name), param.var_type))
+        .collect();
+      let entry = if index as u32 == self.entry {
+        " ; entry"
+      } else {
+        ""
+      };
+
+      writeln!(
+        f,
+        "\n.func {index} {}({}): {}{entry}",
+        name(function.name),
+        params.join(", "),
+        function.return_type
+      )?;
+
+      for (pc, instruction) in function.code.iter().enumerate() {
+        let text = match instruction {
+          Instruction::Push(value) => format!("push {}:{}", value, value.var_type()),
+          Instruction::PushStr(index) => format!("push.str {:?}", name(*index)),
+          Instruction::LoadGlobal(index) => format!("load.global {index}"),
+          Instruction::StoreGlobal(index) => format!("store.global {index}"),
+          Instruction::LoadLocal(index) => format!("load.local {index}"),
+          Instruction::StoreLocal(index) => format!("store.local {index}"),
+          Instruction::LoadIndirect => "load.indirect".to_string(),
+          Instruction::StoreIndirect => "store.indirect".to_string(),
+          Instruction::Arithmetic(operator, var_type) => format!("arith {operator} {var_type}"),
+          Instruction::Compare(condition) => format!(
+            "cmp {}",
+            match condition {
+              Condition::LessThan => "<",
+              Condition::GreaterThan => ">",
+              Condition::LessThanOrEqual => "<=",
+              Condition::GreaterThanOrEqual => ">=",
+              Condition::Equal => "==",
+              Condition::NotEqual => "!=",
+              Condition::And => "&&",
+              Condition::Or => "||",
+            }
+          ),
+          Instruction::Jump(target) => format!("jump {target}"),
+          Instruction::JumpIf(target) => format!("jump.if {target}"),
+          Instruction::Call(function) => format!("call {function}"),
+          Instruction::CallBuiltin(builtin) => format!(
+            "call.builtin {}",
+            crate::runtime::BUILTINS
+              .get(*builtin as usize)
+              .unwrap_or(&"<invalid>")
+          ),
+          Instruction::Pop => "pop".to_string(),
+          Instruction::Return => "return".to_string(),
+          Instruction::Halt => "halt".to_string(),
+        };
+
+        writeln!(f, "  {pc:>4}  {text}")?;
+      }
+    }
+
+    Ok(())
+  }
+}
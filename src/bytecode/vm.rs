@@ -0,0 +1,199 @@
+// Stack based virtual machine that executes a bytecode `Module`.
+
+use crate::runtime::{call_builtin, Io, Memory, RuntimeError, Value, BUILTINS};
+
+use super::{FunctionEntry, Instruction, Module};
+
+struct Frame {
+  function: usize,
+  pc: usize,
+  locals: Vec<Value>,
+}
+
+pub struct Vm<'module> {
+  module: &'module Module,
+  pub globals: Vec<Value>,
+  pub memory: Memory,
+  stack: Vec<Value>,
+  frames: Vec<Frame>,
+}
+
+fn invalid(message: impl Into<String>) -> RuntimeError {
+  RuntimeError::InvalidOperation(message.into())
+}
+
+impl<'module> Vm<'module> {
+  pub fn new(module: &'module Module) -> Self {
+    Self {
+      module,
+      globals: module
+        .globals
+        .iter()
+        .map(|global| Value::zero(global.var_type))
+        .collect(),
+      memory: Memory::default(),
+      stack: vec![],
+      frames: vec![],
+    }
+  }
+
+  fn function(&self, index: usize) -> Result<&'module FunctionEntry, RuntimeError> {
+    self
+      .module
+      .functions
+      .get(index)
+      .ok_or_else(|| invalid(format!("function {index} out of range")))
+  }
+
+  fn pop(&mut self) -> Result<Value, RuntimeError> {
+    self.stack.pop().ok_or_else(|| invalid("stack underflow"))
+  }
+
+  fn pop_args(&mut self, count: usize) -> Result<Vec<Value>, RuntimeError> {
+    if self.stack.len() < count {
+      return Err(invalid("stack underflow"));
+    }
+
+    Ok(self.stack.split_off(self.stack.len() - count))
+  }
+
+  fn global(&mut self, index: u32) -> Result<&mut Value, RuntimeError> {
+    self
+      .globals
+      .get_mut(index as usize)
+      .ok_or_else(|| invalid(format!("global {index} out of range")))
+  }
+
+  fn local(&mut self, index: u32) -> Result<&mut Value, RuntimeError> {
+    self
+      .frames
+      .last_mut()
+      .and_then(|frame| frame.locals.get_mut(index as usize))
+      .ok_or_else(|| invalid(format!("local {index} out of range")))
+  }
+
+  fn jump(&mut self, target: u32) {
+    if let Some(frame) = self.frames.last_mut() {
+      frame.pc = target as usize;
+    }
+  }
+
+  /// Runs the entry function until it halts.
+  pub fn run(&mut self, io: &mut Io) -> Result<(), RuntimeError> {
+    self.frames.push(Frame {
+      function: self.module.entry as usize,
+      pc: 0,
+      locals: vec![],
+    });
+
+    while let Some(frame) = self.frames.last_mut() {
+      let (index, pc) = (frame.function, frame.pc);
+      frame.pc += 1;
+
+      let function = self.function(index)?;
+      let instruction = function
+        .code
+        .get(pc)
+        .ok_or_else(|| invalid(format!("fell off the end of function {index}")))?;
+
+      match instruction {
+        Instruction::Push(value) => self.stack.push(value.clone()),
+        Instruction::PushStr(index) => {
+          let value = self.module.constant(*index).map_err(invalid)?;
+          self.stack.push(Value::Str(value.to_string()));
+        }
+        Instruction::LoadGlobal(index) => {
+          let value = self.global(*index)?.clone();
+          self.stack.push(value);
+        }
+        Instruction::StoreGlobal(index) => {
+          let value = self.pop()?;
+          let slot = self.global(*index)?;
+          *slot = value.cast(slot.var_type());
+        }
+        Instruction::LoadLocal(index) => {
+          let value = self.local(*index)?.clone();
+          self.stack.push(value);
+        }
+        Instruction::StoreLocal(index) => {
+          let value = self.pop()?;
+          let slot = self.local(*index)?;
+          *slot = value.cast(slot.var_type());
+        }
+        Instruction::LoadIndirect => {
+          let address = self.pop()?.as_address()?;
+          self.stack.push(self.memory.load(address)?);
+        }
+        Instruction::StoreIndirect => {
+          let value = self.pop()?;
+          let address = self.pop()?.as_address()?;
+          self.memory.store(address, value)?;
+        }
+        Instruction::Arithmetic(operator, var_type) => {
+          let rhs = self.pop()?;
+          let lhs = self.pop()?;
+          self
+            .stack
+            .push(Value::arithmetic(operator, *var_type, &lhs, &rhs)?);
+        }
+        Instruction::Compare(condition) => {
+          let rhs = self.pop()?;
+          let lhs = self.pop()?;
+          self.stack.push(Value::compare(condition, &lhs, &rhs)?);
+        }
+        Instruction::Jump(target) => self.jump(*target),
+        Instruction::JumpIf(target) => {
+          if self.pop()?.is_truthy() {
+            self.jump(*target);
+          }
+        }
+        Instruction::Call(index) => {
+          let callee = self.function(*index as usize)?;
+          let args = self.pop_args(callee.params.len())?;
+          let locals = args
+            .iter()
+            .zip(&callee.params)
+            .map(|(arg, param)| arg.cast(param.var_type))
+            .collect();
+
+          self.frames.push(Frame {
+            function: *index as usize,
+            pc: 0,
+            locals,
+          });
+        }
+        Instruction::CallBuiltin(index) => {
+          let name = BUILTINS
+            .get(*index as usize)
+            .ok_or_else(|| invalid(format!("builtin {index} out of range")))?;
+          // `read_int` is the only builtin without an argument
+          let arity = match *name {
+            "read_int" => 0,
+            _ => 1,
+          };
+
+          let args = self.pop_args(arity)?;
+          let value = call_builtin(name, &args, io)?;
+          if value != Value::Void {
+            self.stack.push(value);
+          }
+        }
+        Instruction::Pop => {
+          self.pop()?;
+        }
+        Instruction::Return => {
+          let return_type = function.return_type;
+          self.frames.pop();
+
+          if return_type != crate::ast::VarType::Void {
+            let value = self.pop()?.cast(return_type);
+            self.stack.push(value);
+          }
+        }
+        Instruction::Halt => return Ok(()),
+      }
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,186 @@
+use std::path::{Path, PathBuf};
+
+use clap::{Args, ValueEnum};
+
+use crate::codegen::{
+  mips::{
+    assembler::{Branches, Endianness, Layout},
+    elf,
+    image::{self, ImageFormat},
+  },
+  target, Artifact,
+};
+
+use super::emit::{generate, target_parser, LintOptions, MessageFormat};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+  /// ELF32 relocatable object, for the `mips` target
+  Elf,
+  /// Memory images for the RAM and ROM components of Logisim
+  Logisim,
+  /// Memory images for Verilog's `$readmemh`
+  Readmemh,
+  /// Memory images as Memory Initialization Files, for Quartus
+  Mif,
+  /// Memory images as raw words
+  Bin,
+}
+
+impl Format {
+  fn extension(&self) -> &'static str {
+    match self {
+      Format::Elf => "o",
+      Format::Logisim => "img",
+      Format::Readmemh => "hex",
+      Format::Mif => "mif",
+      Format::Bin => "bin",
+    }
+  }
+
+  fn image(&self) -> Option<ImageFormat> {
+    match self {
+      Format::Elf => None,
+      Format::Logisim => Some(ImageFormat::Logisim),
+      Format::Readmemh => Some(ImageFormat::Readmemh),
+      Format::Mif => Some(ImageFormat::Mif),
+      Format::Bin => Some(ImageFormat::Bin),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum Endian {
+  #[default]
+  Big,
+  Little,
+}
+
+impl From<Endian> for Endianness {
+  fn from(endian: Endian) -> Self {
+    match endian {
+      Endian::Big => Endianness::Big,
+      Endian::Little => Endianness::Little,
+    }
+  }
+}
+
+#[derive(Args)]
+pub struct BuildOptions {
+  /// The ETAC file to compile
+  #[arg(short = 'f')]
+  pub filepath: String,
+
+  /// The backend to generate code with
+  #[arg(short, long, default_value = "mips", value_parser = target_parser())]
+  pub target: String,
+
+  /// Assemble the generated code into this format instead of writing it as is
+  #[arg(long, value_enum)]
+  pub format: Option<Format>,
+
+  /// Byte order of the assembled output
+  #[arg(long, value_enum, default_value_t)]
+  pub endian: Endian,
+
+  /// Address of the instruction memory, for memory images
+  #[arg(long, value_parser = parse_address, default_value = "0x00400000")]
+  pub text_base: u32,
+
+  /// Address of the data memory, for memory images
+  #[arg(long, value_parser = parse_address, default_value = "0x10010000")]
+  pub data_base: u32,
+
+  /// Put a `nop` in the delay slot of each branch and jump of memory images, for a pipelined CPU
+  /// that runs it before the branch is taken. ELF objects always have them
+  #[arg(long)]
+  pub delay_slots: bool,
+
+  /// Where to write the output, defaults to the input file with the extension of the output.
+  /// Memory images are written next to it, as `<output>.text.<ext>` and `<output>.data.<ext>`
+  #[arg(short, long)]
+  pub output: Option<String>,
+
+  #[command(flatten)]
+  pub lints: LintOptions,
+
+  /// How to report errors
+  #[arg(long, value_enum, default_value_t)]
+  pub message_format: MessageFormat,
+}
+
+fn parse_address(value: &str) -> Result<u32, String> {
+  let parsed = match value.strip_prefix("0x") {
+    Some(hex) => u32::from_str_radix(hex, 16),
+    None => value.parse(),
+  };
+  let address = parsed.map_err(|error| format!("Invalid address `{value}`: {error}"))?;
+
+  if !address.is_multiple_of(4) {
+    return Err(format!("Address `{value}` is not aligned to a word"));
+  }
+  Ok(address)
+}
+
+/// `prog.hex` becomes `prog.text.hex` for the image of `.text`.
+fn image_path(output: &Path, section: &str) -> PathBuf {
+  let extension = output.extension().unwrap_or_default().to_string_lossy();
+  output.with_extension(format!("{section}.{extension}"))
+}
+
+pub fn build(
+  BuildOptions {
+    filepath,
+    target,
+    format,
+    endian,
+    text_base,
+    data_base,
+    delay_slots,
+    output,
+    lints,
+    message_format,
+  }: &BuildOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let artifact = generate(filepath, target, lints, *message_format)?;
+
+  let output: PathBuf = match output {
+    Some(output) => output.into(),
+    None => {
+      let extension = match format {
+        Some(format) => format.extension(),
+        None => target::find(target)
+          .map(|target| target.info().extension)
+          .unwrap_or_default(),
+      };
+      Path::new(filepath).with_extension(extension)
+    }
+  };
+
+  let program = match (format, &artifact) {
+    (None, artifact) => return Ok(std::fs::write(output, artifact.to_bytes()?)?),
+    (Some(_), Artifact::Mips(program)) => program,
+    (Some(_), _) => Err(format!(
+      "Only the output of the `mips` target can be assembled, not `{target}`"
+    ))?,
+  };
+
+  match format.and_then(|format| format.image()) {
+    None => std::fs::write(output, elf::object(program, (*endian).into())?)?,
+    Some(format) => {
+      let layout = Layout {
+        text_base: *text_base,
+        data_base: *data_base,
+      };
+      let branches = match delay_slots {
+        true => Branches::Delayed,
+        false => Branches::Immediate,
+      };
+      let images = image::images(program, layout, format, (*endian).into(), branches)?;
+      std::fs::write(image_path(&output, "text"), images.text)?;
+      std::fs::write(image_path(&output, "data"), images.data)?;
+    }
+  }
+
+  Ok(())
+}
@@ -0,0 +1,48 @@
+use clap::Args;
+
+use crate::debugger::debug;
+
+#[derive(Args)]
+pub struct DebugOptions {
+  /// The ETAC file to debug
+  #[arg(required_unless_present = "file", conflicts_with = "file")]
+  pub filepath: Option<String>,
+
+  /// The ETAC file to debug, as `-f` like the other commands take it
+  #[arg(short = 'f', value_name = "FILEPATH")]
+  pub file: Option<String>,
+
+  /// A file the program reads from, as the commands are read from stdin
+  #[arg(long)]
+  pub input: Option<String>,
+}
+
+/// Runs the file under the debugger, until it ends or `quit` is typed.
+pub fn run(
+  DebugOptions {
+    filepath,
+    file,
+    input,
+  }: &DebugOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let filepath = filepath
+    .as_ref()
+    .or(file.as_ref())
+    .ok_or("A file to debug is needed")?;
+  let source = std::fs::read_to_string(filepath)?;
+  let input = match input {
+    Some(input) => std::fs::read_to_string(input)?,
+    None => String::new(),
+  };
+
+  let stdin = std::io::stdin();
+  debug(
+    &source,
+    &mut stdin.lock(),
+    &mut input.as_bytes(),
+    &mut std::io::stdout().lock(),
+  )
+  .map_err(|error| format!("{filepath}:\n{error}"))?;
+
+  Ok(())
+}
@@ -1,6 +1,90 @@
-use clap::Args;
+use std::io::Write;
 
-use crate::{ast::Statement, lexer::Lexer, parser::Parser};
+use clap::{
+  builder::{PossibleValue, PossibleValuesParser},
+  Args, ValueEnum,
+};
+
+use crate::{
+  ast::Statement,
+  codegen::{error::CodegenError, target::TARGETS, Artifact},
+  compiler::{self, CompileOptions, CompileOutput},
+  diagnostics::{self, codes, Diagnostic, Format, Severity},
+  lexer::Lexer,
+  lints::{self, Level, Levels},
+};
+
+/// The `--target` values, one per backend of the registry.
+pub fn target_parser() -> PossibleValuesParser {
+  PossibleValuesParser::new(
+    TARGETS
+      .iter()
+      .map(|target| PossibleValue::new(target.info().name).help(target.info().description)),
+  )
+}
+
+#[derive(Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum MessageFormat {
+  /// Reports for people, on stderr
+  #[default]
+  Human,
+  /// One JSON object per diagnostic and line, on stdout
+  Json,
+  /// A SARIF 2.1.0 log, on stdout
+  Sarif,
+}
+
+impl MessageFormat {
+  fn format(self) -> Option<Format> {
+    match self {
+      MessageFormat::Human => None,
+      MessageFormat::Json => Some(Format::Json),
+      MessageFormat::Sarif => Some(Format::Sarif),
+    }
+  }
+}
+
+/// The `-W`, `-A` and `-D` values, a lint or the `warnings` group.
+pub fn lint_parser() -> PossibleValuesParser {
+  PossibleValuesParser::new(
+    lints::LINTS
+      .iter()
+      .map(|lint| PossibleValue::new(lint.name).help(lint.description))
+      .chain([PossibleValue::new(lints::WARNINGS).help("Every lint")]),
+  )
+}
+
+#[derive(Args, Default)]
+pub struct LintOptions {
+  /// Report a lint as a warning
+  #[arg(short = 'W', long = "warn", value_name = "LINT", value_parser = lint_parser())]
+  pub warn: Vec<String>,
+
+  /// Do not report a lint
+  #[arg(short = 'A', long = "allow", value_name = "LINT", value_parser = lint_parser())]
+  pub allow: Vec<String>,
+
+  /// Report a lint as an error, which stops the compilation. `-D warnings` denies every lint
+  #[arg(short = 'D', long = "deny", value_name = "LINT", value_parser = lint_parser())]
+  pub deny: Vec<String>,
+}
+
+impl LintOptions {
+  /// The levels of the lints, the allowed ones first, then the warned and the denied ones.
+  pub fn levels(&self) -> Result<Levels, String> {
+    let mut levels = Levels::default();
+    for (names, level) in [
+      (&self.allow, Level::Allow),
+      (&self.warn, Level::Warn),
+      (&self.deny, Level::Deny),
+    ] {
+      for name in names {
+        levels.set(name, level)?;
+      }
+    }
+    Ok(levels)
+  }
+}
 
 #[derive(Args)]
 pub struct EmitASTOptions {
@@ -8,26 +92,168 @@ pub struct EmitASTOptions {
   #[arg(short = 'f')]
   pub filepath: String,
 
+  /// The backend to generate code with
+  #[arg(short, long, default_value = "mips", value_parser = target_parser())]
+  pub target: String,
+
   /// Turn debugging information on
   #[arg(short, long, action = clap::ArgAction::Count)]
-  debug: u8,
+  pub debug: u8,
+
+  #[command(flatten)]
+  pub lints: LintOptions,
+
+  /// How to report errors
+  #[arg(long, value_enum, default_value_t)]
+  pub message_format: MessageFormat,
 }
 
-pub fn ast(
-  EmitASTOptions { filepath, debug }: &EmitASTOptions,
+/// Prints the code generated for the file, after its tokens and AST when debugging.
+pub fn run(
+  EmitASTOptions {
+    filepath,
+    target,
+    debug,
+    lints,
+    message_format,
+  }: &EmitASTOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let options = CompileOptions {
+    lints: lints.levels()?,
+    ..CompileOptions::target(target)
+  };
+  let output = compile_file(filepath, &options, *message_format)?;
+
+  if *debug > 0 {
+    let source_code = std::fs::read_to_string(filepath)?;
+    println!("{}", Lexer::unchecked(&source_code, filepath));
+    println!("{:#?}", output.ast);
+  }
+
+  if let Some(artifact) = output.artifact {
+    println!("{artifact}");
+  }
+  Ok(())
+}
+
+/// Parses, type checks and lints the file, reporting its diagnostics in the message format.
+pub fn parse_file(
+  filepath: &str,
+  lints: &LintOptions,
+  message_format: MessageFormat,
 ) -> Result<Vec<Statement>, Box<dyn std::error::Error>> {
+  let options = CompileOptions {
+    lints: lints.levels()?,
+    ..Default::default()
+  };
+  let output = compile_file(filepath, &options, message_format)?;
+  Ok(output.ast.unwrap_or_default())
+}
+
+/// Generates the code of the file for a target, reporting its diagnostics in the message format.
+pub fn generate(
+  filepath: &str,
+  target: &str,
+  lints: &LintOptions,
+  message_format: MessageFormat,
+) -> Result<Artifact, Box<dyn std::error::Error>> {
+  let options = CompileOptions {
+    lints: lints.levels()?,
+    ..CompileOptions::target(target)
+  };
+  let output = compile_file(filepath, &options, message_format)?;
+  Ok(output.artifact.ok_or("No code was generated")?)
+}
+
+/// Compiles the file, reporting its diagnostics in the message format, and fails when there is an
+/// error among them.
+pub fn compile_file(
+  filepath: &str,
+  options: &CompileOptions,
+  message_format: MessageFormat,
+) -> Result<CompileOutput, Box<dyn std::error::Error>> {
   let source_code = std::fs::read_to_string(filepath)?;
-  let lexer = Lexer::new(&source_code[..], filepath).map_err(|e| e.to_string())?;
+  let output = compiler::compile(&source_code, filepath, options);
+  report(message_format, &output, &source_code, filepath);
+  Ok(output)
+}
 
-  if *debug > 0 {
-    println!("{}", lexer);
+/// Reports an error in the code generated for the file, like one the assembler finds, as a
+/// diagnostic of the file in the message format, and exits with a failure status.
+pub fn report_generated(
+  filepath: &str,
+  error: String,
+  message_format: MessageFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let source_code = std::fs::read_to_string(filepath)?;
+  let output = CompileOutput {
+    ast: None,
+    artifact: None,
+    diagnostics: vec![Diagnostic::from(&CodegenError::from(error))],
+  };
+  report(message_format, &output, &source_code, filepath);
+
+  Ok(())
+}
+
+// Prints the diagnostics, as reports on stderr or in a format on stdout, and exits with a failure
+// status when there is an error among them
+fn report(
+  message_format: MessageFormat,
+  output: &CompileOutput,
+  source_code: &str,
+  filepath: &str,
+) {
+  use ariadne::{Color, Fmt};
+
+  if output.diagnostics.is_empty() {
+    return;
   }
 
-  let ast = Parser::new().parse(lexer)?;
+  match message_format.format() {
+    Some(format) => print!(
+      "{}",
+      diagnostics::render(format, &output.diagnostics, source_code, filepath)
+    ),
+    None => {
+      let filename = filepath.split('/').next_back().unwrap_or(filepath);
+      for diagnostic in &output.diagnostics {
+        diagnostics::print(diagnostic, filename, source_code);
+      }
+    }
+  }
 
-  if *debug > 0 {
-    println!("{:#?}", ast);
+  let plural = |count: usize, what: &str| match count {
+    1 => format!("1 {what}"),
+    count => format!("{count} {what}s"),
+  };
+  let errors = output.errors();
+  let warnings = output.diagnostics.len() - errors;
+
+  if errors == 0 {
+    if message_format == MessageFormat::Human && warnings > 0 {
+      eprintln!("{}", plural(warnings, "warning").fg(Color::Yellow));
+    }
+    return;
+  }
+
+  if message_format == MessageFormat::Human {
+    eprintln!("{}", plural(errors, "error").fg(Color::Red));
+    // Denied lints are errors too, but only the error codes are explained
+    let explained = output.diagnostics.iter().any(|diagnostic| {
+      diagnostic.severity == Severity::Error
+        && diagnostic
+          .code
+          .as_deref()
+          .and_then(codes::explain)
+          .is_some()
+    });
+    if explained {
+      eprintln!("For more information about an error, try `compass explain <code>`.");
+    }
   }
 
-  Ok(ast)
+  // The diagnostics were already printed
+  let _ = std::io::stdout().flush();
+  std::process::exit(1);
 }
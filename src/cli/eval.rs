@@ -0,0 +1,118 @@
+use std::io::Write;
+
+use clap::{Args, ValueEnum};
+
+use crate::{
+  coverage::Coverage,
+  interpreter::{Interpreter, Observer},
+  profiler::Profiler,
+  runtime::Io,
+  tracer::{TraceFormat, Tracer},
+};
+
+use super::emit::{parse_file, LintOptions, MessageFormat};
+
+#[derive(Args)]
+pub struct EvalOptions {
+  /// The ETAC file to run
+  #[arg(short = 'f')]
+  pub filepath: String,
+
+  /// Writes each statement to stderr before it runs, with the variables it reads
+  #[arg(long)]
+  pub trace: bool,
+
+  #[arg(long, value_enum, default_value_t = Trace::Text)]
+  pub trace_format: Trace,
+
+  /// Writes how many times each function, label and basic block ran to stderr
+  #[arg(long, value_enum)]
+  pub profile: Option<Profile>,
+
+  /// Writes which lines, functions and branches ran to this file, as an LCOV tracefile
+  #[arg(long)]
+  pub coverage: Option<String>,
+
+  #[command(flatten)]
+  pub lints: LintOptions,
+
+  /// How to report errors
+  #[arg(long, value_enum, default_value_t)]
+  pub message_format: MessageFormat,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Trace {
+  /// One line per statement
+  Text,
+  /// One JSON object per line
+  Json,
+}
+
+impl From<Trace> for TraceFormat {
+  fn from(trace: Trace) -> Self {
+    match trace {
+      Trace::Text => TraceFormat::Text,
+      Trace::Json => TraceFormat::Json,
+    }
+  }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Profile {
+  /// A table, most run first
+  Table,
+  /// Folded stacks, for flamegraph tools
+  Folded,
+}
+
+/// Runs the file with the reference interpreter.
+pub fn run(
+  EvalOptions {
+    filepath,
+    trace,
+    trace_format,
+    profile,
+    coverage,
+    lints,
+    message_format,
+  }: &EvalOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let ast = parse_file(filepath, lints, *message_format)?;
+  let source = std::fs::read_to_string(filepath)?;
+
+  let mut stderr = std::io::BufWriter::new(std::io::stderr().lock());
+  let mut tracer = Tracer::new(&source, &mut stderr, (*trace_format).into());
+  let mut profiler = Profiler::new(&source, &ast);
+  let mut observers: Vec<&mut dyn Observer> = vec![];
+  if *trace {
+    observers.push(&mut tracer);
+  }
+  if profile.is_some() {
+    observers.push(&mut profiler);
+  }
+  let mut lcov = Coverage::new(&ast);
+  if coverage.is_some() {
+    observers.push(&mut lcov);
+  }
+
+  let mut input = std::io::stdin().lock();
+  let mut output = std::io::stdout().lock();
+  // The profile and coverage of a run that failed, like one that never ends, is what tells why
+  let result =
+    Interpreter::new(&ast).run_observed(&mut Io::new(&mut input, &mut output), &mut observers);
+  output.flush()?;
+
+  match profile {
+    Some(Profile::Table) => write!(stderr, "{}", profiler.table())?,
+    Some(Profile::Folded) => write!(stderr, "{}", profiler.folded())?,
+    None => {}
+  }
+  stderr.flush()?;
+  if let Some(coverage) = coverage {
+    std::fs::write(coverage, lcov.lcov(&source, filepath))?;
+  }
+  result?;
+
+  Ok(())
+}
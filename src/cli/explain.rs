@@ -0,0 +1,17 @@
+use clap::Args;
+
+use crate::diagnostics::codes::explain;
+
+#[derive(Args)]
+pub struct ExplainOptions {
+  /// The code of the error, like `E0004`
+  pub code: String,
+}
+
+/// Prints the explanation of an error code.
+pub fn run(ExplainOptions { code }: &ExplainOptions) -> Result<(), Box<dyn std::error::Error>> {
+  let explanation = explain(code).ok_or_else(|| format!("`{code}` is not a valid error code"))?;
+
+  print!("{explanation}");
+  Ok(())
+}
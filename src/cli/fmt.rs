@@ -0,0 +1,45 @@
+use clap::Args;
+
+use crate::formatter::format;
+
+#[derive(Args)]
+pub struct FmtOptions {
+  /// The ETAC files to format, in place
+  #[arg(short = 'f', required = true)]
+  pub filepaths: Vec<String>,
+
+  /// Only report the files that are not formatted, and fail if there is any
+  #[arg(long)]
+  pub check: bool,
+}
+
+/// Formats the files in place, or with `--check` lists the ones that would change.
+pub fn run(FmtOptions { filepaths, check }: &FmtOptions) -> Result<(), Box<dyn std::error::Error>> {
+  let mut unformatted = 0;
+
+  for filepath in filepaths {
+    let source = std::fs::read_to_string(filepath)?;
+    let formatted = format(&source).map_err(|error| format!("{filepath}: {error}"))?;
+
+    if formatted == source {
+      continue;
+    }
+
+    if *check {
+      eprintln!("Would reformat {filepath}");
+      unformatted += 1;
+    } else {
+      std::fs::write(filepath, formatted)?;
+    }
+  }
+
+  if unformatted > 0 {
+    eprintln!(
+      "{unformatted} of {} files would be reformatted",
+      filepaths.len()
+    );
+    std::process::exit(1);
+  }
+
+  Ok(())
+}
@@ -0,0 +1,67 @@
+use clap::Args;
+
+use crate::{
+  codegen::target,
+  generator::{generate_for, Config, Generator, ATTEMPTS},
+};
+
+use super::emit::target_parser;
+
+#[derive(Args)]
+pub struct GenOptions {
+  /// The same seed always generates the same program
+  #[arg(long, default_value_t = 0)]
+  pub seed: u64,
+
+  /// Only generate programs this backend compiles, from the first seed that gives one
+  #[arg(short, long, value_parser = target_parser())]
+  pub target: Option<String>,
+
+  /// Statements of the top level and of each function
+  #[arg(long, default_value_t = Config::default().statements)]
+  pub statements: usize,
+
+  /// User functions to define
+  #[arg(long, default_value_t = Config::default().functions)]
+  pub functions: usize,
+}
+
+/// Prints a random, well-typed program, headed by the bound of the statements it runs.
+pub fn run(
+  GenOptions {
+    seed,
+    target,
+    statements,
+    functions,
+  }: &GenOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let (seed, program) = match target {
+    Some(name) => {
+      let target = target::find(name).ok_or_else(|| format!("Unknown target `{name}`"))?;
+      let config = Config {
+        statements: *statements,
+        functions: *functions,
+        ..Config::for_target(target.info())
+      };
+      generate_for(target, *seed, &config).ok_or_else(|| {
+        format!("None of the {ATTEMPTS} seeds from {seed} generated a program for `{name}`")
+      })?
+    }
+    None => {
+      let config = Config {
+        statements: *statements,
+        functions: *functions,
+        ..Config::default()
+      };
+      (*seed, Generator::new(*seed, config).program())
+    }
+  };
+
+  println!(
+    "# compass gen --seed {seed}, runs at most {} statements",
+    program.max_steps
+  );
+  print!("{}", program.source);
+
+  Ok(())
+}
@@ -0,0 +1,12 @@
+use clap::Args;
+
+#[derive(Args)]
+pub struct LspOptions {}
+
+/// Serves the editor on stdin and stdout until it asks to exit.
+pub fn run(_: &LspOptions) -> Result<(), Box<dyn std::error::Error>> {
+  let stdin = std::io::stdin();
+  crate::lsp::serve(&mut stdin.lock(), &mut std::io::stdout().lock())?;
+
+  Ok(())
+}
@@ -1,5 +1,15 @@
+pub mod build;
+pub mod debug;
 pub mod emit;
 pub mod eval;
+pub mod explain;
+pub mod fmt;
+pub mod gen;
+pub mod lsp;
+pub mod repl;
+pub mod sim;
+pub mod test;
+pub mod vm;
 
 use clap::{Parser, Subcommand};
 
@@ -14,4 +24,26 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Commands {
   Emit(emit::EmitASTOptions),
+  /// Compiles an ETAC file and writes the result next to it
+  Build(build::BuildOptions),
+  /// Runs an ETAC file under a source-level debugger
+  Debug(debug::DebugOptions),
+  /// Runs an ETAC file with the reference interpreter
+  Eval(eval::EvalOptions),
+  /// Prints a detailed explanation of an error code
+  Explain(explain::ExplainOptions),
+  /// Formats ETAC files, keeping their comments
+  Fmt(fmt::FmtOptions),
+  /// Prints a random, well-typed ETAC program for fuzzing
+  Gen(gen::GenOptions),
+  /// Runs a Language Server Protocol server over stdio
+  Lsp(lsp::LspOptions),
+  /// Starts an interactive session, which runs ETAC as it is typed
+  Repl(repl::ReplOptions),
+  /// Runs a Compass bytecode file
+  Vm(vm::VmOptions),
+  /// Compiles an ETAC file for the `mips` target and runs it in a simulator
+  Sim(sim::SimOptions),
+  /// Runs the ETAC files of a directory, checking the expectations in their comments
+  Test(test::TestOptions),
 }
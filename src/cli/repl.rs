@@ -0,0 +1,12 @@
+use clap::Args;
+
+#[derive(Args)]
+pub struct ReplOptions {}
+
+/// Reads ETAC from stdin and prints the values and types of each input.
+pub fn run(_: &ReplOptions) -> Result<(), Box<dyn std::error::Error>> {
+  let stdin = std::io::stdin();
+  crate::repl::run(&mut stdin.lock(), &mut std::io::stdout().lock())?;
+
+  Ok(())
+}
@@ -0,0 +1,73 @@
+use std::io::Write;
+
+use clap::Args;
+
+use crate::{
+  codegen::{
+    mips::{
+      assembler::{assemble, Layout},
+      simulator::{Exit, Simulator, DEFAULT_MAX_STEPS},
+    },
+    Artifact,
+  },
+  runtime::Io,
+};
+
+use super::emit::{generate, report_generated, LintOptions, MessageFormat};
+
+#[derive(Args)]
+pub struct SimOptions {
+  /// The ETAC file to compile and run
+  #[arg(short = 'f')]
+  pub filepath: String,
+
+  /// Stop the program with a fault after this many instructions
+  #[arg(long, default_value_t = DEFAULT_MAX_STEPS)]
+  pub max_steps: u64,
+
+  #[command(flatten)]
+  pub lints: LintOptions,
+
+  /// How to report errors
+  #[arg(long, value_enum, default_value_t)]
+  pub message_format: MessageFormat,
+}
+
+/// Compiles the file for the `mips` target and runs it in the simulator. The report goes to
+/// stderr, and the process exits with the status of the program, or 1 when it faults.
+pub fn run(
+  SimOptions {
+    filepath,
+    max_steps,
+    lints,
+    message_format,
+  }: &SimOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let Artifact::Mips(program) = generate(filepath, "mips", lints, *message_format)? else {
+    unreachable!("the `mips` target generates MIPS programs")
+  };
+
+  // The generated code should always assemble, when it does not it is reported like the other errors
+  let loaded = assemble(&program, Layout::MARS).and_then(|assembly| Simulator::new(&assembly));
+  let mut simulator = match loaded {
+    Ok(simulator) => simulator,
+    Err(error) => return report_generated(filepath, error, *message_format),
+  };
+  simulator.max_steps = *max_steps;
+
+  let report = {
+    let mut input = std::io::stdin().lock();
+    let mut output = std::io::stdout().lock();
+    let report = simulator.run(&mut Io::new(&mut input, &mut output));
+    output.flush()?;
+    report
+  };
+
+  // Only what the program printed goes to stdout, so it can be piped or compared
+  writeln!(std::io::stderr().lock(), "{report}")?;
+  match report.exit {
+    Exit::Exited(0) => Ok(()),
+    Exit::Exited(code) => std::process::exit(code),
+    Exit::Faulted { .. } => std::process::exit(1),
+  }
+}
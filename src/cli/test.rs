@@ -0,0 +1,24 @@
+use std::path::PathBuf;
+
+use clap::Args;
+
+use crate::testing::run_all;
+
+#[derive(Args)]
+pub struct TestOptions {
+  /// A directory to search for `.etac` files, or a single file
+  #[arg(default_value = ".")]
+  pub path: PathBuf,
+}
+
+/// Checks the annotations of every ETAC file, and fails if any of them is not met.
+pub fn run(TestOptions { path }: &TestOptions) -> Result<(), Box<dyn std::error::Error>> {
+  let failed = run_all(path, &mut std::io::stdout().lock())?;
+
+  // The summary was already printed
+  if failed > 0 {
+    std::process::exit(1);
+  }
+
+  Ok(())
+}
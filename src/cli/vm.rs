@@ -0,0 +1,23 @@
+use clap::Args;
+
+use crate::{
+  bytecode::{vm::Vm, Module},
+  runtime::Io,
+};
+
+#[derive(Args)]
+pub struct VmOptions {
+  /// The `.cbc` file to run
+  pub filepath: String,
+}
+
+pub fn run(VmOptions { filepath }: &VmOptions) -> Result<(), Box<dyn std::error::Error>> {
+  let bytes = std::fs::read(filepath)?;
+  let module = Module::from_bytes(&bytes)?;
+
+  let mut input = std::io::stdin().lock();
+  let mut output = std::io::stdout().lock();
+  Vm::new(&module).run(&mut Io::new(&mut input, &mut output))?;
+
+  Ok(())
+}
@@ -0,0 +1,248 @@
+// Control flow graph over the goto-based ETAC statements.
+//
+// Backends that need structured control flow (such as WebAssembly) build this graph out of a flat
+// list of statements and then work on the basic blocks instead of on labels and jumps.
+
+use std::collections::HashMap;
+
+use crate::ast::{Expr, Location, Statement};
+
+use super::error::CodegenError;
+
+pub type BlockId = usize;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Terminator {
+  /// Unconditional transfer to another block (a `goto` or a fallthrough).
+  Jump(BlockId),
+  /// `if condition goto then`, falling through to `otherwise`.
+  Branch {
+    condition: Expr,
+    then: BlockId,
+    otherwise: BlockId,
+    /// Of the `if`, for the errors of the condition
+    location: Location,
+  },
+  /// Leaves the function (or the program, at the top level).
+  Return,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BasicBlock {
+  pub label: Option<String>,
+  /// Straight-line statements, without labels nor jumps.
+  pub statements: Vec<Statement>,
+  pub terminator: Terminator,
+}
+
+#[derive(Debug)]
+pub struct ControlFlowGraph {
+  pub blocks: Vec<BasicBlock>,
+  pub entry: BlockId,
+}
+
+// Exit of a block while the graph is still being built, labels are resolved afterwards.
+enum PendingExit {
+  Fallthrough,
+  Goto(String, Location),
+  Branch(Expr, String, Location),
+}
+
+impl ControlFlowGraph {
+  /// Builds the graph of a statement list. Function definitions are skipped, as each one of them
+  /// has its own graph built out of its body.
+  pub fn build(statements: &[Statement]) -> Result<Self, CodegenError> {
+    let mut blocks: Vec<(Option<String>, Vec<Statement>, PendingExit)> = vec![];
+    // The block each label starts
+    let mut labels = HashMap::new();
+    let mut label = None;
+    let mut current = vec![];
+
+    for statement in statements {
+      match statement {
+        Statement::Label { name, location } => {
+          if labels.insert(name.clone(), blocks.len() + 1).is_some() {
+            let error = format!("Label `{name}` defined more than once");
+            return Err(CodegenError::from(error).at(Some(location)));
+          }
+          blocks.push((
+            label.take(),
+            std::mem::take(&mut current),
+            PendingExit::Fallthrough,
+          ));
+          label = Some(name.clone());
+        }
+        Statement::UnconditionalJump {
+          label: target,
+          location,
+        } => {
+          blocks.push((
+            label.take(),
+            std::mem::take(&mut current),
+            PendingExit::Goto(target.clone(), location.clone()),
+          ));
+        }
+        Statement::ConditionalJump {
+          condition,
+          label: target,
+          location,
+        } => {
+          blocks.push((
+            label.take(),
+            std::mem::take(&mut current),
+            PendingExit::Branch(condition.clone(), target.clone(), location.clone()),
+          ));
+        }
+        Statement::FunctionDefinition(_) | Statement::NoOperation => {}
+        _ => current.push(statement.clone()),
+      }
+    }
+    blocks.push((label, current, PendingExit::Fallthrough));
+
+    let resolve = |label: &String, location: &Location| {
+      labels
+        .get(label)
+        .copied()
+        .ok_or_else(|| CodegenError::from(format!("Label `{label}` not found")).at(Some(location)))
+    };
+
+    let count = blocks.len();
+    let blocks = blocks
+      .into_iter()
+      .enumerate()
+      .map(|(id, (label, statements, exit))| {
+        let next = || {
+          if id + 1 < count {
+            Terminator::Jump(id + 1)
+          } else {
+            Terminator::Return
+          }
+        };
+
+        let terminator = match exit {
+          PendingExit::Fallthrough => next(),
+          PendingExit::Goto(target, location) => Terminator::Jump(resolve(&target, &location)?),
+          PendingExit::Branch(condition, target, location) => Terminator::Branch {
+            condition,
+            then: resolve(&target, &location)?,
+            otherwise: if id + 1 < count { id + 1 } else { count },
+            location,
+          },
+        };
+
+        Ok(BasicBlock {
+          label,
+          statements,
+          terminator,
+        })
+      })
+      .collect::<Result<Vec<_>, CodegenError>>()?;
+
+    let mut graph = Self { blocks, entry: 0 };
+
+    // A conditional jump at the very end of the list falls through into an empty exit block
+    if graph.successors(graph.blocks.len() - 1).contains(&count) {
+      graph.blocks.push(BasicBlock {
+        label: None,
+        statements: vec![],
+        terminator: Terminator::Return,
+      });
+    }
+
+    Ok(graph)
+  }
+
+  pub fn successors(&self, id: BlockId) -> Vec<BlockId> {
+    match &self.blocks[id].terminator {
+      Terminator::Jump(target) => vec![*target],
+      Terminator::Branch {
+        then, otherwise, ..
+      } => vec![*then, *otherwise],
+      Terminator::Return => vec![],
+    }
+  }
+
+  pub fn predecessors(&self, id: BlockId) -> Vec<BlockId> {
+    (0..self.blocks.len())
+      .filter(|block| self.successors(*block).contains(&id))
+      .collect()
+  }
+
+  /// Blocks reachable from the entry, in reverse postorder.
+  pub fn reverse_postorder(&self) -> Vec<BlockId> {
+    let mut visited = vec![false; self.blocks.len()];
+    let mut postorder = vec![];
+    // Iterative DFS, each entry is a block and the index of the next successor to visit
+    let mut stack = vec![(self.entry, 0)];
+    visited[self.entry] = true;
+
+    while let Some((block, next)) = stack.pop() {
+      let successors = self.successors(block);
+      if next < successors.len() {
+        stack.push((block, next + 1));
+        let successor = successors[next];
+        if !visited[successor] {
+          visited[successor] = true;
+          stack.push((successor, 0));
+        }
+      } else {
+        postorder.push(block);
+      }
+    }
+
+    postorder.reverse();
+    postorder
+  }
+
+  /// Immediate dominator of every reachable block, following "A Simple, Fast Dominance
+  /// Algorithm" by Cooper, Harvey and Kennedy. The entry is its own dominator.
+  pub fn immediate_dominators(&self) -> HashMap<BlockId, BlockId> {
+    let order = self.reverse_postorder();
+    let rpo_number: HashMap<BlockId, usize> = order
+      .iter()
+      .enumerate()
+      .map(|(i, block)| (*block, i))
+      .collect();
+
+    let mut idom: HashMap<BlockId, BlockId> = HashMap::new();
+    idom.insert(self.entry, self.entry);
+
+    let intersect = |idom: &HashMap<BlockId, BlockId>, mut a: BlockId, mut b: BlockId| {
+      while a != b {
+        while rpo_number[&a] > rpo_number[&b] {
+          a = idom[&a];
+        }
+        while rpo_number[&b] > rpo_number[&a] {
+          b = idom[&b];
+        }
+      }
+      a
+    };
+
+    let mut changed = true;
+    while changed {
+      changed = false;
+
+      for block in order.iter().skip(1) {
+        let mut new_idom = None;
+        for predecessor in self.predecessors(*block) {
+          if !idom.contains_key(&predecessor) {
+            continue;
+          }
+          new_idom = Some(match new_idom {
+            None => predecessor,
+            Some(current) => intersect(&idom, predecessor, current),
+          });
+        }
+
+        let new_idom = new_idom.expect("reachable block without processed predecessors");
+        if idom.get(block) != Some(&new_idom) {
+          idom.insert(*block, new_idom);
+          changed = true;
+        }
+      }
+    }
+
+    idom
+  }
+}
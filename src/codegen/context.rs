@@ -61,6 +61,7 @@ impl Context {
         args: vec![Argument {
           name: "message".to_string(),
           var_type: VarType::Str,
+          location: 0..0,
         }],
         return_type: VarType::Void,
         location: 0..0,
@@ -72,6 +73,7 @@ impl Context {
         args: vec![Argument {
           name: "number".to_string(),
           var_type: VarType::I32,
+          location: 0..0,
         }],
         return_type: VarType::Void,
         location: 0..0,
@@ -91,6 +93,7 @@ impl Context {
         args: vec![Argument {
           name: "size".to_string(),
           var_type: VarType::U32,
+          location: 0..0,
         }],
         return_type: VarType::Str,
         location: 0..0,
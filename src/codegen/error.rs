@@ -0,0 +1,81 @@
+use crate::ast::{Location, VarType};
+
+/// Why a backend could not generate code, at the statement it failed on when it is known.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CodegenError {
+  /// A construct the target has no code for yet
+  Unsupported {
+    construct: String,
+    target: &'static str,
+    location: Option<Location>,
+  },
+  /// A type the target cannot represent
+  UnsupportedType {
+    var_type: VarType,
+    target: &'static str,
+    location: Option<Location>,
+  },
+  /// A program the backend cannot make sense of, which the parser let through
+  Invalid {
+    message: String,
+    location: Option<Location>,
+  },
+}
+
+impl CodegenError {
+  pub fn unsupported(construct: impl Into<String>, target: &'static str) -> Self {
+    CodegenError::Unsupported {
+      construct: construct.into(),
+      target,
+      location: None,
+    }
+  }
+
+  pub fn location(&self) -> Option<&Location> {
+    match self {
+      CodegenError::Unsupported { location, .. }
+      | CodegenError::UnsupportedType { location, .. }
+      | CodegenError::Invalid { location, .. } => location.as_ref(),
+    }
+  }
+
+  /// The error at the statement it happened in, unless it already is at a nested one.
+  pub fn at(mut self, statement: Option<&Location>) -> Self {
+    let (CodegenError::Unsupported { location, .. }
+    | CodegenError::UnsupportedType { location, .. }
+    | CodegenError::Invalid { location, .. }) = &mut self;
+
+    if location.is_none() {
+      *location = statement.cloned();
+    }
+    self
+  }
+}
+
+impl From<String> for CodegenError {
+  fn from(message: String) -> Self {
+    CodegenError::Invalid {
+      message,
+      location: None,
+    }
+  }
+}
+
+impl std::fmt::Display for CodegenError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      CodegenError::Unsupported {
+        construct, target, ..
+      } => write!(f, "Unsupported on the `{target}` target: {construct}"),
+      CodegenError::UnsupportedType {
+        var_type, target, ..
+      } => write!(
+        f,
+        "Type `{var_type}` is not supported by the `{target}` target"
+      ),
+      CodegenError::Invalid { message, .. } => write!(f, "{message}"),
+    }
+  }
+}
+
+impl std::error::Error for CodegenError {}
@@ -0,0 +1,792 @@
+// Assembler for the programs `MipsCodegen` produces: it expands the pseudo-instructions into
+// MIPS32 instructions, lays out `.text` and `.data`, and encodes every instruction into a word.
+//
+// Branches are encoded without delay slots, like MARS and SPIM run them with delayed branching
+// disabled, unless `Branches::Delayed` asks for a `nop` in the slot after each of them, which runs
+// the same on a CPU that executes it. As real assemblers do, `$at` is the scratch register of the
+// expansions.
+//
+// On bare metal there is no kernel to serve `syscall`, so each one is replaced by accesses to the
+// memory-mapped devices in `target::mmio`, using `$k0` and `$k1`, the registers of the kernel.
+
+use std::collections::HashMap;
+
+use celestial_hub_astrolabe::{
+  ast::{Instruction, InstructionArgument, Program, Statement},
+  lexer::tokens::{Type, Value},
+};
+
+use crate::utils::unquote_string_literal;
+
+use super::target::{mmio, registers, syscalls};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Endianness {
+  #[default]
+  Big,
+  Little,
+}
+
+impl Endianness {
+  pub fn half(self, value: u16) -> [u8; 2] {
+    match self {
+      Endianness::Big => value.to_be_bytes(),
+      Endianness::Little => value.to_le_bytes(),
+    }
+  }
+
+  pub fn word(self, value: u32) -> [u8; 4] {
+    match self {
+      Endianness::Big => value.to_be_bytes(),
+      Endianness::Little => value.to_le_bytes(),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Section {
+  Text,
+  Data,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Symbol {
+  pub name: String,
+  pub section: Section,
+  /// Offset from the start of the section
+  pub offset: u32,
+  pub global: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RelocationKind {
+  /// Upper half of an address, adjusted for the sign of the lower one (`lui`)
+  Hi16,
+  /// Lower half of an address (`addiu`)
+  Lo16,
+  /// Word index of a jump target (`j`, `jal`)
+  Jump26,
+}
+
+/// A field of `.text` that depends on the address of a symbol.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Relocation {
+  /// Offset of the instruction from the start of `.text`
+  pub offset: u32,
+  pub kind: RelocationKind,
+  pub symbol: String,
+}
+
+/// Where the sections are placed in memory.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layout {
+  pub text_base: u32,
+  pub data_base: u32,
+}
+
+impl Layout {
+  /// The default memory configuration of MARS and SPIM.
+  pub const MARS: Layout = Layout {
+    text_base: 0x0040_0000,
+    data_base: 0x1001_0000,
+  };
+
+  /// Both sections at address zero, the addresses in the code are offsets into their section.
+  pub const RELOCATABLE: Layout = Layout {
+    text_base: 0,
+    data_base: 0,
+  };
+}
+
+impl Default for Layout {
+  fn default() -> Self {
+    Self::MARS
+  }
+}
+
+/// What the program runs on, which decides how `syscall` and `halt` are assembled.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Environment {
+  /// A simulator or an OS serving the SPIM/MARS syscalls
+  #[default]
+  Hosted,
+  /// A CPU without an OS: I/O goes through memory-mapped devices, `halt` spins forever and the
+  /// program starts at the beginning of `.text`
+  BareMetal,
+}
+
+/// Whether the instruction after a branch or a jump runs before it is taken.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Branches {
+  /// Taken at once, like MARS and SPIM with delayed branching disabled, and the simulator
+  #[default]
+  Immediate,
+  /// Taken after the delay slot, like a MIPS32 CPU does, so each one is followed by a `nop`
+  Delayed,
+}
+
+#[derive(Debug)]
+pub struct Assembly {
+  pub layout: Layout,
+  pub text: Vec<u32>,
+  pub data: Vec<u8>,
+  pub symbols: Vec<Symbol>,
+  pub relocations: Vec<Relocation>,
+  /// Symbols referenced by the program but not defined in it, left for the linker
+  pub undefined: Vec<String>,
+  pub entry: String,
+}
+
+impl Assembly {
+  pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+    self.symbols.iter().find(|symbol| symbol.name == name)
+  }
+
+  pub fn address_of(&self, name: &str) -> Option<u32> {
+    self.symbol(name).map(|symbol| match symbol.section {
+      Section::Text => self.layout.text_base + symbol.offset,
+      Section::Data => self.layout.data_base + symbol.offset,
+    })
+  }
+
+  /// Fails when the program depends on symbols it does not define, so it cannot run on its own.
+  pub fn ensure_linked(&self) -> Result<(), String> {
+    match self.undefined.first() {
+      Some(name) => Err(format!("Label `{name}` not found")),
+      None => Ok(()),
+    }
+  }
+
+  pub fn entry_address(&self) -> Result<u32, String> {
+    self
+      .address_of(&self.entry)
+      .ok_or_else(|| format!("Entry point `{}` not found", self.entry))
+  }
+}
+
+enum Reference {
+  None,
+  Branch(String),
+  Jump(String),
+  Hi(String),
+  Lo(String),
+}
+
+// An instruction with every field but the ones depending on a label
+struct Machine {
+  word: u32,
+  reference: Reference,
+}
+
+pub(super) mod opcode {
+  pub const SPECIAL: u32 = 0x00;
+  pub const REGIMM: u32 = 0x01;
+  pub const J: u32 = 0x02;
+  pub const JAL: u32 = 0x03;
+  pub const BEQ: u32 = 0x04;
+  pub const BNE: u32 = 0x05;
+  pub const BLEZ: u32 = 0x06;
+  pub const BGTZ: u32 = 0x07;
+  pub const ADDI: u32 = 0x08;
+  pub const ADDIU: u32 = 0x09;
+  pub const SLTI: u32 = 0x0a;
+  pub const SLTIU: u32 = 0x0b;
+  pub const ANDI: u32 = 0x0c;
+  pub const ORI: u32 = 0x0d;
+  pub const XORI: u32 = 0x0e;
+  pub const LUI: u32 = 0x0f;
+  pub const SPECIAL2: u32 = 0x1c;
+  pub const LB: u32 = 0x20;
+  pub const LH: u32 = 0x21;
+  pub const LW: u32 = 0x23;
+  pub const LBU: u32 = 0x24;
+  pub const LHU: u32 = 0x25;
+  pub const SB: u32 = 0x28;
+  pub const SH: u32 = 0x29;
+  pub const SW: u32 = 0x2b;
+}
+
+pub(super) mod funct {
+  pub const SLL: u32 = 0x00;
+  pub const SRL: u32 = 0x02;
+  pub const SRA: u32 = 0x03;
+  pub const SLLV: u32 = 0x04;
+  pub const SRLV: u32 = 0x06;
+  pub const SRAV: u32 = 0x07;
+  pub const JR: u32 = 0x08;
+  pub const JALR: u32 = 0x09;
+  pub const SYSCALL: u32 = 0x0c;
+  pub const BREAK: u32 = 0x0d;
+  pub const MFHI: u32 = 0x10;
+  pub const MTHI: u32 = 0x11;
+  pub const MFLO: u32 = 0x12;
+  pub const MTLO: u32 = 0x13;
+  pub const MULT: u32 = 0x18;
+  pub const MULTU: u32 = 0x19;
+  pub const DIV: u32 = 0x1a;
+  pub const DIVU: u32 = 0x1b;
+  pub const ADD: u32 = 0x20;
+  pub const ADDU: u32 = 0x21;
+  pub const SUB: u32 = 0x22;
+  pub const SUBU: u32 = 0x23;
+  pub const AND: u32 = 0x24;
+  pub const OR: u32 = 0x25;
+  pub const XOR: u32 = 0x26;
+  pub const NOR: u32 = 0x27;
+  pub const SLT: u32 = 0x2a;
+  pub const SLTU: u32 = 0x2b;
+  /// `mul` under the SPECIAL2 opcode
+  pub const MUL: u32 = 0x02;
+}
+
+const ZERO: u32 = 0;
+const AT: u32 = 1;
+const V0: u32 = 2;
+const A0: u32 = 4;
+const A1: u32 = 5;
+const K0: u32 = 26;
+const K1: u32 = 27;
+
+fn r_type(funct: u32, rs: u32, rt: u32, rd: u32) -> u32 {
+  (opcode::SPECIAL << 26) | (rs << 21) | (rt << 16) | (rd << 11) | funct
+}
+
+fn i_type(opcode: u32, rs: u32, rt: u32, immediate: u16) -> u32 {
+  (opcode << 26) | (rs << 21) | (rt << 16) | immediate as u32
+}
+
+fn fits_i16(value: i32) -> bool {
+  i16::try_from(value).is_ok()
+}
+
+fn fits_u16(value: i32) -> bool {
+  u16::try_from(value).is_ok()
+}
+
+// A register or immediate operand
+enum Source {
+  Register(u32),
+  Immediate(i32),
+}
+
+#[derive(Default)]
+struct Expander {
+  code: Vec<Machine>,
+  environment: Environment,
+  branches: Branches,
+  /// Value of `$v0` when it is known from the instructions since the last label
+  service: Option<i32>,
+}
+
+impl Expander {
+  fn emit(&mut self, word: u32) {
+    self.code.push(Machine {
+      word,
+      reference: Reference::None,
+    });
+  }
+
+  fn emit_with(&mut self, word: u32, reference: Reference) {
+    self.code.push(Machine { word, reference });
+  }
+
+  fn li(&mut self, rd: u32, value: i32) {
+    if fits_i16(value) {
+      self.emit(i_type(opcode::ADDIU, ZERO, rd, value as u16));
+    } else if fits_u16(value) {
+      self.emit(i_type(opcode::ORI, ZERO, rd, value as u16));
+    } else {
+      self.emit(i_type(opcode::LUI, ZERO, rd, (value as u32 >> 16) as u16));
+      if value & 0xffff != 0 {
+        self.emit(i_type(opcode::ORI, rd, rd, value as u16));
+      }
+    }
+  }
+
+  /// The register holding `source`, immediates are loaded into `$at`.
+  fn in_register(&mut self, source: Source) -> u32 {
+    match source {
+      Source::Register(register) => register,
+      Source::Immediate(value) => {
+        self.li(AT, value);
+        AT
+      }
+    }
+  }
+
+  fn branch(&mut self, opcode: u32, rs: u32, rt: u32, label: String) {
+    self.emit_with(i_type(opcode, rs, rt, 0), Reference::Branch(label));
+    self.delay_slot();
+  }
+
+  fn jump(&mut self, opcode: u32, label: String) {
+    self.emit_with(opcode << 26, Reference::Jump(label));
+    self.delay_slot();
+  }
+
+  // `sll $zero, $zero, 0`, the `nop` after a branch or a jump that is taken after it
+  fn delay_slot(&mut self) {
+    if self.branches == Branches::Delayed {
+      self.emit(0);
+    }
+  }
+
+  /// Follows the value of `$v0`, so the syscall it selects can be lowered on bare metal.
+  fn track_service(&mut self, instruction: &Instruction) {
+    let destination = |args: &[InstructionArgument]| register(args, 0).ok();
+
+    self.service = match instruction {
+      Instruction::Li(args) if destination(args) == Some(V0) => match args.get(1) {
+        Some(InstructionArgument::Immediate(value)) => Some(*value),
+        _ => None,
+      },
+      Instruction::Li(args) | Instruction::La(args) | Instruction::Move(args)
+        if destination(args) != Some(V0) =>
+      {
+        self.service
+      }
+      Instruction::Syscall => self.service,
+      _ => None,
+    };
+  }
+
+  /// Replaces the syscall selected by `$v0` with accesses to the memory-mapped devices.
+  fn bare_metal_syscall(&mut self) -> Result<(), String> {
+    let service = self
+      .service
+      .ok_or("Cannot find the service of the `syscall`, `$v0` is not loaded before it")?;
+    let port = |address: u32| address as u16;
+
+    match service {
+      syscalls::PRINT_INT => self.emit(i_type(opcode::SW, ZERO, A0, port(mmio::PRINT_INT))),
+      syscalls::PRINT_STRING => {
+        self.emit(r_type(funct::ADDU, A0, ZERO, K0));
+        self.emit(i_type(opcode::LBU, K0, K1, 0));
+        self.emit(i_type(opcode::BEQ, K1, ZERO, 3));
+        self.emit(i_type(opcode::SW, ZERO, K1, port(mmio::PUTCHAR)));
+        self.emit(i_type(opcode::ADDIU, K0, K0, 1));
+        self.emit(i_type(opcode::BEQ, ZERO, ZERO, -5i16 as u16));
+      }
+      syscalls::READ_INT => self.emit(i_type(opcode::LW, ZERO, V0, port(mmio::READ_INT))),
+      syscalls::READ_STRING => {
+        // Like SPIM, reads up to `$a1 - 1` characters, stopping after a newline
+        self.emit(r_type(funct::ADDU, A0, ZERO, K0));
+        self.emit(i_type(opcode::ADDIU, A1, AT, -1i16 as u16));
+        self.emit(i_type(opcode::BLEZ, AT, ZERO, 6));
+        self.emit(i_type(opcode::LW, ZERO, K1, port(mmio::GETCHAR)));
+        self.emit(i_type(opcode::SB, K0, K1, 0));
+        self.emit(i_type(opcode::ADDIU, K0, K0, 1));
+        self.emit(i_type(opcode::ADDIU, AT, AT, -1i16 as u16));
+        self.emit(i_type(opcode::ADDIU, K1, K1, -(b'\n' as i16) as u16));
+        self.emit(i_type(opcode::BNE, K1, ZERO, -7i16 as u16));
+        self.emit(i_type(opcode::SB, K0, ZERO, 0));
+      }
+      syscalls::EXIT => self.spin(),
+      service => return Err(format!("Syscall {service} is not available on bare metal")),
+    }
+
+    Ok(())
+  }
+
+  /// Branches to itself, which is how a program stops on a CPU without an OS.
+  fn spin(&mut self) {
+    self.emit(i_type(opcode::BEQ, ZERO, ZERO, -1i16 as u16));
+    self.delay_slot();
+  }
+
+  fn instruction(&mut self, instruction: &Instruction) -> Result<(), String> {
+    self.track_service(instruction);
+
+    match instruction {
+      Instruction::Li(args) => {
+        let value = match argument(args, 1)? {
+          InstructionArgument::Immediate(value) => *value,
+          other => return Err(format!("Expected an immediate, found `{other}`")),
+        };
+        self.li(register(args, 0)?, value);
+      }
+      Instruction::La(args) => {
+        let label = label(args, 1)?;
+        self.emit_with(
+          i_type(opcode::LUI, ZERO, AT, 0),
+          Reference::Hi(label.clone()),
+        );
+        self.emit_with(
+          i_type(opcode::ADDIU, AT, register(args, 0)?, 0),
+          Reference::Lo(label),
+        );
+      }
+      Instruction::Syscall => match self.environment {
+        Environment::Hosted => self.emit(funct::SYSCALL),
+        Environment::BareMetal => self.bare_metal_syscall()?,
+      },
+      Instruction::Halt => match self.environment {
+        Environment::Hosted => {
+          self.li(V0, syscalls::EXIT);
+          self.emit(funct::SYSCALL);
+        }
+        Environment::BareMetal => self.spin(),
+      },
+      Instruction::Move(args) => {
+        self.emit(r_type(
+          funct::ADDU,
+          register(args, 1)?,
+          ZERO,
+          register(args, 0)?,
+        ));
+      }
+      Instruction::J(args) => self.jump(opcode::J, label(args, 0)?),
+      Instruction::Jal(args) => self.jump(opcode::JAL, label(args, 0)?),
+      Instruction::Jr(args) => {
+        self.emit(r_type(funct::JR, register(args, 0)?, ZERO, ZERO));
+        self.delay_slot();
+      }
+      Instruction::Add(args) | Instruction::Addi(args) => {
+        let (rd, rs) = (register(args, 0)?, register(args, 1)?);
+        match source(args, 2)? {
+          Source::Immediate(value) if fits_i16(value) => {
+            self.emit(i_type(opcode::ADDI, rs, rd, value as u16));
+          }
+          rt => {
+            let rt = self.in_register(rt);
+            self.emit(r_type(funct::ADD, rs, rt, rd));
+          }
+        }
+      }
+      Instruction::Sub(args) => {
+        let (rd, rs) = (register(args, 0)?, register(args, 1)?);
+        match source(args, 2)? {
+          Source::Immediate(value) if value != i32::MIN && fits_i16(-value) => {
+            self.emit(i_type(opcode::ADDI, rs, rd, (-value) as u16));
+          }
+          rt => {
+            let rt = self.in_register(rt);
+            self.emit(r_type(funct::SUB, rs, rt, rd));
+          }
+        }
+      }
+      Instruction::Andi(args) => {
+        let (rd, rs) = (register(args, 0)?, register(args, 1)?);
+        match source(args, 2)? {
+          Source::Immediate(value) if fits_u16(value) => {
+            self.emit(i_type(opcode::ANDI, rs, rd, value as u16));
+          }
+          rt => {
+            let rt = self.in_register(rt);
+            self.emit(r_type(funct::AND, rs, rt, rd));
+          }
+        }
+      }
+      Instruction::Mul(args) => {
+        let (rd, rs) = (register(args, 0)?, register(args, 1)?);
+        let rt = self.in_register(source(args, 2)?);
+        self.emit((opcode::SPECIAL2 << 26) | (rs << 21) | (rt << 16) | (rd << 11) | funct::MUL);
+      }
+      Instruction::Div(args) => {
+        if args.len() == 2 {
+          self.emit(r_type(
+            funct::DIV,
+            register(args, 0)?,
+            register(args, 1)?,
+            ZERO,
+          ));
+        } else {
+          let (rd, rs) = (register(args, 0)?, register(args, 1)?);
+          let rt = self.in_register(source(args, 2)?);
+          self.emit(r_type(funct::DIV, rs, rt, ZERO));
+          self.emit(r_type(funct::MFLO, ZERO, ZERO, rd));
+        }
+      }
+      Instruction::Sw(args) => {
+        self.emit(i_type(
+          opcode::SW,
+          register(args, 1)?,
+          register(args, 0)?,
+          0,
+        ));
+      }
+      Instruction::Lw(args) => {
+        self.emit(i_type(
+          opcode::LW,
+          register(args, 1)?,
+          register(args, 0)?,
+          0,
+        ));
+      }
+      Instruction::Slt(args) => {
+        let (rd, rs) = (register(args, 0)?, register(args, 1)?);
+        match source(args, 2)? {
+          Source::Immediate(value) if fits_i16(value) => {
+            self.emit(i_type(opcode::SLTI, rs, rd, value as u16));
+          }
+          rt => {
+            let rt = self.in_register(rt);
+            self.emit(r_type(funct::SLT, rs, rt, rd));
+          }
+        }
+      }
+      Instruction::Sgt(args) => {
+        let (rd, rs) = (register(args, 0)?, register(args, 1)?);
+        let rt = self.in_register(source(args, 2)?);
+        self.emit(r_type(funct::SLT, rt, rs, rd));
+      }
+      Instruction::Sle(args) => {
+        let (rd, rs) = (register(args, 0)?, register(args, 1)?);
+        let rt = self.in_register(source(args, 2)?);
+        self.emit(r_type(funct::SLT, rt, rs, rd));
+        self.emit(i_type(opcode::XORI, rd, rd, 1));
+      }
+      Instruction::Sge(args) => {
+        let (rd, rs) = (register(args, 0)?, register(args, 1)?);
+        let rt = self.in_register(source(args, 2)?);
+        self.emit(r_type(funct::SLT, rs, rt, rd));
+        self.emit(i_type(opcode::XORI, rd, rd, 1));
+      }
+      Instruction::Seq(args) => {
+        let (rd, rs) = (register(args, 0)?, register(args, 1)?);
+        let rt = self.in_register(source(args, 2)?);
+        self.emit(r_type(funct::SUBU, rs, rt, rd));
+        self.emit(i_type(opcode::SLTIU, rd, rd, 1));
+      }
+      Instruction::Sne(args) => {
+        let (rd, rs) = (register(args, 0)?, register(args, 1)?);
+        let rt = self.in_register(source(args, 2)?);
+        self.emit(r_type(funct::SUBU, rs, rt, rd));
+        self.emit(r_type(funct::SLTU, ZERO, rd, rd));
+      }
+      Instruction::Beqz(args) => {
+        let rs = self.in_register(source(args, 0)?);
+        self.branch(opcode::BEQ, rs, ZERO, label(args, 1)?);
+      }
+      Instruction::Bnez(args) => {
+        let rs = self.in_register(source(args, 0)?);
+        self.branch(opcode::BNE, rs, ZERO, label(args, 1)?);
+      }
+      Instruction::Bltz(args) => {
+        let rs = self.in_register(source(args, 0)?);
+        self.branch(opcode::REGIMM, rs, 0, label(args, 1)?);
+      }
+      Instruction::Bgez(args) => {
+        let rs = self.in_register(source(args, 0)?);
+        self.branch(opcode::REGIMM, rs, 1, label(args, 1)?);
+      }
+      Instruction::Bgtz(args) => {
+        let rs = self.in_register(source(args, 0)?);
+        self.branch(opcode::BGTZ, rs, 0, label(args, 1)?);
+      }
+      Instruction::Blez(args) => {
+        let rs = self.in_register(source(args, 0)?);
+        self.branch(opcode::BLEZ, rs, 0, label(args, 1)?);
+      }
+      Instruction::Beq(args) | Instruction::Bne(args) => {
+        let opcode = match instruction {
+          Instruction::Beq(_) => opcode::BEQ,
+          _ => opcode::BNE,
+        };
+        let rs = register(args, 0)?;
+        let rt = self.in_register(source(args, 1)?);
+        self.branch(opcode, rs, rt, label(args, 2)?);
+      }
+      Instruction::Blt(args)
+      | Instruction::Bgt(args)
+      | Instruction::Ble(args)
+      | Instruction::Bge(args) => {
+        let rs = register(args, 0)?;
+        let rt = self.in_register(source(args, 1)?);
+
+        // `$at` is set when the "less than" in the condition holds
+        let (lhs, rhs, opcode) = match instruction {
+          Instruction::Blt(_) => (rs, rt, opcode::BNE),
+          Instruction::Bgt(_) => (rt, rs, opcode::BNE),
+          Instruction::Ble(_) => (rt, rs, opcode::BEQ),
+          _ => (rs, rt, opcode::BEQ),
+        };
+        self.emit(r_type(funct::SLT, lhs, rhs, AT));
+        self.branch(opcode, AT, ZERO, label(args, 2)?);
+      }
+    }
+
+    Ok(())
+  }
+}
+
+fn argument(args: &[InstructionArgument], index: usize) -> Result<&InstructionArgument, String> {
+  args
+    .get(index)
+    .ok_or_else(|| format!("Missing operand {index}"))
+}
+
+fn register(args: &[InstructionArgument], index: usize) -> Result<u32, String> {
+  match argument(args, index)? {
+    InstructionArgument::Register(register) => registers::number(&register.name)
+      .ok_or_else(|| format!("Unknown register `{}`", register.name)),
+    other => Err(format!("Expected a register, found `{other}`")),
+  }
+}
+
+fn source(args: &[InstructionArgument], index: usize) -> Result<Source, String> {
+  match argument(args, index)? {
+    InstructionArgument::Immediate(value) => Ok(Source::Immediate(*value)),
+    _ => Ok(Source::Register(register(args, index)?)),
+  }
+}
+
+fn label(args: &[InstructionArgument], index: usize) -> Result<String, String> {
+  match argument(args, index)? {
+    InstructionArgument::Label(label) => Ok(label.clone()),
+    other => Err(format!("Expected a label, found `{other}`")),
+  }
+}
+
+/// Assembles the program for MARS and SPIM, or an OS serving their syscalls.
+pub fn assemble(program: &Program, layout: Layout) -> Result<Assembly, String> {
+  assemble_for(program, layout, Environment::Hosted, Branches::Immediate)
+}
+
+pub fn assemble_for(
+  program: &Program,
+  layout: Layout,
+  environment: Environment,
+  branches: Branches,
+) -> Result<Assembly, String> {
+  let mut symbols: Vec<Symbol> = vec![];
+  let mut define = |name: &str, section: Section, offset: u32| {
+    if symbols.iter().any(|symbol| symbol.name == name) {
+      return Err(format!("Label `{name}` defined more than once"));
+    }
+
+    symbols.push(Symbol {
+      name: name.to_string(),
+      section,
+      offset,
+      global: name == program.text_section.entrypoint,
+    });
+    Ok(())
+  };
+
+  let mut data = vec![];
+  for variable in &program.data_section.variables {
+    define(&variable.name, Section::Data, data.len() as u32)?;
+
+    match (&variable.type_, &variable.value) {
+      (Type::Asciiz, Value::String(value)) => {
+        data.extend(unquote_string_literal(value).bytes());
+        data.push(0);
+      }
+      (Type::Space, Value::Bytes(size)) if *size >= 0 => {
+        data.resize(data.len() + *size as usize, 0);
+      }
+      _ => return Err(format!("Invalid data for `{}`", variable.name)),
+    }
+  }
+
+  let mut expander = Expander {
+    environment,
+    branches,
+    ..Default::default()
+  };
+
+  // Execution starts at the beginning of the instruction memory
+  let entry = &program.text_section.entrypoint;
+  let starts_at_entry = matches!(
+    program.text_section.statements.first(),
+    Some(Statement::Label(name)) if name == entry
+  );
+  if environment == Environment::BareMetal && !starts_at_entry {
+    expander.jump(opcode::J, entry.clone());
+  }
+
+  for statement in &program.text_section.statements {
+    match statement {
+      Statement::Label(name) => {
+        expander.service = None;
+        define(name, Section::Text, expander.code.len() as u32 * 4)?
+      }
+      Statement::Instruction(instruction) => expander
+        .instruction(instruction)
+        .map_err(|error| format!("{error} in `{instruction}`"))?,
+    }
+  }
+
+  let addresses: HashMap<&str, (Section, u32)> = symbols
+    .iter()
+    .map(|symbol| {
+      let base = match symbol.section {
+        Section::Text => layout.text_base,
+        Section::Data => layout.data_base,
+      };
+      (symbol.name.as_str(), (symbol.section, base + symbol.offset))
+    })
+    .collect();
+
+  let mut text = vec![];
+  let mut relocations = vec![];
+  let mut undefined: Vec<String> = vec![];
+
+  for (index, machine) in expander.code.into_iter().enumerate() {
+    let offset = index as u32 * 4;
+    let pc = layout.text_base + offset;
+    let mut word = machine.word;
+
+    let mut relocate = |kind: RelocationKind, symbol: &String| {
+      relocations.push(Relocation {
+        offset,
+        kind,
+        symbol: symbol.clone(),
+      });
+
+      let address = addresses.get(symbol.as_str()).map(|(_, address)| *address);
+      if address.is_none() && !undefined.contains(symbol) {
+        undefined.push(symbol.clone());
+      }
+      address
+    };
+
+    match &machine.reference {
+      Reference::None => {}
+      Reference::Branch(label) => {
+        let target = match addresses.get(label.as_str()) {
+          Some((Section::Text, address)) => *address,
+          Some((Section::Data, _)) => return Err(format!("Cannot branch to data label `{label}`")),
+          None => return Err(format!("Label `{label}` not found")),
+        };
+
+        let distance = (target.wrapping_sub(pc + 4) as i32) >> 2;
+        if !fits_i16(distance) {
+          return Err(format!("Branch to `{label}` is out of range"));
+        }
+        word |= distance as u16 as u32;
+      }
+      Reference::Jump(label) => {
+        if let Some(target) = relocate(RelocationKind::Jump26, label) {
+          if target & 0xf000_0000 != (pc + 4) & 0xf000_0000 {
+            return Err(format!("Jump to `{label}` is out of range"));
+          }
+          word |= (target >> 2) & 0x03ff_ffff;
+        }
+      }
+      Reference::Hi(label) => {
+        if let Some(address) = relocate(RelocationKind::Hi16, label) {
+          word |= (address.wrapping_add(0x8000) >> 16) & 0xffff;
+        }
+      }
+      Reference::Lo(label) => {
+        if let Some(address) = relocate(RelocationKind::Lo16, label) {
+          word |= address & 0xffff;
+        }
+      }
+    }
+
+    text.push(word);
+  }
+
+  Ok(Assembly {
+    layout,
+    text,
+    data,
+    symbols,
+    relocations,
+    undefined,
+    entry: program.text_section.entrypoint.clone(),
+  })
+}
@@ -0,0 +1,357 @@
+// Writes an assembled program as an ELF32 relocatable object (`.o`) for the o32 ABI.
+//
+// The object holds `.text`, `.data`, the relocations of `.text` in `.rel.text`, and a symbol table.
+// As GNU as does, relocations against symbols defined in the object point to the section symbol
+// and keep the offset of the symbol in the instruction, while undefined symbols are left to the
+// linker by name.
+
+use celestial_hub_astrolabe::ast::Program;
+
+use super::assembler::{
+  assemble_for, Branches, Endianness, Environment, Layout, RelocationKind, Section,
+};
+
+const HEADER_SIZE: u32 = 52;
+const SECTION_HEADER_SIZE: u32 = 40;
+const SYMBOL_SIZE: u32 = 16;
+const RELOCATION_SIZE: u32 = 8;
+
+const EM_MIPS: u16 = 8;
+const ET_REL: u16 = 1;
+const EF_MIPS_NOREORDER: u32 = 0x0000_0001;
+const EF_MIPS_ABI_O32: u32 = 0x0000_1000;
+const EF_MIPS_ARCH_32: u32 = 0x5000_0000;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_REL: u32 = 9;
+
+const SHF_WRITE: u32 = 0x1;
+const SHF_ALLOC: u32 = 0x2;
+const SHF_EXECINSTR: u32 = 0x4;
+const SHF_INFO_LINK: u32 = 0x40;
+
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STT_NOTYPE: u8 = 0;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STT_SECTION: u8 = 3;
+
+const R_MIPS_26: u32 = 4;
+const R_MIPS_HI16: u32 = 5;
+const R_MIPS_LO16: u32 = 6;
+
+// Section header indices
+const TEXT: u16 = 1;
+const DATA: u16 = 2;
+const SYMTAB: u32 = 4;
+const STRTAB: u32 = 5;
+const SHSTRTAB: u16 = 6;
+
+struct ElfSymbol {
+  name: u32,
+  value: u32,
+  info: u8,
+  section: u16,
+}
+
+struct StringTable {
+  bytes: Vec<u8>,
+}
+
+impl StringTable {
+  fn new() -> Self {
+    Self { bytes: vec![0] }
+  }
+
+  fn add(&mut self, name: &str) -> u32 {
+    let offset = self.bytes.len() as u32;
+    self.bytes.extend(name.as_bytes());
+    self.bytes.push(0);
+    offset
+  }
+}
+
+struct Writer {
+  bytes: Vec<u8>,
+  endianness: Endianness,
+}
+
+impl Writer {
+  fn u8(&mut self, value: u8) {
+    self.bytes.push(value);
+  }
+
+  fn u16(&mut self, value: u16) {
+    self.bytes.extend(self.endianness.half(value));
+  }
+
+  fn u32(&mut self, value: u32) {
+    self.bytes.extend(self.endianness.word(value));
+  }
+
+  fn align(&mut self, alignment: usize) {
+    while !self.bytes.len().is_multiple_of(alignment) {
+      self.bytes.push(0);
+    }
+  }
+}
+
+/// Assembles the program and writes it as a relocatable object. A `nop` fills the delay slot of
+/// every branch and jump, as the `noreorder` flag of the object tells linkers and disassemblers.
+pub fn object(program: &Program, endianness: Endianness) -> Result<Vec<u8>, String> {
+  let assembly = assemble_for(
+    program,
+    Layout::RELOCATABLE,
+    Environment::Hosted,
+    Branches::Delayed,
+  )?;
+
+  let mut strings = StringTable::new();
+  let mut symbols = vec![
+    ElfSymbol {
+      name: 0,
+      value: 0,
+      info: 0,
+      section: 0,
+    },
+    ElfSymbol {
+      name: 0,
+      value: 0,
+      info: (STB_LOCAL << 4) | STT_SECTION,
+      section: TEXT,
+    },
+    ElfSymbol {
+      name: 0,
+      value: 0,
+      info: (STB_LOCAL << 4) | STT_SECTION,
+      section: DATA,
+    },
+  ];
+
+  let defined = |global: bool| {
+    assembly
+      .symbols
+      .iter()
+      .filter(move |symbol| symbol.global == global)
+  };
+
+  // Local symbols have to come before the global ones
+  for symbol in defined(false).chain(defined(true)) {
+    let (kind, section) = match symbol.section {
+      Section::Text => (STT_NOTYPE, TEXT),
+      Section::Data => (STT_OBJECT, DATA),
+    };
+    let (binding, kind) = if symbol.global {
+      (STB_GLOBAL, STT_FUNC)
+    } else {
+      (STB_LOCAL, kind)
+    };
+
+    symbols.push(ElfSymbol {
+      name: strings.add(&symbol.name),
+      value: symbol.offset,
+      info: (binding << 4) | kind,
+      section,
+    });
+  }
+  let first_global = 3 + defined(false).count() as u32;
+
+  let first_undefined = symbols.len();
+  for name in &assembly.undefined {
+    symbols.push(ElfSymbol {
+      name: strings.add(name),
+      value: 0,
+      info: (STB_GLOBAL << 4) | STT_NOTYPE,
+      section: 0,
+    });
+  }
+
+  let relocations: Vec<(u32, u32)> = assembly
+    .relocations
+    .iter()
+    .map(|relocation| {
+      let symbol = match assembly.symbol(&relocation.symbol) {
+        Some(symbol) => match symbol.section {
+          Section::Text => TEXT as usize,
+          Section::Data => DATA as usize,
+        },
+        None => {
+          first_undefined
+            + assembly
+              .undefined
+              .iter()
+              .position(|name| *name == relocation.symbol)
+              .expect("undefined symbol not recorded")
+        }
+      };
+      let kind = match relocation.kind {
+        RelocationKind::Hi16 => R_MIPS_HI16,
+        RelocationKind::Lo16 => R_MIPS_LO16,
+        RelocationKind::Jump26 => R_MIPS_26,
+      };
+
+      (relocation.offset, ((symbol as u32) << 8) | kind)
+    })
+    .collect();
+
+  let mut section_names = StringTable::new();
+  let names = [
+    0,
+    section_names.add(".text"),
+    section_names.add(".data"),
+    section_names.add(".rel.text"),
+    section_names.add(".symtab"),
+    section_names.add(".strtab"),
+    section_names.add(".shstrtab"),
+  ];
+
+  let mut writer = Writer {
+    bytes: vec![0; HEADER_SIZE as usize],
+    endianness,
+  };
+
+  let text_offset = writer.bytes.len() as u32;
+  for word in &assembly.text {
+    writer.u32(*word);
+  }
+
+  let data_offset = writer.bytes.len() as u32;
+  writer.bytes.extend(&assembly.data);
+  writer.align(4);
+
+  let rel_offset = writer.bytes.len() as u32;
+  for (offset, info) in &relocations {
+    writer.u32(*offset);
+    writer.u32(*info);
+  }
+
+  let symtab_offset = writer.bytes.len() as u32;
+  for symbol in &symbols {
+    writer.u32(symbol.name);
+    writer.u32(symbol.value);
+    writer.u32(0);
+    writer.u8(symbol.info);
+    writer.u8(0);
+    writer.u16(symbol.section);
+  }
+
+  let strtab_offset = writer.bytes.len() as u32;
+  writer.bytes.extend(&strings.bytes);
+
+  let shstrtab_offset = writer.bytes.len() as u32;
+  writer.bytes.extend(&section_names.bytes);
+  writer.align(4);
+
+  let section_headers_offset = writer.bytes.len() as u32;
+
+  // (type, flags, offset, size, link, info, alignment, entry size)
+  let sections = [
+    (0, 0, 0, 0, 0, 0, 0, 0),
+    (
+      SHT_PROGBITS,
+      SHF_ALLOC | SHF_EXECINSTR,
+      text_offset,
+      assembly.text.len() as u32 * 4,
+      0,
+      0,
+      4,
+      0,
+    ),
+    (
+      SHT_PROGBITS,
+      SHF_WRITE | SHF_ALLOC,
+      data_offset,
+      assembly.data.len() as u32,
+      0,
+      0,
+      1,
+      0,
+    ),
+    (
+      SHT_REL,
+      SHF_INFO_LINK,
+      rel_offset,
+      relocations.len() as u32 * RELOCATION_SIZE,
+      SYMTAB,
+      TEXT as u32,
+      4,
+      RELOCATION_SIZE,
+    ),
+    (
+      SHT_SYMTAB,
+      0,
+      symtab_offset,
+      symbols.len() as u32 * SYMBOL_SIZE,
+      STRTAB,
+      first_global,
+      4,
+      SYMBOL_SIZE,
+    ),
+    (
+      SHT_STRTAB,
+      0,
+      strtab_offset,
+      strings.bytes.len() as u32,
+      0,
+      0,
+      1,
+      0,
+    ),
+    (
+      SHT_STRTAB,
+      0,
+      shstrtab_offset,
+      section_names.bytes.len() as u32,
+      0,
+      0,
+      1,
+      0,
+    ),
+  ];
+
+  for (name, (kind, flags, offset, size, link, info, alignment, entry_size)) in
+    names.iter().zip(sections)
+  {
+    writer.u32(*name);
+    writer.u32(kind);
+    writer.u32(flags);
+    writer.u32(0);
+    writer.u32(offset);
+    writer.u32(size);
+    writer.u32(link);
+    writer.u32(info);
+    writer.u32(alignment);
+    writer.u32(entry_size);
+  }
+
+  let body = std::mem::take(&mut writer.bytes);
+  writer.bytes.extend(b"\x7fELF");
+  writer.u8(1); // ELFCLASS32
+  writer.u8(match endianness {
+    Endianness::Little => 1,
+    Endianness::Big => 2,
+  });
+  writer.u8(1); // EV_CURRENT
+  writer.bytes.resize(16, 0);
+  writer.u16(ET_REL);
+  writer.u16(EM_MIPS);
+  writer.u32(1);
+  writer.u32(0); // entry
+  writer.u32(0); // program headers
+  writer.u32(section_headers_offset);
+  writer.u32(EF_MIPS_ARCH_32 | EF_MIPS_ABI_O32 | EF_MIPS_NOREORDER);
+  writer.u16(HEADER_SIZE as u16);
+  writer.u16(0);
+  writer.u16(0);
+  writer.u16(SECTION_HEADER_SIZE as u16);
+  writer.u16(sections.len() as u16);
+  writer.u16(SHSTRTAB);
+
+  let mut bytes = writer.bytes;
+  bytes.extend(&body[HEADER_SIZE as usize..]);
+
+  Ok(bytes)
+}
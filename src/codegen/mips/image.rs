@@ -0,0 +1,126 @@
+// Memory images of an assembled program, to load into the instruction and data memories of a CPU
+// built in Logisim or described in an HDL.
+//
+// The program is assembled for bare metal, so it needs nothing but the devices of `target::mmio`.
+// Both images hold 32-bit words, the first one being at the base address of its section.
+//
+// Branches and jumps are taken at once, for a single-cycle CPU, unless a `nop` is asked for in
+// their delay slots, for a pipelined one that runs the next instruction before taking them. The
+// header of the `.text` image tells which. `halt` is `1000ffff`, `beq $zero, $zero, -1`, a branch
+// to itself that the CPU spins on once the program is over.
+
+use celestial_hub_astrolabe::ast::Program;
+
+use super::assembler::{assemble_for, Branches, Endianness, Environment, Layout};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageFormat {
+  /// `v2.0 raw` files read by the RAM and ROM components of Logisim
+  Logisim,
+  /// Hexadecimal words for Verilog's `$readmemh`
+  Readmemh,
+  /// Memory Initialization File, for Quartus
+  Mif,
+  /// Raw words, in the byte order of the CPU
+  Bin,
+}
+
+#[derive(Debug)]
+pub struct Images {
+  /// Contents of the instruction memory
+  pub text: Vec<u8>,
+  /// Contents of the data memory
+  pub data: Vec<u8>,
+}
+
+/// Assembles the program for bare metal and renders its two sections as memory images.
+pub fn images(
+  program: &Program,
+  layout: Layout,
+  format: ImageFormat,
+  endianness: Endianness,
+  branches: Branches,
+) -> Result<Images, String> {
+  let assembly = assemble_for(program, layout, Environment::BareMetal, branches)?;
+  assembly.ensure_linked()?;
+
+  // Words as the CPU loads them from the bytes of `.data`
+  let data: Vec<u32> = assembly
+    .data
+    .chunks(4)
+    .map(|chunk| {
+      let mut bytes = [0; 4];
+      bytes[..chunk.len()].copy_from_slice(chunk);
+      match endianness {
+        Endianness::Big => u32::from_be_bytes(bytes),
+        Endianness::Little => u32::from_le_bytes(bytes),
+      }
+    })
+    .collect();
+
+  Ok(Images {
+    text: render(
+      format,
+      &format!(
+        ".text at 0x{:08x}, {} words, {}",
+        layout.text_base,
+        assembly.text.len(),
+        match branches {
+          Branches::Immediate => "branches without delay slots",
+          Branches::Delayed => "a nop in the delay slot of each branch",
+        }
+      ),
+      &assembly.text,
+      endianness,
+    ),
+    data: render(
+      format,
+      &format!(".data at 0x{:08x}, {} words", layout.data_base, data.len()),
+      &data,
+      endianness,
+    ),
+  })
+}
+
+// The header is a comment, in the formats that have them
+fn render(format: ImageFormat, header: &str, words: &[u32], endianness: Endianness) -> Vec<u8> {
+  let text = match format {
+    ImageFormat::Bin => {
+      return words
+        .iter()
+        .flat_map(|word| endianness.word(*word))
+        .collect()
+    }
+    ImageFormat::Logisim => {
+      let mut text = String::from("v2.0 raw\n");
+      for line in words.chunks(8) {
+        let line: Vec<String> = line.iter().map(|word| format!("{word:08x}")).collect();
+        text += &line.join(" ");
+        text += "\n";
+      }
+      text
+    }
+    ImageFormat::Readmemh => {
+      let mut text = format!("// {header}\n");
+      for word in words {
+        text += &format!("{word:08x}\n");
+      }
+      text
+    }
+    ImageFormat::Mif => {
+      // Quartus rejects memories without words
+      let depth = words.len().max(1);
+      let mut text = format!(
+        "-- {header}\nDEPTH = {depth};\nWIDTH = 32;\nADDRESS_RADIX = HEX;\nDATA_RADIX = HEX;\nCONTENT\nBEGIN\n"
+      );
+      for index in 0..depth {
+        let word = words.get(index).copied().unwrap_or_default();
+        text += &format!("{index:x} : {word:08x};\n");
+      }
+      text += "END;\n";
+      text
+    }
+  };
+
+  text.into_bytes()
+}
@@ -0,0 +1,487 @@
+// Simulator for the programs `MipsCodegen` produces, so they run without MARS or SPIM.
+//
+// The program is assembled with the MARS memory layout and its machine words are executed, which
+// also runs the expansions of the pseudo-instructions. As in MARS, memory is little endian,
+// branches have no delay slots, and the simulator serves the SPIM/MARS syscalls itself.
+
+use std::{collections::HashMap, ops::Range};
+
+use celestial_hub_astrolabe::ast::Program;
+
+use crate::runtime::Io;
+
+use super::{
+  assembler::{assemble, funct, opcode, Assembly, Layout},
+  target::syscalls,
+};
+
+/// Initial value of `$sp`, as in MARS
+pub const STACK_POINTER: u32 = 0x7fff_effc;
+/// Initial value of `$gp`, as in MARS
+pub const GLOBAL_POINTER: u32 = 0x1000_8000;
+/// First address returned by `sbrk`, as in MARS
+pub const HEAP_BASE: u32 = 0x1004_0000;
+/// The stack segment spans from here to the end of user memory
+pub const STACK_LIMIT: u32 = 0x7f00_0000;
+const STACK_END: u32 = 0x8000_0000;
+
+pub const DEFAULT_MAX_STEPS: u64 = 10_000_000;
+
+const PAGE_SIZE: usize = 4096;
+
+const V0: u32 = 2;
+const A0: u32 = 4;
+const A1: u32 = 5;
+const GP: u32 = 28;
+const SP: u32 = 29;
+const RA: u32 = 31;
+
+/// Why the program stopped before exiting.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fault {
+  /// Jumped outside of `.text`, or ran past its last instruction
+  InvalidPc(u32),
+  InvalidInstruction(u32),
+  UnalignedAccess(u32),
+  /// Accessed an address outside of `.text`, `.data`, the heap and the stack
+  Unmapped(u32),
+  /// Wrote into `.text`
+  ReadOnly(u32),
+  /// `add`, `addi` or `sub` overflowed
+  Overflow,
+  Break,
+  UnknownSyscall(u32),
+  Io(String),
+  StepLimitExceeded(u64),
+}
+
+impl std::fmt::Display for Fault {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Fault::InvalidPc(pc) => write!(f, "no instruction at {pc:#010x}"),
+      Fault::InvalidInstruction(word) => write!(f, "invalid instruction {word:#010x}"),
+      Fault::UnalignedAccess(address) => write!(f, "unaligned access to {address:#010x}"),
+      Fault::Unmapped(address) => write!(f, "access to unmapped address {address:#010x}"),
+      Fault::ReadOnly(address) => write!(f, "write to read-only address {address:#010x}"),
+      Fault::Overflow => write!(f, "arithmetic overflow"),
+      Fault::Break => write!(f, "break"),
+      Fault::UnknownSyscall(service) => write!(f, "unknown syscall {service}"),
+      Fault::Io(message) => write!(f, "I/O error: {message}"),
+      Fault::StepLimitExceeded(limit) => {
+        write!(f, "program did not finish after {limit} instructions")
+      }
+    }
+  }
+}
+
+impl std::error::Error for Fault {}
+
+impl From<std::io::Error> for Fault {
+  fn from(error: std::io::Error) -> Self {
+    Fault::Io(error.to_string())
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Exit {
+  /// Through the `exit` (and `halt`) or `exit2` syscalls
+  Exited(i32),
+  Faulted {
+    pc: u32,
+    fault: Fault,
+  },
+}
+
+/// How a simulation ended.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+  pub exit: Exit,
+  /// Machine instructions executed, pseudo-instructions count as their expansion
+  pub instructions: u64,
+}
+
+impl std::fmt::Display for Report {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match &self.exit {
+      Exit::Exited(code) => write!(
+        f,
+        "Exited with code {code} after {} instructions",
+        self.instructions
+      ),
+      Exit::Faulted { pc, fault } => write!(
+        f,
+        "Fault at {pc:#010x} after {} instructions: {fault}",
+        self.instructions
+      ),
+    }
+  }
+}
+
+/// Byte addressed memory, allocated a page at a time as it is written.
+#[derive(Debug, Default)]
+pub struct Memory {
+  pages: HashMap<u32, Box<[u8; PAGE_SIZE]>>,
+}
+
+impl Memory {
+  pub fn read_byte(&self, address: u32) -> u8 {
+    let (page, offset) = (address / PAGE_SIZE as u32, address as usize % PAGE_SIZE);
+    self.pages.get(&page).map_or(0, |page| page[offset])
+  }
+
+  pub fn write_byte(&mut self, address: u32, value: u8) {
+    let (page, offset) = (address / PAGE_SIZE as u32, address as usize % PAGE_SIZE);
+    self
+      .pages
+      .entry(page)
+      .or_insert_with(|| Box::new([0; PAGE_SIZE]))[offset] = value;
+  }
+
+  /// Reads `size` bytes as a little endian value.
+  pub fn read(&self, address: u32, size: u32) -> u32 {
+    (0..size).rev().fold(0, |value, index| {
+      (value << 8) | self.read_byte(address.wrapping_add(index)) as u32
+    })
+  }
+
+  /// Writes the `size` lower bytes of `value`, in little endian.
+  pub fn write(&mut self, address: u32, size: u32, value: u32) {
+    for index in 0..size {
+      self.write_byte(address.wrapping_add(index), (value >> (index * 8)) as u8);
+    }
+  }
+}
+
+pub struct Simulator {
+  pub registers: [u32; 32],
+  pub hi: u32,
+  pub lo: u32,
+  pub pc: u32,
+  pub memory: Memory,
+  /// Machine instructions executed so far
+  pub instructions: u64,
+  /// The program faults when it executes more instructions than this
+  pub max_steps: u64,
+  text: Range<u32>,
+  data_base: u32,
+  /// End of the heap, moved by `sbrk`
+  heap_end: u32,
+}
+
+impl Simulator {
+  /// Loads an assembled program, which must not depend on symbols it does not define.
+  pub fn new(assembly: &Assembly) -> Result<Self, String> {
+    assembly.ensure_linked()?;
+
+    let Layout {
+      text_base,
+      data_base,
+    } = assembly.layout;
+
+    let mut memory = Memory::default();
+    for (index, word) in assembly.text.iter().enumerate() {
+      memory.write(text_base + index as u32 * 4, 4, *word);
+    }
+    for (index, byte) in assembly.data.iter().enumerate() {
+      memory.write_byte(data_base + index as u32, *byte);
+    }
+
+    let mut registers = [0; 32];
+    registers[SP as usize] = STACK_POINTER;
+    registers[GP as usize] = GLOBAL_POINTER;
+
+    let data_end = data_base + (assembly.data.len() as u32).next_multiple_of(4);
+
+    Ok(Self {
+      registers,
+      hi: 0,
+      lo: 0,
+      pc: assembly.entry_address()?,
+      memory,
+      instructions: 0,
+      max_steps: DEFAULT_MAX_STEPS,
+      text: text_base..text_base + assembly.text.len() as u32 * 4,
+      data_base,
+      heap_end: data_end.max(HEAP_BASE),
+    })
+  }
+
+  /// Runs until the program exits or faults.
+  pub fn run(&mut self, io: &mut Io) -> Report {
+    let exit = loop {
+      let pc = self.pc;
+      if self.instructions >= self.max_steps {
+        break Exit::Faulted {
+          pc,
+          fault: Fault::StepLimitExceeded(self.max_steps),
+        };
+      }
+
+      match self.step(io) {
+        Ok(None) => {}
+        Ok(Some(code)) => break Exit::Exited(code),
+        Err(fault) => break Exit::Faulted { pc, fault },
+      }
+    };
+    let _ = io.output.flush();
+
+    Report {
+      exit,
+      instructions: self.instructions,
+    }
+  }
+
+  fn set(&mut self, register: u32, value: u32) {
+    if register != 0 {
+      self.registers[register as usize] = value;
+    }
+  }
+
+  fn check(&self, address: u32, size: u32, write: bool) -> Result<(), Fault> {
+    if !address.is_multiple_of(size) {
+      return Err(Fault::UnalignedAccess(address));
+    }
+
+    if self.text.contains(&address) {
+      return match write {
+        true => Err(Fault::ReadOnly(address)),
+        false => Ok(()),
+      };
+    }
+
+    if (self.data_base..self.heap_end).contains(&address)
+      || (STACK_LIMIT..STACK_END).contains(&address)
+    {
+      Ok(())
+    } else {
+      Err(Fault::Unmapped(address))
+    }
+  }
+
+  fn load(&self, address: u32, size: u32) -> Result<u32, Fault> {
+    self.check(address, size, false)?;
+    Ok(self.memory.read(address, size))
+  }
+
+  fn store(&mut self, address: u32, size: u32, value: u32) -> Result<(), Fault> {
+    self.check(address, size, true)?;
+    self.memory.write(address, size, value);
+    Ok(())
+  }
+
+  /// Executes one instruction, returning the exit code once the program exits.
+  pub fn step(&mut self, io: &mut Io) -> Result<Option<i32>, Fault> {
+    if !self.text.contains(&self.pc) || !self.pc.is_multiple_of(4) {
+      return Err(Fault::InvalidPc(self.pc));
+    }
+    let word = self.memory.read(self.pc, 4);
+    self.instructions += 1;
+
+    let op = word >> 26;
+    let (rs, rt, rd) = ((word >> 21) & 31, (word >> 16) & 31, (word >> 11) & 31);
+    let shamt = (word >> 6) & 31;
+    let immediate = word as u16 as u32;
+    let signed = word as u16 as i16 as i32;
+    let (s, t) = (self.registers[rs as usize], self.registers[rt as usize]);
+    let address = s.wrapping_add(signed as u32);
+
+    let mut next = self.pc.wrapping_add(4);
+    let branch = next.wrapping_add((signed << 2) as u32);
+    let jump = (next & 0xf000_0000) | ((word & 0x03ff_ffff) << 2);
+    let invalid = Err(Fault::InvalidInstruction(word));
+
+    match op {
+      opcode::SPECIAL => match word & 63 {
+        funct::SLL => self.set(rd, t << shamt),
+        funct::SRL => self.set(rd, t >> shamt),
+        funct::SRA => self.set(rd, ((t as i32) >> shamt) as u32),
+        funct::SLLV => self.set(rd, t << (s & 31)),
+        funct::SRLV => self.set(rd, t >> (s & 31)),
+        funct::SRAV => self.set(rd, ((t as i32) >> (s & 31)) as u32),
+        funct::JR => next = s,
+        funct::JALR => {
+          self.set(rd, next);
+          next = s;
+        }
+        funct::SYSCALL => {
+          self.pc = next;
+          return self.syscall(io);
+        }
+        funct::BREAK => return Err(Fault::Break),
+        funct::MFHI => self.set(rd, self.hi),
+        funct::MTHI => self.hi = s,
+        funct::MFLO => self.set(rd, self.lo),
+        funct::MTLO => self.lo = s,
+        funct::MULT => {
+          let product = s as i32 as i64 * t as i32 as i64;
+          (self.hi, self.lo) = ((product >> 32) as u32, product as u32);
+        }
+        funct::MULTU => {
+          let product = s as u64 * t as u64;
+          (self.hi, self.lo) = ((product >> 32) as u32, product as u32);
+        }
+        // As on hardware, dividing by zero leaves `hi` and `lo` as they were
+        funct::DIV if t != 0 => {
+          self.lo = (s as i32).wrapping_div(t as i32) as u32;
+          self.hi = (s as i32).wrapping_rem(t as i32) as u32;
+        }
+        funct::DIVU if t != 0 => (self.hi, self.lo) = (s % t, s / t),
+        funct::DIV | funct::DIVU => {}
+        funct::ADD => {
+          let sum = (s as i32).checked_add(t as i32).ok_or(Fault::Overflow)?;
+          self.set(rd, sum as u32);
+        }
+        funct::ADDU => self.set(rd, s.wrapping_add(t)),
+        funct::SUB => {
+          let difference = (s as i32).checked_sub(t as i32).ok_or(Fault::Overflow)?;
+          self.set(rd, difference as u32);
+        }
+        funct::SUBU => self.set(rd, s.wrapping_sub(t)),
+        funct::AND => self.set(rd, s & t),
+        funct::OR => self.set(rd, s | t),
+        funct::XOR => self.set(rd, s ^ t),
+        funct::NOR => self.set(rd, !(s | t)),
+        funct::SLT => self.set(rd, ((s as i32) < (t as i32)) as u32),
+        funct::SLTU => self.set(rd, (s < t) as u32),
+        _ => return invalid,
+      },
+      opcode::REGIMM => match rt {
+        0 if (s as i32) < 0 => next = branch,
+        1 if (s as i32) >= 0 => next = branch,
+        0 | 1 => {}
+        _ => return invalid,
+      },
+      opcode::J => next = jump,
+      opcode::JAL => {
+        self.set(RA, next);
+        next = jump;
+      }
+      opcode::BEQ if s == t => next = branch,
+      opcode::BNE if s != t => next = branch,
+      opcode::BLEZ if s as i32 <= 0 => next = branch,
+      opcode::BGTZ if s as i32 > 0 => next = branch,
+      opcode::BEQ | opcode::BNE | opcode::BLEZ | opcode::BGTZ => {}
+      opcode::ADDI => {
+        let sum = (s as i32).checked_add(signed).ok_or(Fault::Overflow)?;
+        self.set(rt, sum as u32);
+      }
+      opcode::ADDIU => self.set(rt, address),
+      opcode::SLTI => self.set(rt, ((s as i32) < signed) as u32),
+      opcode::SLTIU => self.set(rt, (s < signed as u32) as u32),
+      opcode::ANDI => self.set(rt, s & immediate),
+      opcode::ORI => self.set(rt, s | immediate),
+      opcode::XORI => self.set(rt, s ^ immediate),
+      opcode::LUI => self.set(rt, immediate << 16),
+      opcode::SPECIAL2 if word & 63 == funct::MUL => {
+        self.set(rd, (s as i32).wrapping_mul(t as i32) as u32);
+      }
+      opcode::LB => {
+        let value = self.load(address, 1)? as u8 as i8;
+        self.set(rt, value as u32);
+      }
+      opcode::LH => {
+        let value = self.load(address, 2)? as u16 as i16;
+        self.set(rt, value as u32);
+      }
+      opcode::LW => {
+        let value = self.load(address, 4)?;
+        self.set(rt, value);
+      }
+      opcode::LBU => {
+        let value = self.load(address, 1)?;
+        self.set(rt, value);
+      }
+      opcode::LHU => {
+        let value = self.load(address, 2)?;
+        self.set(rt, value);
+      }
+      opcode::SB => self.store(address, 1, t)?,
+      opcode::SH => self.store(address, 2, t)?,
+      opcode::SW => self.store(address, 4, t)?,
+      _ => return invalid,
+    }
+
+    self.pc = next;
+    Ok(None)
+  }
+
+  fn read_line(io: &mut Io) -> Result<String, Fault> {
+    let mut line = String::new();
+    io.input.read_line(&mut line)?;
+    Ok(line)
+  }
+
+  /// Serves the syscall selected by `$v0`, returning the exit code for `exit` and `exit2`.
+  fn syscall(&mut self, io: &mut Io) -> Result<Option<i32>, Fault> {
+    let (service, a0, a1) = (
+      self.registers[V0 as usize],
+      self.registers[A0 as usize],
+      self.registers[A1 as usize],
+    );
+
+    match service as i32 {
+      syscalls::PRINT_INT => {
+        write!(io.output, "{}", a0 as i32)?;
+        io.output.flush()?;
+      }
+      syscalls::PRINT_STRING => {
+        let mut bytes = vec![];
+        let mut address = a0;
+        loop {
+          match self.load(address, 1)? as u8 {
+            0 => break,
+            byte => bytes.push(byte),
+          }
+          address = address.wrapping_add(1);
+        }
+        io.output.write_all(&bytes)?;
+        io.output.flush()?;
+      }
+      syscalls::READ_INT => {
+        // Like SPIM, anything that is not a number reads as zero
+        let value: i32 = Self::read_line(io)?.trim().parse().unwrap_or(0);
+        self.set(V0, value as u32);
+      }
+      syscalls::READ_STRING => {
+        let line = Self::read_line(io)?;
+        if a1 as i32 > 0 {
+          // At most `$a1 - 1` bytes are read, leaving room for the NUL terminator
+          let bytes = &line.as_bytes()[..line.len().min(a1 as usize - 1)];
+          for (index, byte) in bytes.iter().chain([&0]).enumerate() {
+            self.store(a0.wrapping_add(index as u32), 1, *byte as u32)?;
+          }
+        }
+      }
+      syscalls::SBRK => {
+        let end = self.heap_end;
+        self.heap_end = end.wrapping_add(a0.next_multiple_of(4));
+        self.set(V0, end);
+      }
+      syscalls::EXIT => return Ok(Some(0)),
+      syscalls::EXIT2 => return Ok(Some(a0 as i32)),
+      syscalls::PRINT_CHAR => {
+        io.output.write_all(&[a0 as u8])?;
+        io.output.flush()?;
+      }
+      syscalls::READ_CHAR => {
+        let byte = io.input.fill_buf()?.first().copied();
+        if byte.is_some() {
+          io.input.consume(1);
+        }
+        self.set(V0, byte.unwrap_or_default() as u32);
+      }
+      _ => return Err(Fault::UnknownSyscall(service)),
+    }
+
+    Ok(None)
+  }
+}
+
+/// Assembles the program with the MARS layout and runs it.
+pub fn simulate(program: &Program, max_steps: u64, io: &mut Io) -> Result<Report, String> {
+  let assembly = assemble(program, Layout::MARS)?;
+  let mut simulator = Simulator::new(&assembly)?;
+  simulator.max_steps = max_steps;
+
+  Ok(simulator.run(io))
+}
@@ -0,0 +1,108 @@
+// Facts about the MIPS32 target shared by the code generator and the tools built on its output.
+
+use crate::{
+  ast::VarType,
+  codegen::target::{CallingConvention, TargetInfo},
+};
+
+pub mod registers {
+  pub const ZERO: &str = "$zero";
+  /// Reserved for the assembler, which uses it to expand pseudo-instructions
+  pub const AT: &str = "$at";
+  pub const V0: &str = "$v0";
+  pub const A0: &str = "$a0";
+  pub const A1: &str = "$a1";
+  pub const SP: &str = "$sp";
+  pub const RA: &str = "$ra";
+  pub const ARGUMENTS: [&str; 4] = ["$a0", "$a1", "$a2", "$a3"];
+
+  pub fn argument(index: usize) -> String {
+    format!("$a{index}")
+  }
+
+  /// Number of temporary registers, `$t0` to `$t9`
+  pub const TEMPORARIES: usize = 10;
+
+  /// The temporary register `index`, if there is one.
+  pub fn temporary(index: usize) -> Option<String> {
+    (index < TEMPORARIES).then(|| format!("$t{index}"))
+  }
+
+  const NAMES: [&str; 32] = [
+    "$zero", "$at", "$v0", "$v1", "$a0", "$a1", "$a2", "$a3", "$t0", "$t1", "$t2", "$t3", "$t4",
+    "$t5", "$t6", "$t7", "$s0", "$s1", "$s2", "$s3", "$s4", "$s5", "$s6", "$s7", "$t8", "$t9",
+    "$k0", "$k1", "$gp", "$sp", "$fp", "$ra",
+  ];
+
+  /// Number of a register given by its conventional name (`$t0`) or its number (`$8`).
+  pub fn number(name: &str) -> Option<u32> {
+    if let Some(index) = NAMES.iter().position(|candidate| *candidate == name) {
+      return Some(index as u32);
+    }
+
+    match name.strip_prefix('$')?.parse() {
+      Ok(number) if number < 32 => Some(number),
+      _ => None,
+    }
+  }
+
+  pub fn name(number: u32) -> &'static str {
+    NAMES[number as usize]
+  }
+}
+
+/// Service numbers of the SPIM/MARS syscalls, loaded into `$v0` before a `syscall`.
+pub mod syscalls {
+  pub const PRINT_INT: i32 = 1;
+  pub const PRINT_STRING: i32 = 4;
+  pub const READ_INT: i32 = 5;
+  pub const READ_STRING: i32 = 8;
+  pub const SBRK: i32 = 9;
+  pub const EXIT: i32 = 10;
+  pub const PRINT_CHAR: i32 = 11;
+  pub const READ_CHAR: i32 = 12;
+  /// Exits with the status in `$a0`
+  pub const EXIT2: i32 = 17;
+}
+
+/// Addresses of the devices a program assembled for bare metal does its I/O with.
+///
+/// They sit in the last 16 bytes of the address space, so they are reached with a negative offset
+/// from `$zero`. Every access is a full word.
+pub mod mmio {
+  /// Writing prints the character in the low byte
+  pub const PUTCHAR: u32 = 0xffff_fff0;
+  /// Writing prints the word as a signed decimal integer
+  pub const PRINT_INT: u32 = 0xffff_fff4;
+  /// Reading blocks until a character is typed, and returns it
+  pub const GETCHAR: u32 = 0xffff_fff8;
+  /// Reading blocks until an integer is entered, and returns it
+  pub const READ_INT: u32 = 0xffff_fffc;
+}
+
+pub static INFO: TargetInfo = TargetInfo {
+  name: "mips",
+  description: "MIPS assembly, for MARS or SPIM",
+  extension: "s",
+  word_size: 4,
+  pointer_size: 4,
+  var_types: &[
+    VarType::I8,
+    VarType::I16,
+    VarType::I32,
+    VarType::U8,
+    VarType::U16,
+    VarType::U32,
+    VarType::Bool,
+    VarType::Str,
+    VarType::Ptr,
+    VarType::Void,
+  ],
+  calling_convention: CallingConvention::Registers {
+    arguments: &registers::ARGUMENTS,
+    return_value: registers::V0,
+    return_address: registers::RA,
+  },
+  max_values: Some(registers::TEMPORARIES),
+  compares_strings: false,
+};
@@ -1,10 +1,42 @@
-use crate::ast::Statement;
+use crate::{ast::Statement, bytecode};
 
-use self::context::Context;
+use self::{context::Context, error::CodegenError, target::TargetInfo};
+pub mod cfg;
 pub(crate) mod context;
-#[allow(warnings)] // TODO: remove me later
+pub mod error;
 pub mod mips;
+pub mod target;
+pub mod wasm;
+
+/// What a backend produces, before it is rendered or written to a file.
+#[derive(Debug)]
+pub enum Artifact {
+  Mips(celestial_hub_astrolabe::ast::Program),
+  Wat(String),
+  Bytecode(bytecode::Module),
+}
+
+impl Artifact {
+  /// The bytes written by `compass build`.
+  pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+    match self {
+      Artifact::Bytecode(module) => module.to_bytes(),
+      _ => Ok(self.to_string().into_bytes()),
+    }
+  }
+}
+
+impl std::fmt::Display for Artifact {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Artifact::Mips(program) => write!(f, "{program}"),
+      Artifact::Wat(module) => write!(f, "{module}"),
+      Artifact::Bytecode(module) => write!(f, "{module}"),
+    }
+  }
+}
 
 pub trait Codegen {
-  fn generate(&self, ast: Vec<Statement>, context: &mut Context) -> Result<String, String>;
+  fn info(&self) -> &'static TargetInfo;
+  fn generate(&self, ast: Vec<Statement>, context: &mut Context) -> Result<Artifact, CodegenError>;
 }
@@ -0,0 +1,95 @@
+// Registry of the backends Compass can generate code with, keyed by their `--target` name.
+
+use crate::{
+  ast::{Location, Statement, VarType},
+  bytecode::compiler::BytecodeCodegen,
+};
+
+use super::{error::CodegenError, mips::MipsCodegen, wasm::WatCodegen, Artifact, Codegen};
+
+/// How arguments and results travel between a caller and a callee.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CallingConvention {
+  /// Arguments are passed in registers, in order.
+  Registers {
+    arguments: &'static [&'static str],
+    return_value: &'static str,
+    return_address: &'static str,
+  },
+  /// Arguments are pushed on the operand stack and become the locals of the callee.
+  Stack,
+}
+
+/// Facts about a target that the rest of the compiler can rely on without knowing the backend.
+#[derive(Clone, Debug)]
+pub struct TargetInfo {
+  /// Name used to select the target with `--target`
+  pub name: &'static str,
+  pub description: &'static str,
+  /// Extension of the files generated for this target
+  pub extension: &'static str,
+  /// Size of a machine word, in bytes
+  pub word_size: u32,
+  /// Size of a `ptr`, in bytes
+  pub pointer_size: u32,
+  pub var_types: &'static [VarType],
+  pub calling_convention: CallingConvention,
+  /// Values a program can hold, variables and temporaries alike, when registers are never reused
+  pub max_values: Option<usize>,
+  /// Whether conditions can compare strings
+  pub compares_strings: bool,
+}
+
+impl TargetInfo {
+  pub fn supports(&self, var_type: VarType) -> bool {
+    self.var_types.contains(&var_type)
+  }
+
+  /// Ensures every variable, argument and return type of the program can be represented on the
+  /// target.
+  pub fn check(&self, ast: &[Statement]) -> Result<(), CodegenError> {
+    let check = |var_type: VarType, location: &Location| {
+      if self.supports(var_type) {
+        Ok(())
+      } else {
+        Err(CodegenError::UnsupportedType {
+          var_type,
+          target: self.name,
+          location: Some(location.clone()),
+        })
+      }
+    };
+
+    for statement in ast {
+      match statement {
+        Statement::VariableDeclaration(variable) => check(variable.var_type, &variable.location)?,
+        Statement::FunctionDefinition(function) => {
+          check(function.return_type, &function.location)?;
+          for argument in &function.args {
+            check(argument.var_type, &argument.location)?;
+          }
+          self.check(&function.body)?;
+        }
+        _ => {}
+      }
+    }
+
+    Ok(())
+  }
+}
+
+pub static TARGETS: [&(dyn Codegen + Sync); 3] = [&MipsCodegen, &WatCodegen, &BytecodeCodegen];
+
+pub fn find(name: &str) -> Option<&'static (dyn Codegen + Sync)> {
+  TARGETS
+    .iter()
+    .find(|target| target.info().name == name)
+    .copied()
+}
+
+/// Generates code for the target named `name`, after checking it supports the program.
+pub fn generate(name: &str, ast: Vec<Statement>) -> Result<Artifact, CodegenError> {
+  let target = find(name).ok_or_else(|| format!("Unknown target `{name}`"))?;
+  target.info().check(&ast)?;
+  target.generate(ast, &mut Default::default())
+}
@@ -0,0 +1,811 @@
+// WebAssembly text format (WAT) backend.
+//
+// Variables are module globals, mirroring the MIPS backend where every variable owns a register
+// for the whole program. Builtins are imported from the host (`env.write_int` and friends), and
+// strings live in the exported linear memory as NUL-terminated bytes.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+  ast::{BinaryOperation, Condition, Expr, Function, Operand, Operator, Statement, VarType},
+  utils::unquote_string_literal,
+};
+
+use self::stackify::{stackify, Structured};
+use super::{
+  cfg::{ControlFlowGraph, Terminator},
+  context::Context,
+  error::CodegenError,
+  target::{CallingConvention, TargetInfo},
+  Artifact, Codegen,
+};
+
+mod stackify;
+
+pub struct WatCodegen;
+
+const PAGE_SIZE: u32 = 65536;
+
+pub static INFO: TargetInfo = TargetInfo {
+  name: "wat",
+  description: "WebAssembly text format",
+  extension: "wat",
+  word_size: 4,
+  pointer_size: 4,
+  var_types: &[
+    VarType::I8,
+    VarType::I16,
+    VarType::I32,
+    VarType::I64,
+    VarType::U8,
+    VarType::U16,
+    VarType::U32,
+    VarType::U64,
+    VarType::Bool,
+    VarType::F32,
+    VarType::F64,
+    VarType::Str,
+    VarType::Ptr,
+    VarType::Void,
+  ],
+  calling_convention: CallingConvention::Stack,
+  max_values: None,
+  compares_strings: true,
+};
+
+impl Codegen for WatCodegen {
+  fn info(&self) -> &'static TargetInfo {
+    &INFO
+  }
+
+  fn generate(
+    &self,
+    ast: Vec<Statement>,
+    _context: &mut Context,
+  ) -> Result<Artifact, CodegenError> {
+    let mut module = Module::default();
+
+    let mut functions = vec![];
+    module.collect(&ast, &mut functions);
+
+    let mut bodies = vec![];
+    for function in &functions {
+      let body = module.function(function);
+      bodies.push(body.map_err(|error| error.at(Some(&function.location)))?);
+    }
+    bodies.push(module.main(&ast)?);
+
+    Ok(Artifact::Wat(module.finish(bodies)))
+  }
+}
+
+#[derive(Default)]
+struct Module {
+  /// Builtins called by the program, imported from the host
+  imports: BTreeSet<String>,
+  /// User defined functions by name
+  functions: HashMap<String, Function>,
+  /// Type of the first declaration of each variable, which is the one the parser type checks
+  /// every use against
+  variable_types: HashMap<String, VarType>,
+  /// Type of the last declaration lowered of each variable in the current body, which is the
+  /// global its reads load from
+  declared_types: HashMap<String, VarType>,
+  /// Every global as (name, wasm type) in declaration order
+  globals: Vec<(String, &'static str)>,
+  /// Strings already placed in memory and their address
+  strings: HashMap<String, u32>,
+  data: Vec<(u32, Vec<u8>)>,
+  data_end: u32,
+}
+
+// The function being lowered, its parameters shadow the globals.
+struct Scope<'function> {
+  params: HashMap<&'function str, VarType>,
+  return_type: VarType,
+  uses_dispatch: bool,
+}
+
+struct Body {
+  lines: Vec<String>,
+  depth: usize,
+}
+
+impl Body {
+  fn new(depth: usize) -> Self {
+    Self {
+      lines: vec![],
+      depth,
+    }
+  }
+
+  fn emit(&mut self, instruction: impl AsRef<str>) {
+    self.lines.push(format!(
+      "{}{}",
+      "  ".repeat(self.depth),
+      instruction.as_ref()
+    ));
+  }
+
+  fn open(&mut self, instruction: &str) {
+    self.emit(instruction);
+    self.depth += 1;
+  }
+
+  fn close(&mut self) {
+    self.depth -= 1;
+    self.emit("end");
+  }
+}
+
+fn value_type(var_type: VarType) -> &'static str {
+  match var_type {
+    VarType::I64 | VarType::U64 => "i64",
+    VarType::F32 => "f32",
+    VarType::F64 => "f64",
+    _ => "i32",
+  }
+}
+
+fn is_unsigned(var_type: VarType) -> bool {
+  matches!(
+    var_type,
+    VarType::U8 | VarType::U16 | VarType::U32 | VarType::U64 | VarType::Bool | VarType::Ptr
+  )
+}
+
+fn is_float(var_type: VarType) -> bool {
+  matches!(var_type, VarType::F32 | VarType::F64)
+}
+
+// Narrow integers wrap around like they would in a register of their own size
+fn narrow(var_type: VarType, body: &mut Body) {
+  match var_type {
+    VarType::I8 => body.emit("i32.extend8_s"),
+    VarType::I16 => body.emit("i32.extend16_s"),
+    VarType::U8 => {
+      body.emit("i32.const 255");
+      body.emit("i32.and");
+    }
+    VarType::U16 => {
+      body.emit("i32.const 65535");
+      body.emit("i32.and");
+    }
+    _ => {}
+  }
+}
+
+// Converts the value on top of the stack, like `Value::cast` does in the interpreter
+fn convert(from: VarType, to: VarType, body: &mut Body) {
+  let (from_value, to_value) = (value_type(from), value_type(to));
+  let sign = |var_type| if is_unsigned(var_type) { "u" } else { "s" };
+
+  match (from_value, to_value) {
+    _ if from_value == to_value => return,
+    ("i32", "i64") => body.emit(format!("i64.extend_i32_{}", sign(from))),
+    ("i64", "i32") => body.emit("i32.wrap_i64"),
+    ("f32", "f64") => body.emit("f64.promote_f32"),
+    ("f64", "f32") => body.emit("f32.demote_f64"),
+    (_, "f32" | "f64") => body.emit(format!("{to_value}.convert_{from_value}_{}", sign(from))),
+    _ => body.emit(format!("{to_value}.trunc_sat_{from_value}_{}", sign(to))),
+  }
+  narrow(to, body);
+}
+
+fn float_literal(value: f64) -> String {
+  if value.is_nan() {
+    "nan".to_string()
+  } else if value.is_infinite() {
+    if value > 0.0 { "inf" } else { "-inf" }.to_string()
+  } else {
+    format!("{value:?}")
+  }
+}
+
+fn global_id(name: &str, var_type: VarType, first_type: VarType) -> String {
+  if value_type(var_type) == value_type(first_type) {
+    format!("${name}")
+  } else {
+    format!("${name}@{}", value_type(var_type))
+  }
+}
+
+impl Module {
+  fn collect<'ast>(&mut self, statements: &'ast [Statement], functions: &mut Vec<&'ast Function>) {
+    for statement in statements {
+      match statement {
+        Statement::VariableDeclaration(variable) => {
+          let first_type = *self
+            .variable_types
+            .entry(variable.name.clone())
+            .or_insert(variable.var_type);
+          let id = global_id(&variable.name, variable.var_type, first_type);
+
+          if !self.globals.iter().any(|(global, _)| *global == id) {
+            self.globals.push((id, value_type(variable.var_type)));
+          }
+        }
+        Statement::FunctionDefinition(function) => {
+          self
+            .functions
+            .entry(function.name.clone())
+            .or_insert_with(|| function.clone());
+          functions.push(function);
+          self.collect(&function.body, functions);
+        }
+        _ => {}
+      }
+    }
+  }
+
+  fn finish(self, bodies: Vec<Vec<String>>) -> String {
+    let mut lines = vec!["(module".to_string()];
+
+    for builtin in &self.imports {
+      let signature = match builtin.as_str() {
+        "write_int" | "write_string" => "(param i32)",
+        "read_int" => "(result i32)",
+        "read_string" => "(param i32 i32)",
+        _ => unreachable!("unknown builtin {builtin}"),
+      };
+      lines.push(format!(
+        "  (import \"env\" \"{builtin}\" (func $env.{builtin} {signature}))"
+      ));
+    }
+
+    let pages = self.data_end.div_ceil(PAGE_SIZE).max(1);
+    lines.push(format!("  (memory (export \"memory\") {pages})"));
+
+    for (address, bytes) in &self.data {
+      let escaped: String = bytes
+        .iter()
+        .map(|byte| match byte {
+          b'"' | b'\\' => format!("\\{byte:02x}"),
+          0x20..=0x7e => (*byte as char).to_string(),
+          _ => format!("\\{byte:02x}"),
+        })
+        .collect();
+      lines.push(format!("  (data (i32.const {address}) \"{escaped}\")"));
+    }
+
+    for (id, value_type) in &self.globals {
+      lines.push(format!(
+        "  (global {id} (mut {value_type}) ({value_type}.const 0))"
+      ));
+    }
+
+    for body in bodies {
+      lines.extend(body);
+    }
+
+    lines.push(")".to_string());
+    lines.join("\n")
+  }
+
+  fn string(&mut self, literal: &str) -> u32 {
+    if let Some(address) = self.strings.get(literal) {
+      return *address;
+    }
+
+    let mut bytes = unquote_string_literal(literal).into_bytes();
+    bytes.push(0);
+
+    let address = self.reserve(bytes.len() as u32);
+    self.data.push((address, bytes));
+    self.strings.insert(literal.to_string(), address);
+
+    address
+  }
+
+  fn reserve(&mut self, size: u32) -> u32 {
+    let address = self.data_end;
+    // Keep every allocation word aligned
+    self.data_end += size.div_ceil(4) * 4;
+    address
+  }
+
+  fn function(&mut self, function: &Function) -> Result<Vec<String>, CodegenError> {
+    let mut header = format!("  (func ${}", function.name);
+    for arg in &function.args {
+      header.push_str(&format!(
+        " (param ${} {})",
+        arg.name,
+        value_type(arg.var_type)
+      ));
+    }
+    if function.return_type != VarType::Void {
+      header.push_str(&format!(" (result {})", value_type(function.return_type)));
+    }
+
+    let scope = Scope {
+      params: function
+        .args
+        .iter()
+        .map(|arg| (arg.name.as_str(), arg.var_type))
+        .collect(),
+      return_type: function.return_type,
+      uses_dispatch: false,
+    };
+
+    self.body(header, &function.body, scope)
+  }
+
+  fn main(&mut self, ast: &[Statement]) -> Result<Vec<String>, CodegenError> {
+    let scope = Scope {
+      params: HashMap::new(),
+      return_type: VarType::Void,
+      uses_dispatch: false,
+    };
+
+    self.body("  (func $__main (export \"main\")".to_string(), ast, scope)
+  }
+
+  fn body(
+    &mut self,
+    header: String,
+    statements: &[Statement],
+    mut scope: Scope,
+  ) -> Result<Vec<String>, CodegenError> {
+    let graph = ControlFlowGraph::build(statements)?;
+    let mut body = Body::new(2);
+    // Functions can be called from anywhere, so no body knows which declarations ran before it
+    self.declared_types.clear();
+
+    match stackify(&graph) {
+      Some(structured) => self.structured(&graph, &structured, &scope, &mut body)?,
+      None => {
+        scope.uses_dispatch = true;
+        self.dispatch(&graph, &scope, &mut body)?;
+      }
+    }
+    // Every path already returned, this only keeps the validator happy about the result type
+    body.emit("unreachable");
+
+    let mut lines = vec![header];
+    if scope.uses_dispatch {
+      lines.push("    (local $__pc i32)".to_string());
+    }
+    lines.extend(body.lines);
+    lines.push("  )".to_string());
+
+    Ok(lines)
+  }
+
+  fn structured(
+    &mut self,
+    graph: &ControlFlowGraph,
+    code: &[Structured],
+    scope: &Scope,
+    body: &mut Body,
+  ) -> Result<(), CodegenError> {
+    for item in code {
+      match item {
+        Structured::Block(inner) => {
+          body.open("block");
+          self.structured(graph, inner, scope, body)?;
+          body.close();
+        }
+        Structured::Loop(inner) => {
+          body.open("loop");
+          self.structured(graph, inner, scope, body)?;
+          body.close();
+        }
+        Structured::If {
+          condition,
+          then,
+          otherwise,
+          location,
+        } => {
+          self
+            .condition(condition, scope, body)
+            .map_err(|error| CodegenError::from(error).at(Some(location)))?;
+          body.open("if");
+          self.structured(graph, then, scope, body)?;
+          body.depth -= 1;
+          body.open("else");
+          self.structured(graph, otherwise, scope, body)?;
+          body.close();
+        }
+        Structured::Br(depth) => body.emit(format!("br {depth}")),
+        Structured::Code(block) => {
+          for statement in &graph.blocks[*block].statements {
+            self
+              .statement(statement, scope, body)
+              .map_err(|error| CodegenError::from(error).at(statement.location()))?;
+          }
+        }
+        Structured::Return => self.return_(scope, body),
+      }
+    }
+
+    Ok(())
+  }
+
+  // Fallback for irreducible graphs: a loop around a `br_table` on the index of the next block.
+  fn dispatch(
+    &mut self,
+    graph: &ControlFlowGraph,
+    scope: &Scope,
+    body: &mut Body,
+  ) -> Result<(), CodegenError> {
+    let count = graph.blocks.len();
+
+    body.emit(format!("i32.const {}", graph.entry));
+    body.emit("local.set $__pc");
+    body.open("loop");
+    for _ in 0..count {
+      body.open("block");
+    }
+
+    let targets: Vec<String> = (0..count).map(|i| i.to_string()).collect();
+    body.emit("local.get $__pc");
+    body.emit(format!("br_table {} {}", targets.join(" "), count - 1));
+
+    for (id, block) in graph.blocks.iter().enumerate() {
+      body.close();
+
+      for statement in &block.statements {
+        self
+          .statement(statement, scope, body)
+          .map_err(|error| CodegenError::from(error).at(statement.location()))?;
+      }
+
+      let loop_depth = (count - 1 - id) as u32;
+      let jump = |body: &mut Body, target: usize, depth: u32| {
+        body.emit(format!("i32.const {target}"));
+        body.emit("local.set $__pc");
+        body.emit(format!("br {depth}"));
+      };
+
+      match &block.terminator {
+        Terminator::Jump(target) => jump(body, *target, loop_depth),
+        Terminator::Branch {
+          condition,
+          then,
+          otherwise,
+          location,
+        } => {
+          self
+            .condition(condition, scope, body)
+            .map_err(|error| CodegenError::from(error).at(Some(location)))?;
+          body.open("if");
+          jump(body, *then, loop_depth + 1);
+          body.depth -= 1;
+          body.open("else");
+          jump(body, *otherwise, loop_depth + 1);
+          body.close();
+        }
+        Terminator::Return => self.return_(scope, body),
+      }
+    }
+
+    body.close();
+    Ok(())
+  }
+
+  fn return_(&self, scope: &Scope, body: &mut Body) {
+    // Functions end with `Value::zero` of their return type
+    if scope.return_type != VarType::Void {
+      let value_type = value_type(scope.return_type);
+      body.emit(format!("{value_type}.const 0"));
+    }
+    body.emit("return");
+  }
+
+  fn statement(
+    &mut self,
+    statement: &Statement,
+    scope: &Scope,
+    body: &mut Body,
+  ) -> Result<(), String> {
+    match statement {
+      Statement::VariableDeclaration(variable) => {
+        self.expr(&variable.value, scope, body)?;
+
+        match scope.params.get(variable.name.as_str()) {
+          Some(param_type) if value_type(*param_type) == value_type(variable.var_type) => {
+            body.emit(format!("local.set ${}", variable.name));
+          }
+          _ => {
+            let first_type = self.variable_types[&variable.name];
+            body.emit(format!(
+              "global.set {}",
+              global_id(&variable.name, variable.var_type, first_type)
+            ));
+            self
+              .declared_types
+              .insert(variable.name.clone(), variable.var_type);
+          }
+        }
+      }
+      Statement::Store { at, from, .. } => {
+        let address = match at {
+          Operand::Dereference(name) => Operand::Identifier(name.clone()),
+          _ => {
+            return Err(format!(
+              "Invalid operands for store operation {} and {}",
+              at, from
+            ));
+          }
+        };
+
+        self.address(&address, scope, body)?;
+        let value_type = value_type(self.operand(from, scope, body)?);
+        body.emit(format!("{value_type}.store"));
+      }
+      Statement::Call(call) => {
+        let return_type = self.call(&call.name, &call.params, scope, body)?;
+        if return_type != VarType::Void {
+          body.emit("drop");
+        }
+      }
+      Statement::NoOperation => {}
+      Statement::Label { .. }
+      | Statement::ConditionalJump { .. }
+      | Statement::UnconditionalJump { .. }
+      | Statement::FunctionDefinition(_) => {
+        unreachable!("control flow is handled by the CFG")
+      }
+    }
+
+    Ok(())
+  }
+
+  fn address(&mut self, operand: &Operand, scope: &Scope, body: &mut Body) -> Result<(), String> {
+    if self.operand(operand, scope, body)? == VarType::I64 {
+      body.emit("i32.wrap_i64");
+    }
+
+    Ok(())
+  }
+
+  fn condition(&mut self, condition: &Expr, scope: &Scope, body: &mut Body) -> Result<(), String> {
+    let var_type = self.expr(condition, scope, body)?;
+
+    match value_type(var_type) {
+      "i32" => {}
+      "i64" => {
+        body.emit("i64.const 0");
+        body.emit("i64.ne");
+      }
+      value_type => {
+        body.emit(format!("{value_type}.const 0"));
+        body.emit(format!("{value_type}.ne"));
+      }
+    }
+
+    Ok(())
+  }
+
+  fn expr(&mut self, expr: &Expr, scope: &Scope, body: &mut Body) -> Result<VarType, String> {
+    match expr {
+      Expr::Operand(operand) => self.operand(operand, scope, body),
+      Expr::FunctionCall(call) => self.call(&call.name, &call.params, scope, body),
+      Expr::BinaryOperation(BinaryOperation::Arithmetic {
+        lhs,
+        operator,
+        rhs,
+        operation_type,
+      }) => {
+        self.operand(lhs, scope, body)?;
+        self.operand(rhs, scope, body)?;
+
+        let value_type = value_type(*operation_type);
+        let instruction = match operator {
+          Operator::Add => "add",
+          Operator::Sub => "sub",
+          Operator::Mul => "mul",
+          Operator::Div if is_float(*operation_type) => "div",
+          Operator::Div if is_unsigned(*operation_type) => "div_u",
+          Operator::Div => "div_s",
+        };
+        body.emit(format!("{value_type}.{instruction}"));
+
+        narrow(*operation_type, body);
+
+        Ok(*operation_type)
+      }
+      Expr::BinaryOperation(BinaryOperation::Conditional {
+        lhs,
+        condition,
+        rhs,
+        ..
+      }) => {
+        let operand_type = self.operand(lhs, scope, body)?;
+        let value_type = value_type(operand_type);
+
+        if matches!(condition, Condition::And | Condition::Or) {
+          self.truthy(operand_type, body);
+          self.operand(rhs, scope, body)?;
+          self.truthy(operand_type, body);
+
+          body.emit(match condition {
+            Condition::And => "i32.and",
+            _ => "i32.or",
+          });
+
+          return Ok(VarType::Bool);
+        }
+
+        self.operand(rhs, scope, body)?;
+
+        let suffix = if is_float(operand_type) {
+          ""
+        } else if is_unsigned(operand_type) {
+          "_u"
+        } else {
+          "_s"
+        };
+        let instruction = match condition {
+          Condition::Equal => "eq".to_string(),
+          Condition::NotEqual => "ne".to_string(),
+          Condition::LessThan => format!("lt{suffix}"),
+          Condition::GreaterThan => format!("gt{suffix}"),
+          Condition::LessThanOrEqual => format!("le{suffix}"),
+          Condition::GreaterThanOrEqual => format!("ge{suffix}"),
+          Condition::And | Condition::Or => unreachable!(),
+        };
+        body.emit(format!("{value_type}.{instruction}"));
+
+        Ok(VarType::Bool)
+      }
+    }
+  }
+
+  // Turns the value on top of the stack into an i32 that is either 0 or 1.
+  fn truthy(&self, var_type: VarType, body: &mut Body) {
+    match value_type(var_type) {
+      "i32" => {
+        body.emit("i32.eqz");
+        body.emit("i32.eqz");
+      }
+      "i64" => {
+        body.emit("i64.eqz");
+        body.emit("i32.eqz");
+      }
+      value_type => {
+        body.emit(format!("{value_type}.const 0"));
+        body.emit(format!("{value_type}.ne"));
+      }
+    }
+  }
+
+  fn operand(
+    &mut self,
+    operand: &Operand,
+    scope: &Scope,
+    body: &mut Body,
+  ) -> Result<VarType, String> {
+    let var_type = match operand {
+      Operand::Identifier(name) => {
+        if let Some(var_type) = scope.params.get(name.as_str()) {
+          body.emit(format!("local.get ${name}"));
+          *var_type
+        } else {
+          // Uses are type checked against the first declaration, the value may be of a later one
+          let first_type = *self
+            .variable_types
+            .get(name)
+            .ok_or_else(|| format!("Variable {} not found", name))?;
+          let var_type = self.declared_types.get(name).copied().unwrap_or(first_type);
+          body.emit(format!(
+            "global.get {}",
+            global_id(name, var_type, first_type)
+          ));
+          convert(var_type, first_type, body);
+          first_type
+        }
+      }
+      Operand::Dereference(name) => {
+        self.address(&Operand::Identifier(name.clone()), scope, body)?;
+        body.emit("i32.load");
+        VarType::I32
+      }
+      Operand::LiteralStr(value) => {
+        let address = self.string(value);
+        body.emit(format!("i32.const {address}"));
+        VarType::Str
+      }
+      Operand::LiteralBool(value) => {
+        body.emit(format!("i32.const {}", *value as i32));
+        VarType::Bool
+      }
+      Operand::LiteralI8(value) => {
+        body.emit(format!("i32.const {value}"));
+        VarType::I8
+      }
+      Operand::LiteralI16(value) => {
+        body.emit(format!("i32.const {value}"));
+        VarType::I16
+      }
+      Operand::LiteralI32(value) => {
+        body.emit(format!("i32.const {value}"));
+        VarType::I32
+      }
+      Operand::LiteralI64(value) => {
+        body.emit(format!("i64.const {value}"));
+        VarType::I64
+      }
+      Operand::LiteralU8(value) => {
+        body.emit(format!("i32.const {value}"));
+        VarType::U8
+      }
+      Operand::LiteralU16(value) => {
+        body.emit(format!("i32.const {value}"));
+        VarType::U16
+      }
+      Operand::LiteralU32(value) => {
+        body.emit(format!("i32.const {value}"));
+        VarType::U32
+      }
+      Operand::LiteralU64(value) => {
+        body.emit(format!("i64.const {value}"));
+        VarType::U64
+      }
+      Operand::LiteralF32(value) => {
+        body.emit(format!("f32.const {}", float_literal(*value as f64)));
+        VarType::F32
+      }
+      Operand::LiteralF64(value) => {
+        body.emit(format!("f64.const {}", float_literal(*value)));
+        VarType::F64
+      }
+    };
+
+    Ok(var_type)
+  }
+
+  fn call(
+    &mut self,
+    name: &str,
+    params: &[Operand],
+    scope: &Scope,
+    body: &mut Body,
+  ) -> Result<VarType, String> {
+    match name {
+      "write_int" | "write_string" => {
+        self.operand(&params[0], scope, body)?;
+        self.imports.insert(name.to_string());
+        body.emit(format!("call $env.{name}"));
+
+        Ok(VarType::Void)
+      }
+      "read_int" => {
+        self.imports.insert(name.to_string());
+        body.emit("call $env.read_int");
+
+        Ok(VarType::I32)
+      }
+      "read_string" => {
+        // The host fills a buffer reserved in the data section, like the MIPS syscall does
+        let size = match params.first() {
+          Some(Operand::LiteralU32(size)) => *size,
+          _ => return Err("Invalid argument for read_string".to_string()),
+        };
+        let buffer = self.reserve(size);
+
+        self.imports.insert(name.to_string());
+        body.emit(format!("i32.const {buffer}"));
+        body.emit(format!("i32.const {size}"));
+        body.emit("call $env.read_string");
+        body.emit(format!("i32.const {buffer}"));
+
+        Ok(VarType::Str)
+      }
+      _ => {
+        let return_type = self
+          .functions
+          .get(name)
+          .ok_or_else(|| format!("Function {} not found", name))?
+          .return_type;
+
+        for param in params {
+          self.operand(param, scope, body)?;
+        }
+        body.emit(format!("call ${name}"));
+
+        Ok(return_type)
+      }
+    }
+  }
+}
@@ -0,0 +1,209 @@
+// Turns a control flow graph into WebAssembly structured control flow.
+//
+// This follows "Beyond Relooper" by Norman Ramsey: the code of each block is placed by walking
+// the dominator tree, merge nodes become the continuation of a `block`, and loop headers are
+// wrapped in a `loop`. It only works on reducible graphs, for the others `stackify` returns `None`
+// and the caller falls back to a dispatch loop.
+
+use std::collections::HashMap;
+
+use crate::{
+  ast::{Expr, Location},
+  codegen::cfg::{BlockId, ControlFlowGraph, Terminator},
+};
+
+#[derive(Debug)]
+pub enum Structured {
+  Block(Vec<Structured>),
+  Loop(Vec<Structured>),
+  If {
+    condition: Expr,
+    then: Vec<Structured>,
+    otherwise: Vec<Structured>,
+    location: Location,
+  },
+  /// Branch to the enclosing construct at the given relative depth.
+  Br(u32),
+  /// The straight-line statements of a basic block.
+  Code(BlockId),
+  Return,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Frame {
+  LoopHeadedBy(BlockId),
+  BlockFollowedBy(BlockId),
+  IfThenElse,
+}
+
+struct Stackifier<'graph> {
+  graph: &'graph ControlFlowGraph,
+  rpo_number: HashMap<BlockId, usize>,
+  dominator_children: HashMap<BlockId, Vec<BlockId>>,
+  forward_predecessors: HashMap<BlockId, usize>,
+  loop_headers: Vec<BlockId>,
+}
+
+pub fn stackify(graph: &ControlFlowGraph) -> Option<Vec<Structured>> {
+  let order = graph.reverse_postorder();
+  let rpo_number: HashMap<BlockId, usize> = order
+    .iter()
+    .enumerate()
+    .map(|(i, block)| (*block, i))
+    .collect();
+  let idom = graph.immediate_dominators();
+
+  let dominates = |a: BlockId, mut b: BlockId| loop {
+    if a == b {
+      return true;
+    }
+    if b == graph.entry {
+      return false;
+    }
+    b = idom[&b];
+  };
+
+  let mut dominator_children: HashMap<BlockId, Vec<BlockId>> = HashMap::new();
+  let mut forward_predecessors: HashMap<BlockId, usize> = HashMap::new();
+  let mut loop_headers = vec![];
+
+  for block in &order {
+    if *block != graph.entry {
+      dominator_children
+        .entry(idom[block])
+        .or_default()
+        .push(*block);
+    }
+
+    for successor in graph.successors(*block) {
+      if rpo_number[&successor] <= rpo_number[block] {
+        // A back edge whose target does not dominate its source makes the graph irreducible
+        if !dominates(successor, *block) {
+          return None;
+        }
+        loop_headers.push(successor);
+      } else {
+        *forward_predecessors.entry(successor).or_default() += 1;
+      }
+    }
+  }
+
+  let stackifier = Stackifier {
+    graph,
+    rpo_number,
+    dominator_children,
+    forward_predecessors,
+    loop_headers,
+  };
+
+  Some(stackifier.do_tree(graph.entry, &mut vec![]))
+}
+
+impl<'graph> Stackifier<'graph> {
+  fn is_merge_node(&self, block: BlockId) -> bool {
+    self.forward_predecessors.get(&block).copied().unwrap_or(0) >= 2
+  }
+
+  fn do_tree(&self, block: BlockId, context: &mut Vec<Frame>) -> Vec<Structured> {
+    let mut merge_children: Vec<BlockId> = self
+      .dominator_children
+      .get(&block)
+      .map(|children| {
+        children
+          .iter()
+          .copied()
+          .filter(|child| self.is_merge_node(*child))
+          .collect()
+      })
+      .unwrap_or_default();
+    // The merge node placed last in the code is the outermost block
+    merge_children.sort_by_key(|child| std::cmp::Reverse(self.rpo_number[child]));
+
+    if self.loop_headers.contains(&block) {
+      context.push(Frame::LoopHeadedBy(block));
+      let body = self.node_within(block, &merge_children, context);
+      context.pop();
+
+      vec![Structured::Loop(body)]
+    } else {
+      self.node_within(block, &merge_children, context)
+    }
+  }
+
+  fn node_within(
+    &self,
+    block: BlockId,
+    merge_children: &[BlockId],
+    context: &mut Vec<Frame>,
+  ) -> Vec<Structured> {
+    match merge_children.split_first() {
+      Some((follower, rest)) => {
+        context.push(Frame::BlockFollowedBy(*follower));
+        let inner = self.node_within(block, rest, context);
+        context.pop();
+
+        let mut code = vec![Structured::Block(inner)];
+        code.extend(self.do_tree(*follower, context));
+        code
+      }
+      None => {
+        let mut code = vec![Structured::Code(block)];
+
+        match &self.graph.blocks[block].terminator {
+          Terminator::Jump(target) => code.extend(self.do_branch(block, *target, context)),
+          Terminator::Branch {
+            condition,
+            then,
+            otherwise,
+            location,
+          } => {
+            context.push(Frame::IfThenElse);
+            let then = self.do_branch(block, *then, context);
+            let otherwise = self.do_branch(block, *otherwise, context);
+            context.pop();
+
+            code.push(Structured::If {
+              condition: condition.clone(),
+              then,
+              otherwise,
+              location: location.clone(),
+            });
+          }
+          Terminator::Return => code.push(Structured::Return),
+        }
+
+        code
+      }
+    }
+  }
+
+  fn do_branch(
+    &self,
+    source: BlockId,
+    target: BlockId,
+    context: &mut Vec<Frame>,
+  ) -> Vec<Structured> {
+    if self.rpo_number[&target] <= self.rpo_number[&source] {
+      vec![Structured::Br(depth_of(
+        context,
+        Frame::LoopHeadedBy(target),
+      ))]
+    } else if self.is_merge_node(target) {
+      vec![Structured::Br(depth_of(
+        context,
+        Frame::BlockFollowedBy(target),
+      ))]
+    } else {
+      self.do_tree(target, context)
+    }
+  }
+}
+
+fn depth_of(context: &[Frame], frame: Frame) -> u32 {
+  let position = context
+    .iter()
+    .rposition(|candidate| *candidate == frame)
+    .expect("branch target is not an enclosing construct");
+
+  (context.len() - 1 - position) as u32
+}
@@ -0,0 +1,105 @@
+// Compiling a source as a library, without printing anything.
+//
+// Every problem found on the way is returned as a diagnostic next to what could be produced, and
+// showing them, as ariadne reports, JSON or plain text, is up to the caller.
+
+use crate::{
+  ast::{context::Context, Statement},
+  codegen::{target, Artifact},
+  diagnostics::{self, Diagnostic, Severity},
+  lexer::Lexer,
+  lints::{self, Levels},
+  parser,
+};
+
+#[derive(Clone, Debug, Default)]
+pub struct CompileOptions {
+  /// The backend to generate code with, none to only check the program
+  pub target: Option<String>,
+  /// The levels of the lints, on top of those of the pragmas of the source
+  pub lints: Levels,
+}
+
+impl CompileOptions {
+  pub fn target(target: impl Into<String>) -> Self {
+    Self {
+      target: Some(target.into()),
+      ..Default::default()
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct CompileOutput {
+  /// The program, unless it has errors
+  pub ast: Option<Vec<Statement>>,
+  /// The generated code, when a target was asked for and there are no errors, warnings do not
+  /// prevent it
+  pub artifact: Option<Artifact>,
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+impl CompileOutput {
+  pub fn has_errors(&self) -> bool {
+    self
+      .diagnostics
+      .iter()
+      .any(|diagnostic| diagnostic.severity == Severity::Error)
+  }
+
+  pub fn errors(&self) -> usize {
+    self
+      .diagnostics
+      .iter()
+      .filter(|diagnostic| diagnostic.severity == Severity::Error)
+      .count()
+  }
+}
+
+/// Parses and type checks the source, lints it, then generates code for the target of the options
+/// unless a lint is denied.
+pub fn compile(source: &str, filename: &str, options: &CompileOptions) -> CompileOutput {
+  compile_in(&mut Context::new(0), source, filename, options)
+}
+
+/// Compiles like `compile`, with the functions and variables the context already has. It is left
+/// with those the source declares, for tools that look them up afterwards.
+pub fn compile_in(
+  context: &mut Context,
+  source: &str,
+  filename: &str,
+  options: &CompileOptions,
+) -> CompileOutput {
+  let mut output = CompileOutput {
+    ast: None,
+    artifact: None,
+    diagnostics: vec![],
+  };
+
+  let (ast, errors) = parser::parse_partially(context, Lexer::unchecked(source, filename));
+  output.diagnostics = diagnostics::from_errors(&errors);
+
+  // What could be parsed is linted too, not to wait for the errors to be fixed to show them
+  if let Some(ast) = &ast {
+    output
+      .diagnostics
+      .extend(lints::check(ast, source, &options.lints));
+    output
+      .diagnostics
+      .sort_by_key(|diagnostic| diagnostic.spans.first().map(|span| span.location.start));
+  }
+
+  let Some(ast) = ast.filter(|_| errors.is_empty()) else {
+    return output;
+  };
+
+  if let (Some(name), false) = (&options.target, output.has_errors()) {
+    match target::generate(name, ast.clone()) {
+      Ok(artifact) => output.artifact = Some(artifact),
+      Err(error) => output.diagnostics.push(Diagnostic::from(&error)),
+    }
+  }
+  output.ast = Some(ast);
+
+  output
+}
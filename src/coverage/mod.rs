@@ -0,0 +1,208 @@
+// Which statements, functions and branches a program ran, keyed by their source spans.
+//
+// A `Coverage` can observe several runs of the same program, like one per test input, and adds
+// them up. It is exported as LCOV tracefiles, which `genhtml` and most coverage viewers read.
+
+use std::collections::HashMap;
+
+use crate::{
+  ast::{Location, Statement},
+  interpreter::{Interpreter, Observer},
+  runtime::{RuntimeError, Value},
+  utils::line_of,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatementCoverage {
+  pub location: Location,
+  pub hits: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionCoverage {
+  pub name: String,
+  pub location: Location,
+  pub calls: u64,
+}
+
+/// The two ways out of a `ConditionalJump`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BranchCoverage {
+  pub location: Location,
+  pub taken: u64,
+  pub not_taken: u64,
+}
+
+impl BranchCoverage {
+  pub fn evaluated(&self) -> bool {
+    self.taken + self.not_taken > 0
+  }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Coverage {
+  pub statements: Vec<StatementCoverage>,
+  /// `main` first, for the statements outside of functions
+  pub functions: Vec<FunctionCoverage>,
+  pub branches: Vec<BranchCoverage>,
+  /// Indices of the statements and branches, by where their span starts
+  statement_indices: HashMap<usize, usize>,
+  branch_indices: HashMap<usize, usize>,
+  /// How many calls deep the last statement ran
+  depth: usize,
+}
+
+impl Coverage {
+  pub fn new(program: &[Statement]) -> Self {
+    let mut coverage = Self::default();
+
+    let start = program
+      .iter()
+      .find(|statement| !matches!(statement, Statement::FunctionDefinition(_)))
+      .and_then(Statement::location)
+      .map_or(0..0, Clone::clone);
+    coverage.functions.push(FunctionCoverage {
+      name: "main".to_string(),
+      location: start,
+      calls: 0,
+    });
+    coverage.add(program);
+
+    coverage
+  }
+
+  fn add(&mut self, statements: &[Statement]) {
+    for statement in statements {
+      if let Statement::FunctionDefinition(function) = statement {
+        self.functions.push(FunctionCoverage {
+          name: function.name.clone(),
+          location: function.location.clone(),
+          calls: 0,
+        });
+        self.add(&function.body);
+        continue;
+      }
+      let Some(location) = statement.location() else {
+        continue;
+      };
+
+      if let Statement::ConditionalJump { .. } = statement {
+        self
+          .branch_indices
+          .insert(location.start, self.branches.len());
+        self.branches.push(BranchCoverage {
+          location: location.clone(),
+          taken: 0,
+          not_taken: 0,
+        });
+      }
+      self
+        .statement_indices
+        .insert(location.start, self.statements.len());
+      self.statements.push(StatementCoverage {
+        location: location.clone(),
+        hits: 0,
+      });
+    }
+  }
+
+  /// The coverage as an LCOV tracefile, for the program at `path`.
+  pub fn lcov(&self, source: &str, path: &str) -> String {
+    let line = |location: &Location| line_of(source, location.start);
+    let mut lcov = format!("TN:\nSF:{path}\n");
+
+    for function in &self.functions {
+      lcov += &format!("FN:{},{}\n", line(&function.location), function.name);
+    }
+    for function in &self.functions {
+      lcov += &format!("FNDA:{},{}\n", function.calls, function.name);
+    }
+    let hit = self.functions.iter().filter(|function| function.calls > 0);
+    lcov += &format!("FNF:{}\nFNH:{}\n", self.functions.len(), hit.count());
+
+    // Taken first, then not taken, `-` for a jump that was never reached
+    let mut branches_hit = 0;
+    for (block, branch) in self.branches.iter().enumerate() {
+      let line = line(&branch.location);
+      for (index, count) in [branch.taken, branch.not_taken].into_iter().enumerate() {
+        let count = match branch.evaluated() {
+          true => count.to_string(),
+          false => "-".to_string(),
+        };
+        lcov += &format!("BRDA:{line},{block},{index},{count}\n");
+      }
+      branches_hit += (branch.taken > 0) as usize + (branch.not_taken > 0) as usize;
+    }
+    lcov += &format!("BRF:{}\nBRH:{branches_hit}\n", self.branches.len() * 2);
+
+    // A line ran as many times as its most run statement
+    let mut lines: Vec<(usize, u64)> = vec![];
+    for statement in &self.statements {
+      let line = line(&statement.location);
+      match lines.iter_mut().find(|(other, _)| *other == line) {
+        Some((_, hits)) => *hits = (*hits).max(statement.hits),
+        None => lines.push((line, statement.hits)),
+      }
+    }
+    lines.sort();
+    for (line, hits) in &lines {
+      lcov += &format!("DA:{line},{hits}\n");
+    }
+    let hit = lines.iter().filter(|(_, hits)| *hits > 0);
+    lcov += &format!("LF:{}\nLH:{}\n", lines.len(), hit.count());
+
+    lcov + "end_of_record\n"
+  }
+}
+
+impl Observer for Coverage {
+  fn statement(
+    &mut self,
+    interpreter: &Interpreter,
+    locals: &HashMap<String, Value>,
+    statement: &Statement,
+  ) -> Result<(), RuntimeError> {
+    // Every run starts again from `main`
+    if interpreter.steps == 1 {
+      self.depth = 0;
+    }
+    if matches!(statement, Statement::FunctionDefinition(_)) {
+      return Ok(());
+    }
+    let Some(location) = statement.location() else {
+      return Ok(());
+    };
+
+    // A statement deeper than the last one is the first of a call
+    let depth = interpreter.stack.len() + 1;
+    if depth > self.depth {
+      let name = interpreter
+        .stack
+        .last()
+        .map_or("main", |frame| frame.function.name.as_str());
+      if let Some(function) = self.functions.iter_mut().find(|f| f.name == name) {
+        function.calls += 1;
+      }
+    }
+    self.depth = depth;
+
+    if let Some(&index) = self.statement_indices.get(&location.start) {
+      self.statements[index].hits += 1;
+    }
+
+    if let Statement::ConditionalJump { condition, .. } = statement {
+      let (Some(&index), Ok(value)) = (
+        self.branch_indices.get(&location.start),
+        interpreter.evaluate(condition, locals),
+      ) else {
+        return Ok(());
+      };
+      match value.is_truthy() {
+        true => self.branches[index].taken += 1,
+        false => self.branches[index].not_taken += 1,
+      }
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,441 @@
+// Source-level debugger, which pauses the reference interpreter between statements.
+//
+// It is an interpreter `Observer`: before each statement it decides from the breakpoints and the
+// last stepping command whether to stop, and then reads commands until one resumes the program.
+// Lines come from the spans the parser gave the statements, such as `Variable::location` and
+// `FunctionCall::location`, and the call stack from the frames of the interpreter.
+
+use std::{
+  cell::RefCell,
+  collections::HashMap,
+  io::{BufRead, Write},
+};
+
+use crate::{
+  ast::{context::Context, Expr, Statement},
+  compiler::{self, CompileOptions},
+  diagnostics,
+  interpreter::{Interpreter, Observer},
+  lexer::Lexer,
+  parser::parse_expr,
+  runtime::{Io, RuntimeError, Value},
+  utils::line_of,
+};
+
+pub const PROMPT: &str = "(debug) ";
+
+const HELP: &str = "break <line|label>   stops when the line or the label is reached
+delete <line|label>  removes a breakpoint
+step                 runs one statement, entering calls
+next                 runs one statement, stepping over calls
+finish               runs until the current function returns
+continue             runs until a breakpoint or the end of the program
+print [expr]         shows an expression, or every variable in scope
+watch <expr>         shows an expression at every stop
+backtrace            shows the calls being run
+quit                 stops the program
+An empty line repeats the last command.";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Breakpoint {
+  Line(usize),
+  Label(String),
+}
+
+impl std::fmt::Display for Breakpoint {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Breakpoint::Line(line) => write!(f, "line {line}"),
+      Breakpoint::Label(label) => write!(f, "label `{label}`"),
+    }
+  }
+}
+
+// Until when the program runs, depths count the calls being run
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Resume {
+  Step,
+  Next(usize),
+  Finish(usize),
+  Continue,
+}
+
+pub struct Debugger<'session> {
+  source: &'session str,
+  /// What the parser knew at the end of the program, to type check expressions
+  context: Context,
+  labels: Vec<String>,
+  commands: &'session mut dyn BufRead,
+  output: &'session mut dyn Write,
+  breakpoints: Vec<Breakpoint>,
+  watches: Vec<(String, Expr)>,
+  resume: Resume,
+  /// Line of the last statement, a line breakpoint stops once each time its line is reached
+  line: usize,
+  last_command: String,
+}
+
+/// Debugs the program in `source` with the commands read from `commands`. The program reads
+/// `input`, and writes to `output` along with the session.
+pub fn debug(
+  source: &str,
+  commands: &mut dyn BufRead,
+  input: &mut dyn BufRead,
+  output: &mut dyn Write,
+) -> Result<(), String> {
+  let mut context = Context::new(0);
+  let compiled = compiler::compile_in(&mut context, source, "", &CompileOptions::default());
+  let program = match compiled.ast {
+    Some(program) if !compiled.has_errors() => program,
+    _ => return Err(diagnostics::plain(source, &compiled.diagnostics)),
+  };
+
+  let shared = RefCell::new(Shared {
+    output,
+    unfinished: false,
+  });
+  let mut session = Session(&shared);
+  let mut printed = Printed(&shared);
+
+  let mut debugger = Debugger {
+    source,
+    context,
+    labels: labels(&program),
+    commands,
+    output: &mut session,
+    breakpoints: vec![],
+    watches: vec![],
+    resume: Resume::Step,
+    line: 0,
+    last_command: String::new(),
+  };
+
+  let mut interpreter = Interpreter::new(&program);
+  let result = interpreter.run_observed(&mut Io::new(input, &mut printed), &mut debugger);
+
+  let output = &mut debugger.output;
+  match result {
+    Ok(()) => writeln!(output, "Program finished"),
+    Err(RuntimeError::Interrupted) => Ok(()),
+    Err(error) => writeln!(output, "Program failed at line {}: {error}", debugger.line),
+  }
+  .map_err(|e| e.to_string())
+}
+
+impl Observer for Debugger<'_> {
+  fn statement(
+    &mut self,
+    interpreter: &Interpreter,
+    locals: &HashMap<String, Value>,
+    statement: &Statement,
+  ) -> Result<(), RuntimeError> {
+    // Definitions are skipped over, their bodies run when they are called
+    if matches!(statement, Statement::FunctionDefinition(_)) {
+      return Ok(());
+    }
+    let Some(location) = statement.location() else {
+      return Ok(());
+    };
+
+    let line = self.line_of(location.start);
+    let entered = line != self.line;
+    self.line = line;
+
+    let hit = self
+      .breakpoints
+      .iter()
+      .position(|breakpoint| match breakpoint {
+        Breakpoint::Line(breakpoint) => entered && *breakpoint == line,
+        Breakpoint::Label(label) => {
+          matches!(statement, Statement::Label { name, .. } if name == label)
+        }
+      });
+
+    let depth = interpreter.stack.len();
+    let stop = hit.is_some()
+      || match self.resume {
+        Resume::Step => true,
+        Resume::Next(from) => depth <= from,
+        Resume::Finish(from) => depth < from,
+        Resume::Continue => false,
+      };
+    if !stop {
+      return Ok(());
+    }
+
+    if let Some(index) = hit {
+      writeln!(
+        self.output,
+        "Breakpoint {} at {}",
+        index + 1,
+        self.breakpoints[index]
+      )?;
+    }
+    self.show(interpreter, locals)?;
+    self.prompt(interpreter, locals)
+  }
+}
+
+impl Debugger<'_> {
+  fn line_of(&self, offset: usize) -> usize {
+    line_of(self.source, offset)
+  }
+
+  // Where the program stopped, and the watched expressions
+  fn show(
+    &mut self,
+    interpreter: &Interpreter,
+    locals: &HashMap<String, Value>,
+  ) -> Result<(), RuntimeError> {
+    let function = interpreter
+      .stack
+      .last()
+      .map_or("main", |frame| frame.function.name.as_str());
+    let code = self
+      .source
+      .lines()
+      .nth(self.line - 1)
+      .unwrap_or_default()
+      .trim();
+    writeln!(self.output, "{function}:{}  {code}", self.line)?;
+
+    for (text, expr) in &self.watches {
+      let value = describe(text, interpreter.evaluate(expr, locals));
+      writeln!(self.output, "  {value}")?;
+    }
+
+    Ok(())
+  }
+
+  // Reads commands until one resumes the program
+  fn prompt(
+    &mut self,
+    interpreter: &Interpreter,
+    locals: &HashMap<String, Value>,
+  ) -> Result<(), RuntimeError> {
+    let depth = interpreter.stack.len();
+
+    loop {
+      write!(self.output, "{PROMPT}")?;
+      self.output.flush()?;
+
+      let mut command = String::new();
+      if self.commands.read_line(&mut command)? == 0 {
+        writeln!(self.output)?;
+        return Err(RuntimeError::Interrupted);
+      }
+      let command = match command.trim() {
+        "" => self.last_command.clone(),
+        command => command.to_string(),
+      };
+      self.last_command.clone_from(&command);
+
+      let (name, argument) = command
+        .split_once(char::is_whitespace)
+        .map_or((command.as_str(), ""), |(name, argument)| {
+          (name, argument.trim())
+        });
+
+      let resume = match name {
+        "s" | "step" => Resume::Step,
+        "n" | "next" => Resume::Next(depth),
+        "f" | "finish" if depth == 0 => {
+          writeln!(self.output, "`finish` needs a function to return from")?;
+          continue;
+        }
+        "f" | "finish" => Resume::Finish(depth),
+        "c" | "continue" => Resume::Continue,
+        "q" | "quit" => return Err(RuntimeError::Interrupted),
+        _ => {
+          let message = match name {
+            "b" | "break" => self.add_breakpoint(argument),
+            "d" | "delete" => self.delete_breakpoint(argument),
+            "p" | "print" if argument.is_empty() => variables(interpreter, locals),
+            "p" | "print" => self
+              .expr(argument)
+              .map(|expr| describe(argument, interpreter.evaluate(&expr, locals))),
+            "w" | "watch" => self.expr(argument).map(|expr| {
+              let value = describe(argument, interpreter.evaluate(&expr, locals));
+              self.watches.push((argument.to_string(), expr));
+              value
+            }),
+            "bt" | "backtrace" => Ok(self.backtrace(interpreter)),
+            "h" | "help" => Ok(HELP.to_string()),
+            "" => Ok(String::new()),
+            _ => Err(format!("Unknown command `{name}`, `help` lists them")),
+          };
+
+          let message = message.unwrap_or_else(|error| error);
+          if !message.is_empty() {
+            writeln!(self.output, "{message}")?;
+          }
+          continue;
+        }
+      };
+
+      self.resume = resume;
+      return Ok(());
+    }
+  }
+
+  fn breakpoint(&self, argument: &str) -> Result<Breakpoint, String> {
+    if let Ok(line) = argument.parse::<usize>() {
+      return match line {
+        1.. if line <= self.source.lines().count() => Ok(Breakpoint::Line(line)),
+        _ => Err(format!("There is no line {line}")),
+      };
+    }
+
+    match self.labels.iter().any(|label| label == argument) {
+      true => Ok(Breakpoint::Label(argument.to_string())),
+      false => Err(format!(
+        "There is no label `{argument}`, breakpoints take a line number or a label"
+      )),
+    }
+  }
+
+  fn add_breakpoint(&mut self, argument: &str) -> Result<String, String> {
+    let breakpoint = self.breakpoint(argument)?;
+    if self.breakpoints.contains(&breakpoint) {
+      return Err(format!("There already is a breakpoint at {breakpoint}"));
+    }
+
+    self.breakpoints.push(breakpoint);
+    Ok(format!(
+      "Breakpoint {} at {}",
+      self.breakpoints.len(),
+      self.breakpoints.last().unwrap()
+    ))
+  }
+
+  fn delete_breakpoint(&mut self, argument: &str) -> Result<String, String> {
+    let breakpoint = self.breakpoint(argument)?;
+    let index = self
+      .breakpoints
+      .iter()
+      .position(|existing| *existing == breakpoint)
+      .ok_or_else(|| format!("There is no breakpoint at {breakpoint}"))?;
+
+    self.breakpoints.remove(index);
+    Ok(format!("Deleted the breakpoint at {breakpoint}"))
+  }
+
+  // Checked like the program, with the variables it declares
+  fn expr(&self, text: &str) -> Result<Expr, String> {
+    if text.is_empty() {
+      return Err("An expression is needed, such as `x` or `a + b`".to_string());
+    }
+
+    let invalid = diagnostics::invalid_tokens(text);
+    if !invalid.is_empty() {
+      return Err(diagnostics::plain(text, &invalid));
+    }
+
+    parse_expr(&mut self.context.clone(), Lexer::unchecked(text, ""))
+      .map_err(|errors| diagnostics::plain(text, &diagnostics::from_errors(&errors)))
+  }
+
+  // The innermost call first, each with the line it is at
+  fn backtrace(&self, interpreter: &Interpreter) -> String {
+    let mut frames = vec![];
+    let mut line = self.line;
+
+    for frame in interpreter.stack.iter().rev() {
+      frames.push(format!("{} at line {line}", frame.function.name));
+      line = self.line_of(frame.call.start);
+    }
+    frames.push(format!("main at line {line}"));
+
+    frames
+      .iter()
+      .enumerate()
+      .map(|(index, frame)| format!("#{index} {frame}"))
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
+}
+
+fn describe(text: &str, value: Result<Value, RuntimeError>) -> String {
+  match value {
+    Ok(value) => format!("{text}: {} = {value}", value.var_type()),
+    Err(error) => format!("{text}: {error}"),
+  }
+}
+
+// The locals of the current call, then the globals they do not hide
+fn variables(interpreter: &Interpreter, locals: &HashMap<String, Value>) -> Result<String, String> {
+  let mut names: Vec<&String> = locals.keys().collect();
+  names.sort();
+  names.extend(
+    interpreter
+      .globals
+      .keys()
+      .filter(|name| !locals.contains_key(*name)),
+  );
+
+  if names.is_empty() {
+    return Ok("No variable has a value yet".to_string());
+  }
+
+  let lines: Vec<String> = names
+    .into_iter()
+    .map(|name| describe(name, interpreter.variable(name, locals)))
+    .collect();
+  Ok(lines.join("\n"))
+}
+
+fn labels(statements: &[Statement]) -> Vec<String> {
+  let mut labels = vec![];
+
+  for statement in statements {
+    match statement {
+      Statement::Label { name, .. } => labels.push(name.clone()),
+      Statement::FunctionDefinition(function) => labels.extend(self::labels(&function.body)),
+      _ => {}
+    }
+  }
+
+  labels
+}
+
+// The session and the program write to the same output, what the program prints is ended by a
+// line break before the session goes on
+struct Shared<'output> {
+  output: &'output mut dyn Write,
+  /// Whether the program printed part of a line
+  unfinished: bool,
+}
+
+struct Printed<'shared, 'output>(&'shared RefCell<Shared<'output>>);
+
+struct Session<'shared, 'output>(&'shared RefCell<Shared<'output>>);
+
+impl Write for Printed<'_, '_> {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    let mut shared = self.0.borrow_mut();
+    let written = shared.output.write(buf)?;
+    if written > 0 {
+      shared.unfinished = buf[written - 1] != b'\n';
+    }
+    Ok(written)
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    self.0.borrow_mut().output.flush()
+  }
+}
+
+impl Write for Session<'_, '_> {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    let mut shared = self.0.borrow_mut();
+    if shared.unfinished {
+      shared.output.write_all(b"\n")?;
+      shared.unfinished = false;
+    }
+    shared.output.write(buf)
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    self.0.borrow_mut().output.flush()
+  }
+}
@@ -0,0 +1,91 @@
+// The code of each kind of error, and its long-form explanation for `compass explain`.
+//
+// Codes are stable: a kind that is no longer reported keeps its code, which is never reused.
+
+use lalrpop_util::ParseError;
+
+use crate::{
+  lexer::{tokens::LexingError, LexicalError},
+  parser::recovery,
+};
+
+pub const INVALID_TOKEN: &str = "E0001";
+pub const UNEXPECTED_TOKEN: &str = "E0002";
+pub const UNEXPECTED_EOF: &str = "E0003";
+pub const MISMATCHED_TYPES: &str = "E0004";
+pub const UNKNOWN_VARIABLE: &str = "E0005";
+pub const UNKNOWN_FUNCTION: &str = "E0006";
+pub const WRONG_ARGUMENT_COUNT: &str = "E0007";
+pub const DUPLICATE_FUNCTION: &str = "E0008";
+pub const UNUSED_VALUE: &str = "E0009";
+pub const UNSUPPORTED: &str = "E0010";
+pub const UNSUPPORTED_TYPE: &str = "E0011";
+pub const INVALID_PROGRAM: &str = "E0012";
+pub const INTEGER_OVERFLOW: &str = "E0013";
+pub const UNTERMINATED_STRING: &str = "E0014";
+pub const NON_ASCII_CHARACTER: &str = "E0015";
+pub const UNKNOWN_SUFFIX: &str = "E0016";
+pub const UNKNOWN_TYPE: &str = "E0017";
+pub const UNKNOWN_LABEL: &str = "E0018";
+
+/// Every code, with its explanation.
+pub const CODES: &[(&str, &str)] = &[
+  (INVALID_TOKEN, include_str!("explanations/E0001.md")),
+  (UNEXPECTED_TOKEN, include_str!("explanations/E0002.md")),
+  (UNEXPECTED_EOF, include_str!("explanations/E0003.md")),
+  (MISMATCHED_TYPES, include_str!("explanations/E0004.md")),
+  (UNKNOWN_VARIABLE, include_str!("explanations/E0005.md")),
+  (UNKNOWN_FUNCTION, include_str!("explanations/E0006.md")),
+  (WRONG_ARGUMENT_COUNT, include_str!("explanations/E0007.md")),
+  (DUPLICATE_FUNCTION, include_str!("explanations/E0008.md")),
+  (UNUSED_VALUE, include_str!("explanations/E0009.md")),
+  (UNSUPPORTED, include_str!("explanations/E0010.md")),
+  (UNSUPPORTED_TYPE, include_str!("explanations/E0011.md")),
+  (INVALID_PROGRAM, include_str!("explanations/E0012.md")),
+  (INTEGER_OVERFLOW, include_str!("explanations/E0013.md")),
+  (UNTERMINATED_STRING, include_str!("explanations/E0014.md")),
+  (NON_ASCII_CHARACTER, include_str!("explanations/E0015.md")),
+  (UNKNOWN_SUFFIX, include_str!("explanations/E0016.md")),
+  (UNKNOWN_TYPE, include_str!("explanations/E0017.md")),
+  (UNKNOWN_LABEL, include_str!("explanations/E0018.md")),
+];
+
+/// The explanation of a code, which can be written without its `E` and leading zeros.
+pub fn explain(code: &str) -> Option<&'static str> {
+  let code = code.trim();
+  let number: u32 = code.strip_prefix(['E', 'e']).unwrap_or(code).parse().ok()?;
+  let code = format!("E{number:04}");
+
+  CODES
+    .iter()
+    .find(|(known, _)| *known == code)
+    .map(|(_, explanation)| *explanation)
+}
+
+/// The code of an error of the parser or the type checker.
+pub fn code(error: &recovery::Error) -> &'static str {
+  match error {
+    ParseError::InvalidToken { .. } => INVALID_TOKEN,
+    ParseError::UnrecognizedToken { .. } | ParseError::ExtraToken { .. } => UNEXPECTED_TOKEN,
+    ParseError::UnrecognizedEof { .. } => UNEXPECTED_EOF,
+    ParseError::User { error } => match error {
+      LexicalError::InvalidToken { kind, .. } => match kind {
+        LexingError::IntegerOverflow(_) => INTEGER_OVERFLOW,
+        LexingError::UnterminatedString => UNTERMINATED_STRING,
+        LexingError::NonAsciiCharacter(_) => NON_ASCII_CHARACTER,
+        LexingError::UnknownSuffix(_) => UNKNOWN_SUFFIX,
+        LexingError::UnknownType(_) => UNKNOWN_TYPE,
+        LexingError::InvalidInteger(_)
+        | LexingError::InvalidPrefix(_)
+        | LexingError::UnexpectedCharacter => INVALID_TOKEN,
+      },
+      LexicalError::WrongType { .. } => MISMATCHED_TYPES,
+      LexicalError::UnknownVariable { .. } => UNKNOWN_VARIABLE,
+      LexicalError::UnknownFunction { .. } => UNKNOWN_FUNCTION,
+      LexicalError::WrongArgumentCount { .. } => WRONG_ARGUMENT_COUNT,
+      LexicalError::FunctionIsBuiltin { .. } => DUPLICATE_FUNCTION,
+      LexicalError::UnusedValue { .. } => UNUSED_VALUE,
+      LexicalError::UnknownLabel { .. } => UNKNOWN_LABEL,
+    },
+  }
+}
@@ -0,0 +1,424 @@
+// Errors as data, for the tools that read them instead of people.
+//
+// A diagnostic has a code, a severity, a message, its labelled spans, the first of them being
+// where the error is, and a help. They are written as JSON, one object per line like `cargo
+// --message-format json`, as a SARIF log for code scanning tools, as ariadne reports, or as plain
+// text for the REPL and the debugger.
+
+use std::ops::Range;
+
+use lalrpop_util::ParseError;
+use serde_json::{json, Value};
+
+use crate::{
+  codegen::{error::CodegenError, target},
+  compiler::{self, CompileOptions},
+  lexer::{self, FixIt, LexicalError},
+  parser::{self, recovery},
+};
+
+pub mod codes;
+pub mod suggestions;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+  Error,
+  Warning,
+  Note,
+}
+
+impl Severity {
+  pub fn name(&self) -> &'static str {
+    match self {
+      Severity::Error => "error",
+      Severity::Warning => "warning",
+      Severity::Note => "note",
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+  pub location: Range<usize>,
+  pub label: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+  pub code: Option<String>,
+  pub severity: Severity,
+  pub message: String,
+  /// The primary span first, then the secondary ones. Errors of the backends have none
+  pub spans: Vec<Span>,
+  pub help: Option<String>,
+  /// A replacement that fixes the error, when it is unambiguous
+  pub fix: Option<FixIt>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+  Json,
+  Sarif,
+}
+
+impl Diagnostic {
+  /// An error without a code or spans, like those of the backends.
+  pub fn error(message: impl Into<String>) -> Self {
+    Self {
+      code: None,
+      severity: Severity::Error,
+      message: message.into(),
+      spans: vec![],
+      help: None,
+      fix: None,
+    }
+  }
+
+  pub(crate) fn with_span(mut self, location: Range<usize>, label: impl Into<String>) -> Self {
+    self.spans.push(Span {
+      location,
+      label: label.into(),
+    });
+    self
+  }
+}
+
+impl From<&recovery::Error> for Diagnostic {
+  fn from(error: &recovery::Error) -> Self {
+    let diagnostic = |message: &str| Diagnostic {
+      code: Some(codes::code(error).to_string()),
+      ..Diagnostic::error(message)
+    };
+
+    match error {
+      ParseError::User { error } => {
+        let (tips, help) = error.tips();
+        let diagnostic = tips
+          .iter()
+          .fold(diagnostic(error.title()), |diagnostic, tip| {
+            diagnostic.with_span(tip.location.clone(), &tip.message)
+          });
+        Diagnostic {
+          help: help.cloned(),
+          fix: error.fix().cloned(),
+          ..diagnostic
+        }
+      }
+      ParseError::InvalidToken { location } => {
+        diagnostic("Invalid token").with_span(*location..*location, "Invalid token")
+      }
+      ParseError::UnrecognizedEof { location, expected } => Diagnostic {
+        help: Some(parser::expected_tokens(expected)),
+        ..diagnostic("Unexpected end of file")
+          .with_span(*location..*location, "Unexpected end of file")
+      },
+      ParseError::UnrecognizedToken {
+        token: (start, _, end),
+        expected,
+      } => Diagnostic {
+        help: Some(parser::expected_tokens(expected)),
+        ..diagnostic("Unrecognized token").with_span(*start..*end, "Unrecognized token")
+      },
+      ParseError::ExtraToken {
+        token: (start, _, end),
+      } => diagnostic("Extra token").with_span(*start..*end, "Extra token"),
+    }
+  }
+}
+
+impl From<&CodegenError> for Diagnostic {
+  fn from(error: &CodegenError) -> Self {
+    let (code, label, help) = match error {
+      CodegenError::Unsupported {
+        construct, target, ..
+      } => (
+        codes::UNSUPPORTED,
+        format!("{construct} is not supported by the `{target}` target"),
+        Some("Write it another way, or compile for another target with `--target`".to_string()),
+      ),
+      CodegenError::UnsupportedType {
+        var_type, target, ..
+      } => {
+        let supported: Vec<String> = target::find(target)
+          .map(|target| target.info().var_types)
+          .unwrap_or_default()
+          .iter()
+          .map(|var_type| format!("`{var_type}`"))
+          .collect();
+        (
+          codes::UNSUPPORTED_TYPE,
+          format!("`{var_type}` is not supported by the `{target}` target"),
+          Some(format!(
+            "The `{target}` target supports {}",
+            supported.join(", ")
+          )),
+        )
+      }
+      CodegenError::Invalid { message, .. } => (codes::INVALID_PROGRAM, message.clone(), None),
+    };
+
+    let diagnostic = Diagnostic {
+      code: Some(code.to_string()),
+      help,
+      ..Diagnostic::error(error.to_string())
+    };
+    match error.location() {
+      Some(location) => diagnostic.with_span(location.clone(), label),
+      None => diagnostic,
+    }
+  }
+}
+
+/// A diagnostic for each error of the parser or the type checker.
+pub fn from_errors(errors: &[recovery::Error]) -> Vec<Diagnostic> {
+  errors.iter().map(Diagnostic::from).collect()
+}
+
+/// The tokens the lexer rejects, which stop the parser of a single expression at the first of them.
+pub fn invalid_tokens(source: &str) -> Vec<Diagnostic> {
+  lexer::errors(source)
+    .into_iter()
+    .map(|(kind, location)| {
+      let error = LexicalError::invalid_token(kind, location, source);
+      Diagnostic::from(&ParseError::User { error })
+    })
+    .collect()
+}
+
+/// Every diagnostic of a source: its invalid tokens, the errors of the parser and the type checker,
+/// and its lints at their default levels.
+pub fn check(source: &str, filepath: &str) -> Vec<Diagnostic> {
+  compiler::compile(source, filepath, &CompileOptions::default()).diagnostics
+}
+
+/// The line and column, both counted from 1, of a byte offset. Columns count characters.
+fn position(source: &str, offset: usize) -> (usize, usize) {
+  let offset = offset.min(source.len());
+  let line_start = source[..offset].rfind('\n').map_or(0, |index| index + 1);
+  let line = source[..offset].matches('\n').count() + 1;
+  (line, source[line_start..offset].chars().count() + 1)
+}
+
+impl Diagnostic {
+  /// The diagnostic as a JSON object, with the line and column of each span.
+  pub fn to_json(&self, source: &str, filepath: &str) -> Value {
+    let spans: Vec<Value> = self
+      .spans
+      .iter()
+      .enumerate()
+      .map(|(index, span)| {
+        let (line_start, column_start) = position(source, span.location.start);
+        let (line_end, column_end) = position(source, span.location.end);
+        json!({
+          "file": filepath,
+          "byte_start": span.location.start,
+          "byte_end": span.location.end,
+          "line_start": line_start,
+          "column_start": column_start,
+          "line_end": line_end,
+          "column_end": column_end,
+          "label": span.label,
+          "primary": index == 0,
+        })
+      })
+      .collect();
+
+    let fix = self.fix.as_ref().map(|fix| {
+      let (line_start, column_start) = position(source, fix.location.start);
+      let (line_end, column_end) = position(source, fix.location.end);
+      json!({
+        "byte_start": fix.location.start,
+        "byte_end": fix.location.end,
+        "line_start": line_start,
+        "column_start": column_start,
+        "line_end": line_end,
+        "column_end": column_end,
+        "replacement": fix.replacement,
+      })
+    });
+
+    json!({
+      "file": filepath,
+      "code": self.code,
+      "severity": self.severity.name(),
+      "message": self.message,
+      "spans": spans,
+      "help": self.help,
+      "fix": fix,
+    })
+  }
+}
+
+/// The diagnostics as a SARIF 2.1.0 log, with one result each.
+pub fn sarif(diagnostics: &[Diagnostic], source: &str, filepath: &str) -> Value {
+  let region = |location: &Range<usize>| {
+    let (start_line, start_column) = position(source, location.start);
+    let (end_line, end_column) = position(source, location.end);
+    json!({
+      "startLine": start_line,
+      "startColumn": start_column,
+      "endLine": end_line,
+      "endColumn": end_column,
+    })
+  };
+  let location = |span: &Span| {
+    json!({
+      "physicalLocation": {
+        "artifactLocation": { "uri": filepath },
+        "region": region(&span.location),
+      },
+      "message": { "text": span.label },
+    })
+  };
+
+  let results: Vec<Value> = diagnostics
+    .iter()
+    .map(|diagnostic| {
+      let text = match &diagnostic.help {
+        Some(help) => format!("{}\nhelp: {help}", diagnostic.message),
+        None => diagnostic.message.clone(),
+      };
+      let locations: Vec<Value> = match diagnostic.spans.split_first() {
+        Some((primary, _)) => vec![location(primary)],
+        None => vec![json!({ "physicalLocation": { "artifactLocation": { "uri": filepath } } })],
+      };
+      let related: Vec<Value> = diagnostic.spans.iter().skip(1).map(location).collect();
+
+      let mut result = json!({
+        "level": diagnostic.severity.name(),
+        "message": { "text": text },
+        "locations": locations,
+        "relatedLocations": related,
+      });
+      if let Some(code) = &diagnostic.code {
+        result["ruleId"] = json!(code);
+      }
+      if let Some(fix) = &diagnostic.fix {
+        result["fixes"] = json!([{
+          "description": { "text": format!("Replace with `{}`", fix.replacement) },
+          "artifactChanges": [{
+            "artifactLocation": { "uri": filepath },
+            "replacements": [{
+              "deletedRegion": region(&fix.location),
+              "insertedContent": { "text": fix.replacement },
+            }],
+          }],
+        }]);
+      }
+      result
+    })
+    .collect();
+
+  let mut rules: Vec<&String> = diagnostics
+    .iter()
+    .filter_map(|diagnostic| diagnostic.code.as_ref())
+    .collect();
+  rules.sort();
+  rules.dedup();
+
+  json!({
+    "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+    "version": "2.1.0",
+    "runs": [{
+      "tool": {
+        "driver": {
+          "name": "compass",
+          "version": env!("CARGO_PKG_VERSION"),
+          "informationUri": "https://github.com/celestial-hub/compass",
+          "rules": rules.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+        },
+      },
+      "columnKind": "unicodeCodePoints",
+      "results": results,
+    }],
+  })
+}
+
+/// The diagnostics in a format, JSON lines or a SARIF log, ending with a newline.
+pub fn render(format: Format, diagnostics: &[Diagnostic], source: &str, filepath: &str) -> String {
+  match format {
+    Format::Json => diagnostics
+      .iter()
+      .map(|diagnostic| format!("{}\n", diagnostic.to_json(source, filepath)))
+      .collect(),
+    Format::Sarif => format!("{:#}\n", sarif(diagnostics, source, filepath)),
+  }
+}
+
+/// Each span under its line of the source, with carets below it, for interactive tools.
+pub fn plain(source: &str, diagnostics: &[Diagnostic]) -> String {
+  let mut lines = vec![];
+
+  for diagnostic in diagnostics {
+    let severity = diagnostic.severity.name();
+    if diagnostic.spans.is_empty() {
+      lines.push(format!("{severity}: {}", diagnostic.message));
+    }
+
+    for span in &diagnostic.spans {
+      let start = span.location.start.min(source.len());
+      let line_start = source[..start].rfind('\n').map_or(0, |index| index + 1);
+      let line_end = source[start..]
+        .find('\n')
+        .map_or(source.len(), |index| start + index);
+      let end = span.location.end.clamp(start, line_end);
+
+      lines.push(format!("{severity}: {}", span.label));
+      lines.push(format!("  {}", &source[line_start..line_end]));
+      lines.push(format!(
+        "  {}{}",
+        " ".repeat(source[line_start..start].chars().count()),
+        "^".repeat(source[start..end].chars().count().max(1))
+      ));
+    }
+
+    if let Some(help) = &diagnostic.help {
+      lines.push(format!("help: {help}"));
+    }
+  }
+
+  lines.join("\n")
+}
+
+/// Prints the diagnostic as an ariadne report on stderr.
+pub fn print(diagnostic: &Diagnostic, filename: &str, source: &str) {
+  use ariadne::{Color, ColorGenerator, Config, Fmt, Label, Report, ReportKind, Source};
+
+  let (kind, color) = match diagnostic.severity {
+    Severity::Error => (ReportKind::Error, Color::Red),
+    Severity::Warning => (ReportKind::Warning, Color::Yellow),
+    Severity::Note => (ReportKind::Advice, Color::Blue),
+  };
+  let offset = diagnostic
+    .spans
+    .first()
+    .map_or(0, |span| span.location.start);
+
+  let mut colors = ColorGenerator::default();
+  let mut report = Report::build(kind, filename, offset)
+    .with_config(Config::default().with_tab_width(2))
+    .with_message(diagnostic.message.clone().fg(color))
+    .with_note(format!(
+      "If you think this is a bug, please file an issue at {}",
+      "github.com/celestial-hub/compass/issues".fg(Color::Blue)
+    ));
+
+  if let Some(code) = &diagnostic.code {
+    report = report.with_code(code);
+  }
+  for span in &diagnostic.spans {
+    report = report.with_label(
+      Label::new((filename, span.location.clone()))
+        .with_message(span.label.clone())
+        .with_color(colors.next()),
+    );
+  }
+  if let Some(help) = &diagnostic.help {
+    report = report.with_help(help.clone());
+  }
+
+  report
+    .finish()
+    .eprint((filename, Source::from(source)))
+    .unwrap();
+}
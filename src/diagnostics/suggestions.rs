@@ -0,0 +1,77 @@
+// "Did you mean" suggestions, the known names closest to a misspelt one.
+//
+// A name is close when it is at most a third of its length of edits away, one edit at least, like
+// rustc does. The closest names are all suggested, and only a single one is offered as a fix-it.
+
+use std::ops::Range;
+
+use crate::lexer::FixIt;
+
+/// The Levenshtein distance between two names, counting characters.
+pub fn distance(a: &str, b: &str) -> usize {
+  let b: Vec<char> = b.chars().collect();
+  let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+  for (i, a) in a.chars().enumerate() {
+    let mut current = vec![i + 1];
+    for (j, b) in b.iter().enumerate() {
+      let substitution = previous[j] + usize::from(a != *b);
+      current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+    }
+    previous = current;
+  }
+
+  previous[b.len()]
+}
+
+/// The candidates closest to `name`, sorted, none when all of them are too far.
+pub fn similar<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+  let limit = (name.chars().count() / 3).max(1);
+
+  let mut closest = vec![];
+  let mut best = limit + 1;
+  for candidate in candidates {
+    let distance = distance(name, candidate);
+    if distance == 0 || distance > best {
+      continue;
+    }
+    if distance < best {
+      best = distance;
+      closest.clear();
+    }
+    closest.push(candidate);
+  }
+
+  closest.sort_unstable();
+  closest.dedup();
+  closest
+}
+
+/// The help suggesting the names closest to the one at `location`, with its fix-it when there is
+/// a single one.
+pub fn did_you_mean<'a>(
+  name: &str,
+  candidates: impl IntoIterator<Item = &'a str>,
+  location: Range<usize>,
+) -> (Option<String>, Option<FixIt>) {
+  match similar(name, candidates)[..] {
+    [] => (None, None),
+    [suggestion] => (
+      Some(format!("Did you mean `{suggestion}`?")),
+      Some(FixIt {
+        location,
+        replacement: suggestion.to_string(),
+      }),
+    ),
+    ref suggestions => {
+      let suggestions: Vec<String> = suggestions
+        .iter()
+        .map(|suggestion| format!("`{suggestion}`"))
+        .collect();
+      (
+        Some(format!("Did you mean one of {}?", suggestions.join(", "))),
+        None,
+      )
+    }
+  }
+}
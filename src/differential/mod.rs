@@ -0,0 +1,239 @@
+// Differential testing of the MIPS backend against the reference interpreter.
+//
+// A program runs in the interpreter and, compiled by `MipsCodegen`, in the MIPS simulator. Both
+// runs must write the same output, stop the same way and leave every variable with the same value,
+// compared as the 32-bit word of the register the backend keeps it in. On a mismatch, the program
+// is reduced line by line (delta debugging) to a smaller one that still disagrees. A program the
+// interpreter runs is only left out when the target rejects its types up front, failing to compile
+// or assemble it later is a mismatch too.
+
+use std::collections::BTreeMap;
+
+use crate::{
+  ast::{context::Context as ParserContext, Statement, VarType},
+  codegen::{
+    context::Context,
+    mips::{
+      assembler::{assemble, Layout},
+      simulator::{Exit, Simulator},
+      target::{self, registers},
+      MipsCodegen,
+    },
+    Artifact, Codegen,
+  },
+  interpreter::Interpreter,
+  lexer::Lexer,
+  parser::parse_program,
+  runtime::{Io, RuntimeError, Value},
+};
+
+/// What a run left behind.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outcome {
+  pub output: String,
+  /// Why the program stopped before its end, if it did
+  pub error: Option<String>,
+  /// Final values of the variables, as 32-bit words
+  pub variables: BTreeMap<String, u32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mismatch {
+  pub differences: Vec<String>,
+  /// The smallest program found that still shows a mismatch
+  pub minimized: String,
+}
+
+impl std::fmt::Display for Mismatch {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    for difference in &self.differences {
+      writeln!(f, "- {difference}")?;
+    }
+    writeln!(f, "minimized program:")?;
+    for line in self.minimized.lines() {
+      writeln!(f, "  {line}")?;
+    }
+    Ok(())
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Verdict {
+  Agree,
+  /// The program cannot be compared, because it does not parse, does not terminate, or has types
+  /// the target does not support
+  Skipped(String),
+  Mismatch(Mismatch),
+}
+
+/// Runs the program in both engines with the same input, and minimizes it when they disagree.
+pub fn check(source: &str, input: &str) -> Verdict {
+  match differences(source, input) {
+    Err(reason) => Verdict::Skipped(reason),
+    Ok(differences) if differences.is_empty() => Verdict::Agree,
+    Ok(differences) => Verdict::Mismatch(Mismatch {
+      differences,
+      minimized: minimize(source, input),
+    }),
+  }
+}
+
+// Parses without printing the diagnostics, as most candidates of the minimization are invalid
+fn parse(source: &str) -> Result<Vec<Statement>, String> {
+  let lexer = Lexer::new(source, "differential").map_err(|error| error.to_string())?;
+  parse_program(&mut ParserContext::new(0), lexer).map_err(|errors| {
+    errors
+      .iter()
+      .map(ToString::to_string)
+      .collect::<Vec<_>>()
+      .join("\n")
+  })
+}
+
+// The register word holding a value, strings are addresses and cannot be compared
+fn word(value: &Value) -> Option<u32> {
+  match value.cast(VarType::I64) {
+    Value::I64(value) => Some(value as u32),
+    _ => None,
+  }
+}
+
+/// Runs the program in the reference interpreter, fails when it does not terminate.
+pub fn interpret(ast: &[Statement], input: &str) -> Result<Outcome, String> {
+  let mut interpreter = Interpreter::new(ast);
+  let mut output = vec![];
+  let result = interpreter.run(&mut Io::new(&mut input.as_bytes(), &mut output));
+
+  let error = match result {
+    Ok(()) => None,
+    Err(error @ RuntimeError::StepLimitExceeded(_)) => return Err(error.to_string()),
+    Err(error) => Some(error.to_string()),
+  };
+
+  Ok(Outcome {
+    output: String::from_utf8_lossy(&output).into_owned(),
+    error,
+    variables: interpreter
+      .globals
+      .iter()
+      .filter_map(|(name, value)| Some((name.clone(), word(value)?)))
+      .collect(),
+  })
+}
+
+/// Compiles the program with `MipsCodegen` and runs it in the simulator, fails when the backend
+/// cannot compile it.
+pub fn simulate(ast: &[Statement], input: &str) -> Result<Outcome, String> {
+  target::INFO.check(ast).map_err(|error| error.to_string())?;
+
+  let mut context = Context::default();
+  let generated = MipsCodegen.generate(ast.to_vec(), &mut context);
+  let Artifact::Mips(program) = generated.map_err(|error| error.to_string())? else {
+    unreachable!("`MipsCodegen` generates MIPS programs")
+  };
+
+  let mut simulator = Simulator::new(&assemble(&program, Layout::MARS)?)?;
+  let mut output = vec![];
+  let report = simulator.run(&mut Io::new(&mut input.as_bytes(), &mut output));
+
+  let error = match &report.exit {
+    Exit::Exited(0) => None,
+    Exit::Exited(code) => Some(format!("exited with code {code}")),
+    Exit::Faulted { .. } => Some(report.to_string()),
+  };
+
+  Ok(Outcome {
+    output: String::from_utf8_lossy(&output).into_owned(),
+    error,
+    variables: context
+      .register_map
+      .iter()
+      // Temporaries are named after their register
+      .filter(|(name, _)| !name.starts_with('$'))
+      .filter_map(|(name, register)| {
+        let number = registers::number(register)?;
+        Some((name.clone(), simulator.registers[number as usize]))
+      })
+      .collect(),
+  })
+}
+
+/// How the runs of the program in both engines differ, fails when the program cannot be compared.
+pub fn differences(source: &str, input: &str) -> Result<Vec<String>, String> {
+  let ast = parse(source)?;
+  let expected = interpret(&ast, input).map_err(|error| format!("interpreter: {error}"))?;
+  target::INFO
+    .check(&ast)
+    .map_err(|error| format!("mips: {error}"))?;
+  let actual = match simulate(&ast, input) {
+    Ok(actual) => actual,
+    Err(error) => return Ok(vec![format!("mips could not run it: {error}")]),
+  };
+
+  let mut differences = vec![];
+  if expected.output != actual.output {
+    differences.push(format!(
+      "output: the interpreter wrote {:?}, mips wrote {:?}",
+      expected.output, actual.output
+    ));
+  }
+
+  match (&expected.error, &actual.error) {
+    (None, Some(error)) => differences.push(format!("mips stopped early: {error}")),
+    (Some(error), None) => differences.push(format!(
+      "the interpreter stopped early but mips did not: {error}"
+    )),
+    (Some(_), Some(_)) => {}
+    (None, None) => {
+      for (name, value) in &expected.variables {
+        match actual.variables.get(name) {
+          Some(actual) if actual != value => differences.push(format!(
+            "variable `{name}`: the interpreter has {}, mips has {}",
+            *value as i32, *actual as i32
+          )),
+          _ => {}
+        }
+      }
+    }
+  }
+
+  Ok(differences)
+}
+
+/// Removes as many lines as possible while the engines still disagree on the program.
+pub fn minimize(source: &str, input: &str) -> String {
+  reduce(
+    source,
+    |candidate| matches!(differences(candidate, input), Ok(differences) if !differences.is_empty()),
+  )
+}
+
+/// Removes as many lines of the source as possible while `fails` holds for what is left.
+pub fn reduce(source: &str, fails: impl Fn(&str) -> bool) -> String {
+  let still_fails = |lines: &[&str]| fails(&lines.join("\n"));
+
+  let mut lines: Vec<&str> = source
+    .lines()
+    .filter(|line| !line.trim().is_empty())
+    .collect();
+  let mut chunks = 2;
+  while lines.len() >= 2 {
+    let size = lines.len().div_ceil(chunks);
+    let reduced = (0..lines.len()).step_by(size).find_map(|start| {
+      let mut candidate = lines.clone();
+      candidate.drain(start..(start + size).min(lines.len()));
+      still_fails(&candidate).then_some(candidate)
+    });
+
+    match reduced {
+      Some(candidate) => {
+        lines = candidate;
+        chunks = (chunks - 1).max(2);
+      }
+      None if chunks >= lines.len() => break,
+      None => chunks = (chunks * 2).min(lines.len()),
+    }
+  }
+
+  lines.join("\n")
+}
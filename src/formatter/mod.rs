@@ -0,0 +1,261 @@
+// Opinionated formatter for ETAC sources.
+//
+// It works on the token stream with its trivia, so it also formats files that do not type check,
+// and gives back every comment. Lines keep their statements, with one space between tokens.
+// Function bodies are indented by two spaces, labels get a line of their own at the indentation of
+// their block, and the statements after a label are indented one more level. Trailing comments of
+// consecutive lines start at the same column, two spaces after the longest of them.
+
+use crate::lexer::{
+  tokens::Token,
+  trivia::{self, Piece},
+};
+
+const INDENT: usize = 2;
+
+#[derive(Default)]
+struct Line<'input> {
+  tokens: Vec<(Token, &'input str)>,
+  comment: Option<&'input str>,
+}
+
+impl Line<'_> {
+  fn is_blank(&self) -> bool {
+    self.tokens.is_empty() && self.comment.is_none()
+  }
+
+  // `name:` on its own, `end` is a keyword but also a common label name
+  fn is_label(&self) -> bool {
+    matches!(
+      self.tokens.as_slice(),
+      [(Token::Identifier(_) | Token::End, _), (Token::Colon, _)]
+    )
+  }
+
+  fn code(&self) -> String {
+    let mut code = String::new();
+
+    for (index, (token, text)) in self.tokens.iter().enumerate() {
+      let spaced = match index
+        .checked_sub(1)
+        .map(|previous| &self.tokens[previous].0)
+      {
+        None => false,
+        Some(Token::OpenParen) => false,
+        Some(Token::Identifier(_)) if *token == Token::OpenParen => false,
+        Some(_) => !matches!(token, Token::Colon | Token::CloseParen),
+      };
+
+      if spaced {
+        code.push(' ');
+      }
+      code.push_str(text);
+    }
+
+    code
+  }
+}
+
+// A function body, or the top level
+struct Block {
+  /// Indentation of its labels
+  base: usize,
+  /// Whether a label was seen, so that the statements are indented under it
+  labelled: bool,
+}
+
+impl Block {
+  fn column(&self) -> usize {
+    match self.labelled {
+      true => self.base + INDENT,
+      false => self.base,
+    }
+  }
+}
+
+/// Formats the source, fails when it does not lex.
+pub fn format(source: &str) -> Result<String, String> {
+  let pieces = trivia::lex(source)?;
+  let lines = split_labels(lines(&pieces));
+  let indents = indentation(&lines);
+
+  // Trailing comments of consecutive lines are aligned
+  let codes: Vec<String> = lines.iter().map(Line::code).collect();
+  let mut comment_columns = vec![0; lines.len()];
+  let mut start = 0;
+  while start < lines.len() {
+    let trailing = |index: usize| !lines[index].tokens.is_empty() && lines[index].comment.is_some();
+    if !trailing(start) {
+      start += 1;
+      continue;
+    }
+
+    let mut end = start;
+    while end < lines.len() && trailing(end) {
+      end += 1;
+    }
+
+    let column = (start..end)
+      .map(|index| indents[index] + codes[index].len() + 2)
+      .max()
+      .unwrap_or_default();
+    comment_columns[start..end].fill(column);
+    start = end;
+  }
+
+  let mut formatted = String::new();
+  let mut previous_blank = true;
+  for (index, line) in lines.iter().enumerate() {
+    // Runs of blank lines collapse into one, and the file starts with code or a comment
+    if line.is_blank() {
+      if !previous_blank {
+        formatted.push('\n');
+      }
+      previous_blank = true;
+      continue;
+    }
+    previous_blank = false;
+
+    let mut text = " ".repeat(indents[index]) + &codes[index];
+    if let Some(comment) = line.comment {
+      if !line.tokens.is_empty() {
+        let padding = comment_columns[index] - text.len();
+        text += &" ".repeat(padding);
+      }
+      text += comment;
+    }
+
+    formatted += &text;
+    formatted.push('\n');
+  }
+
+  // Blank lines at the end of the file are dropped too
+  while formatted.ends_with("\n\n") {
+    formatted.pop();
+  }
+
+  let expected = significant(&pieces);
+  if significant(&trivia::lex(&formatted)?) != expected {
+    return Err("Formatting would change the meaning of the program".to_string());
+  }
+
+  Ok(formatted)
+}
+
+// The tokens and comments, in order, which formatting must not change
+fn significant<'input>(pieces: &[trivia::Spanned<'input>]) -> Vec<Piece<'input>> {
+  pieces
+    .iter()
+    .filter(|(piece, _)| *piece != Piece::Newline)
+    .map(|(piece, _)| piece.clone())
+    .collect()
+}
+
+fn lines<'input>(pieces: &[trivia::Spanned<'input>]) -> Vec<Line<'input>> {
+  let mut lines = vec![Line::default()];
+
+  for (piece, _) in pieces {
+    let line = lines.last_mut().unwrap();
+    match piece {
+      Piece::Token(token, text) => line.tokens.push((token.clone(), text)),
+      Piece::Comment(comment) => line.comment = Some(comment),
+      Piece::Newline => lines.push(Line::default()),
+    }
+  }
+
+  lines
+}
+
+// `loop: if i < n goto loop` becomes a label line followed by the statement
+fn split_labels(lines: Vec<Line<'_>>) -> Vec<Line<'_>> {
+  let mut split = vec![];
+
+  for mut line in lines {
+    let labelled = matches!(
+      line.tokens.as_slice(),
+      [(Token::Identifier(_) | Token::End, _), (Token::Colon, _), (next, _), ..]
+        if !matches!(next, Token::Type(_))
+    );
+
+    if labelled {
+      let statement = line.tokens.split_off(2);
+      split.push(line);
+      split.push(Line {
+        tokens: statement,
+        comment: None,
+      });
+      let last = split.len() - 1;
+      split[last].comment = split[last - 1].comment.take();
+    } else {
+      split.push(line);
+    }
+  }
+
+  split
+}
+
+fn indentation(lines: &[Line<'_>]) -> Vec<usize> {
+  let mut blocks = vec![Block {
+    base: 0,
+    labelled: false,
+  }];
+  let mut indents = vec![0; lines.len()];
+  // Comment lines are indented like the code that follows them
+  let mut comments = vec![];
+
+  for (index, line) in lines.iter().enumerate() {
+    if line.tokens.is_empty() {
+      if line.comment.is_some() {
+        comments.push(index);
+      }
+      continue;
+    }
+
+    let column = blocks.last().unwrap().column();
+    let indent = if line.is_label() {
+      let block = blocks.last_mut().unwrap();
+      block.labelled = true;
+      block.base
+    } else if ends_block(line, 0) && blocks.len() > 1 {
+      blocks.pop();
+      blocks.last().unwrap().column()
+    } else {
+      column
+    };
+
+    for comment in comments.drain(..) {
+      indents[comment] = match ends_block(line, 0) {
+        true => column,
+        false => indent,
+      };
+    }
+    indents[index] = indent;
+
+    for (position, (token, _)) in line.tokens.iter().enumerate() {
+      if *token == Token::Begin {
+        blocks.push(Block {
+          base: indent + INDENT,
+          labelled: false,
+        });
+      } else if position > 0 && ends_block(line, position) && blocks.len() > 1 {
+        blocks.pop();
+      }
+    }
+  }
+
+  let column = blocks.last().unwrap().column();
+  for comment in comments {
+    indents[comment] = column;
+  }
+
+  indents
+}
+
+// `end` closes a function, unless it is the target of a `goto` or a label
+fn ends_block(line: &Line<'_>, position: usize) -> bool {
+  let tokens = &line.tokens;
+
+  tokens.get(position).map(|(token, _)| token) == Some(&Token::End)
+    && (position == 0 || tokens[position - 1].0 != Token::Goto)
+    && tokens.get(position + 1).map(|(token, _)| token) != Some(&Token::Colon)
+}
@@ -0,0 +1,556 @@
+// Random, well-typed ETAC programs for fuzzing the compiler.
+//
+// Programs are built only from what the parser accepts: literals of every type with a syntax,
+// arithmetic and comparisons, forward jumps, counted loops, user functions and the builtins. Loops
+// count up to a small bound and functions only call the functions defined before them, so every
+// program terminates, and the generator keeps an upper bound of the statements it executes.
+// `ptr` has neither a type annotation nor literals yet, so it is never generated.
+//
+// `Config::for_target` keeps to what a backend supports. For one that never reuses a register,
+// the values a program holds are budgeted, and a statement going over it ends its body.
+
+use crate::{
+  ast::{Condition, Operator, VarType},
+  codegen::{target::TargetInfo, Codegen},
+  compiler::{self, CompileOptions},
+};
+
+/// Types a variable can be declared with.
+pub const TYPES: [VarType; 12] = [
+  VarType::I8,
+  VarType::I16,
+  VarType::I32,
+  VarType::I64,
+  VarType::U8,
+  VarType::U16,
+  VarType::U32,
+  VarType::U64,
+  VarType::F32,
+  VarType::F64,
+  VarType::Bool,
+  VarType::Str,
+];
+
+const OPERATORS: [Operator; 4] = [Operator::Add, Operator::Sub, Operator::Mul, Operator::Div];
+
+const COMPARISONS: [Condition; 6] = [
+  Condition::LessThan,
+  Condition::GreaterThan,
+  Condition::LessThanOrEqual,
+  Condition::GreaterThanOrEqual,
+  Condition::Equal,
+  Condition::NotEqual,
+];
+
+const LOGICAL: [Condition; 3] = [Condition::And, Condition::Or, Condition::Equal];
+
+const WORDS: [&str; 8] = [
+  "compass", "north", "star", "hello", "orbit", "zenith", "needle", "map",
+];
+
+#[derive(Clone, Debug)]
+pub struct Config {
+  /// Types of the variables
+  pub types: Vec<VarType>,
+  /// User functions, defined before the top level statements
+  pub functions: usize,
+  /// Statements of the top level and of each function
+  pub statements: usize,
+  /// How deep loops and jumps nest
+  pub depth: usize,
+  /// Iterations of each loop
+  pub iterations: u32,
+  /// Most values the program holds: its variables, loop counters included, and its literals,
+  /// which a target without enough immediates loads into registers of their own
+  pub values: usize,
+  /// Whether conditions may compare strings
+  pub string_comparisons: bool,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      types: TYPES.to_vec(),
+      functions: 3,
+      statements: 12,
+      depth: 2,
+      iterations: 4,
+      values: usize::MAX,
+      string_comparisons: true,
+    }
+  }
+}
+
+impl Config {
+  /// A configuration that only generates what `target` supports, as far as its facts tell.
+  pub fn for_target(target: &TargetInfo) -> Self {
+    Self {
+      types: TYPES
+        .into_iter()
+        .filter(|var_type| target.supports(*var_type))
+        .collect(),
+      values: target.max_values.unwrap_or(usize::MAX),
+      string_comparisons: target.compares_strings,
+      ..Self::default()
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Program {
+  pub source: String,
+  /// No run of the program executes more statements than this
+  pub max_steps: u64,
+}
+
+/// Generates a program with the default configuration.
+pub fn generate(seed: u64) -> Program {
+  Generator::new(seed, Config::default()).program()
+}
+
+/// Seeds `generate_for` tries before giving up
+pub const ATTEMPTS: u64 = 1000;
+
+/// Generates the first program from `seed` on that `target` compiles, with the seed that generated
+/// it. Its facts do not tell everything a backend needs, like the temporaries of each statement,
+/// so the programs it rejects are skipped.
+pub fn generate_for(target: &dyn Codegen, seed: u64, config: &Config) -> Option<(u64, Program)> {
+  let options = CompileOptions::target(target.info().name);
+
+  (seed..seed.saturating_add(ATTEMPTS)).find_map(|seed| {
+    let program = Generator::new(seed, config.clone()).program();
+    let output = compiler::compile(&program.source, "generated", &options);
+    output.artifact.is_some().then_some((seed, program))
+  })
+}
+
+struct Signature {
+  name: String,
+  args: Vec<VarType>,
+  return_type: VarType,
+  /// Statements a call runs at most
+  steps: u64,
+}
+
+pub struct Generator {
+  rng: Rng,
+  config: Config,
+  /// Variables that are assigned at this point of the program
+  variables: Vec<(String, VarType)>,
+  functions: Vec<Signature>,
+  names: usize,
+  /// Values held so far, out of `config.values`
+  values: usize,
+  /// Values the program may hold at the end of the current body
+  budget: usize,
+  indent: usize,
+  source: String,
+}
+
+impl Generator {
+  pub fn new(seed: u64, config: Config) -> Self {
+    Self {
+      rng: Rng(seed),
+      config,
+      variables: vec![],
+      functions: vec![],
+      names: 0,
+      values: 0,
+      budget: 0,
+      indent: 0,
+      source: String::new(),
+    }
+  }
+
+  pub fn program(mut self) -> Program {
+    let mut steps = 0;
+
+    // Each function gets its share of the values, and the top level the rest
+    let share = self.config.values / (self.config.functions + 1);
+    for function in 1..=self.config.functions {
+      self.budget = share * function;
+      steps += 1 + self.function();
+    }
+
+    self.budget = self.config.values;
+    let statements = self.config.statements;
+    steps += self.body(statements, self.config.depth);
+
+    Program {
+      source: self.source,
+      max_steps: steps,
+    }
+  }
+
+  fn name(&mut self, prefix: &str) -> String {
+    self.names += 1;
+    format!("{prefix}{}", self.names)
+  }
+
+  // A name for a new variable, which holds a value
+  fn variable(&mut self, prefix: &str) -> String {
+    self.values += 1;
+    self.name(prefix)
+  }
+
+  fn line(&mut self, line: &str) {
+    self.source += &"  ".repeat(self.indent);
+    self.source += line;
+    self.source += "\n";
+  }
+
+  fn function(&mut self) -> u64 {
+    let name = self.name("fun");
+    let args: Vec<VarType> = (0..self.rng.below(3))
+      .map(|_| *self.rng.pick(&self.config.types))
+      .collect();
+    let return_type = match self.rng.chance(3) {
+      true => VarType::Void,
+      false => *self.rng.pick(&self.config.types),
+    };
+
+    let args_source = args
+      .iter()
+      .map(|var_type| format!("{}: {var_type}", self.name("a")))
+      .collect::<Vec<_>>()
+      .join(" ");
+    match return_type {
+      VarType::Void => self.line(&format!("func {name}({args_source})")),
+      _ => self.line(&format!("func {name}({args_source}): {return_type}")),
+    }
+    self.line("begin");
+
+    // The arguments are not in scope of the body, and its variables are not assigned outside it
+    let variables = std::mem::take(&mut self.variables);
+    self.indent += 1;
+    let statements = self.rng.between(1, self.config.statements);
+    let steps = self.body(statements, self.config.depth);
+    self.indent -= 1;
+    self.variables = variables;
+
+    self.line("end");
+    self.line("");
+
+    self.functions.push(Signature {
+      name,
+      args,
+      return_type,
+      steps,
+    });
+
+    steps
+  }
+
+  fn body(&mut self, statements: usize, depth: usize) -> u64 {
+    let mut steps = 0;
+
+    for _ in 0..statements {
+      let checkpoint = (self.source.len(), self.variables.len(), self.values);
+      let statement = self.statement(depth);
+
+      // A statement holding too many values is left out, with the ones after it
+      if self.values > self.budget {
+        self.values = checkpoint.2;
+        self.source.truncate(checkpoint.0);
+        self.variables.truncate(checkpoint.1);
+        break;
+      }
+      steps += statement;
+    }
+
+    steps
+  }
+
+  // A nested body, whose variables may not be assigned after it
+  fn block(&mut self, depth: usize) -> u64 {
+    let variables = self.variables.len();
+    self.indent += 1;
+    let statements = self.rng.between(1, self.config.statements / 2);
+    let steps = self.body(statements, depth);
+    self.indent -= 1;
+    self.variables.truncate(variables);
+
+    steps
+  }
+
+  fn statement(&mut self, depth: usize) -> u64 {
+    match self.rng.below(10) {
+      0 | 1 => {
+        let var_type = *self.rng.pick(&self.config.types);
+        let name = self.variable("v");
+        let steps = self.declaration(&name, var_type);
+        self.variables.push((name, var_type));
+        steps
+      }
+      2 | 3 if !self.variables.is_empty() => {
+        let (name, var_type) = self.rng.pick(&self.variables).clone();
+        self.declaration(&name, var_type)
+      }
+      4 => {
+        let number = self.operand(VarType::I32);
+        self.line(&format!("call write_int({number})"));
+        1
+      }
+      5 => {
+        let message = self.operand(VarType::Str);
+        self.line(&format!("call write_string({message})"));
+        1
+      }
+      6 if self
+        .functions
+        .iter()
+        .any(|f| f.return_type == VarType::Void) =>
+      {
+        let index = self.rng.below(self.functions.len());
+        match self.functions[index].return_type {
+          VarType::Void => {
+            let (call, steps) = self.call(index);
+            self.line(&call);
+            1 + steps
+          }
+          _ => self.statement(depth),
+        }
+      }
+      7 | 8 if depth > 0 => self.jump(depth),
+      9 if depth > 0 => self.counted_loop(depth),
+      _ => self.statement(depth),
+    }
+  }
+
+  fn declaration(&mut self, name: &str, var_type: VarType) -> u64 {
+    let (value, steps) = self.expr(var_type);
+    self.line(&format!("{name}: {var_type} = {value}"));
+    1 + steps
+  }
+
+  // Forward jumps over a nested body, taken or not
+  fn jump(&mut self, depth: usize) -> u64 {
+    let label = self.name("skip");
+
+    let jump = match self.rng.below(5) {
+      0 => format!("goto {label}"),
+      1 => format!("if {} goto {label}", self.rng.pick(&["true", "false"])),
+      2 if self.variables.iter().any(|(_, t)| *t == VarType::Bool) => {
+        format!(
+          "if {} goto {label}",
+          self.identifier(VarType::Bool).unwrap()
+        )
+      }
+      _ => format!("if {} goto {label}", self.comparison()),
+    };
+    self.line(&jump);
+
+    let steps = self.block(depth - 1);
+    self.line(&format!("{label}:"));
+
+    2 + steps
+  }
+
+  // A loop that runs a nested body a fixed number of times, with its own counter
+  fn counted_loop(&mut self, depth: usize) -> u64 {
+    let label = self.name("loop");
+    let counter = self.variable("n");
+    let iterations = self.rng.between(1, self.config.iterations as usize);
+
+    self.line(&format!("{counter}: i32 = 0"));
+    self.line(&format!("{label}:"));
+    let steps = self.block(depth - 1);
+    self.indent += 1;
+    self.line(&format!("{counter}: i32 = {counter} + 1"));
+    match self.rng.chance(2) {
+      true => self.line(&format!("if {counter} < {iterations} goto {label}")),
+      false => {
+        let flag = self.variable("c");
+        self.line(&format!("{flag}: bool = {counter} < {iterations}"));
+        self.line(&format!("if {flag} goto {label}"));
+      }
+    }
+    self.indent -= 1;
+
+    1 + iterations as u64 * (4 + steps)
+  }
+
+  fn expr(&mut self, var_type: VarType) -> (String, u64) {
+    let numeric = !matches!(var_type, VarType::Bool | VarType::Str);
+
+    match self.rng.below(6) {
+      0 | 1 if numeric => (self.arithmetic(var_type), 0),
+      0 | 1 if var_type == VarType::Bool => (self.comparison(), 0),
+      2 => {
+        let candidates: Vec<usize> = (0..self.functions.len())
+          .filter(|index| self.functions[*index].return_type == var_type)
+          .collect();
+
+        match candidates.is_empty() {
+          false => {
+            let index = *self.rng.pick(&candidates);
+            self.call(index)
+          }
+          true => (self.operand(var_type), 0),
+        }
+      }
+      3 if var_type == VarType::I32 => ("call read_int()".to_string(), 0),
+      3 if var_type == VarType::Str => {
+        let size = self.rng.between(1, 32);
+        (format!("call read_string({size}u32)"), 0)
+      }
+      _ => (self.operand(var_type), 0),
+    }
+  }
+
+  fn arithmetic(&mut self, var_type: VarType) -> String {
+    let operator = self.rng.pick(&OPERATORS).clone();
+    let lhs = self.operand(var_type);
+    let rhs = match operator {
+      // Dividing by a variable could divide by zero
+      Operator::Div => self.divisor(var_type),
+      _ => self.operand(var_type),
+    };
+
+    format!("{lhs} {operator} {rhs}")
+  }
+
+  fn comparison(&mut self) -> String {
+    let types: Vec<VarType> = self
+      .config
+      .types
+      .iter()
+      .copied()
+      .filter(|var_type| self.config.string_comparisons || *var_type != VarType::Str)
+      .collect();
+    let var_type = match types.is_empty() {
+      true => VarType::I32,
+      false => *self.rng.pick(&types),
+    };
+    let condition = match var_type {
+      VarType::Bool => self.rng.pick(&LOGICAL).clone(),
+      _ => self.rng.pick(&COMPARISONS).clone(),
+    };
+
+    let (lhs, rhs) = (self.operand(var_type), self.operand(var_type));
+    format!("{lhs} {condition} {rhs}")
+  }
+
+  fn call(&mut self, index: usize) -> (String, u64) {
+    let args = self.functions[index].args.clone();
+    let params = args
+      .into_iter()
+      .map(|var_type| self.operand(var_type))
+      .collect::<Vec<_>>()
+      .join(" ");
+
+    let function = &self.functions[index];
+    (format!("call {}({params})", function.name), function.steps)
+  }
+
+  fn identifier(&mut self, var_type: VarType) -> Option<String> {
+    let candidates: Vec<&String> = self
+      .variables
+      .iter()
+      .filter(|(_, candidate)| *candidate == var_type)
+      .map(|(name, _)| name)
+      .collect();
+
+    match candidates.is_empty() {
+      true => None,
+      false => Some(self.rng.pick(&candidates).to_string()),
+    }
+  }
+
+  fn operand(&mut self, var_type: VarType) -> String {
+    match self.rng.chance(2) {
+      true => self
+        .identifier(var_type)
+        .unwrap_or_else(|| self.literal(var_type)),
+      false => self.literal(var_type),
+    }
+  }
+
+  fn literal(&mut self, var_type: VarType) -> String {
+    self.values += 1;
+    let suffix = self.suffix(var_type);
+    let integer = |rng: &mut Rng, low, high| {
+      // Both spellings of the integers
+      let value = rng.range(low, high);
+      match value >= 0 && rng.chance(4) {
+        true => format!("{value:#x}{suffix}"),
+        false => format!("{value}{suffix}"),
+      }
+    };
+
+    match var_type {
+      VarType::I8 => integer(&mut self.rng, -100, 100),
+      VarType::I16 | VarType::I32 => integer(&mut self.rng, -1000, 1000),
+      VarType::I64 => integer(&mut self.rng, -100_000, 100_000),
+      VarType::U8 => integer(&mut self.rng, 0, 255),
+      VarType::U16 => integer(&mut self.rng, 0, 1000),
+      VarType::U32 | VarType::U64 => integer(&mut self.rng, 0, 100_000),
+      // Float literals have no sign
+      VarType::F32 | VarType::F64 => {
+        format!("{:?}{suffix}", self.rng.range(0, 400) as f64 / 4.0)
+      }
+      VarType::Bool => self.rng.pick(&["true", "false"]).to_string(),
+      VarType::Str => format!("\"{}\"", self.rng.pick(&WORDS)),
+      VarType::Void | VarType::Ptr => unreachable!("`{var_type}` has no literals"),
+    }
+  }
+
+  fn divisor(&mut self, var_type: VarType) -> String {
+    let value = self.rng.range(1, 9);
+    match var_type {
+      VarType::F32 | VarType::F64 => format!("{value}.0{}", self.suffix(var_type)),
+      _ => format!("{value}{}", self.suffix(var_type)),
+    }
+  }
+
+  // Plain integers are `i32` and plain floats `f32`, so their suffix is optional
+  fn suffix(&mut self, var_type: VarType) -> &'static str {
+    match var_type {
+      VarType::I8 => "i8",
+      VarType::I16 => "i16",
+      VarType::I32 if self.rng.chance(2) => "i32",
+      VarType::I64 => "i64",
+      VarType::U8 => "u8",
+      VarType::U16 => "u16",
+      VarType::U32 => "u32",
+      VarType::U64 => "u64",
+      VarType::F32 if self.rng.chance(2) => "f32",
+      VarType::F64 => "f64",
+      _ => "",
+    }
+  }
+}
+
+// SplitMix64, so that a seed generates the same program everywhere
+struct Rng(u64);
+
+impl Rng {
+  fn next(&mut self) -> u64 {
+    self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = self.0;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+  }
+
+  fn below(&mut self, bound: usize) -> usize {
+    (self.next() % bound as u64) as usize
+  }
+
+  /// A number from `low` to `high`, both included, `high` is raised to `low` if needed
+  fn between(&mut self, low: usize, high: usize) -> usize {
+    low + self.below(high.max(low) - low + 1)
+  }
+
+  fn range(&mut self, low: i64, high: i64) -> i64 {
+    low + (self.next() % (high - low + 1) as u64) as i64
+  }
+
+  fn chance(&mut self, one_in: usize) -> bool {
+    self.below(one_in) == 0
+  }
+
+  fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+    &items[self.below(items.len())]
+  }
+}
@@ -0,0 +1,306 @@
+// Reference interpreter that evaluates the ETAC AST directly.
+//
+// It defines what a program means for the rest of Compass: the backends are checked against it.
+// Its semantics are those of the bytecode VM: variables are globals shared by the whole program,
+// function arguments are locals of the call, and functions yield `Value::zero` of their return
+// type.
+//
+// An `Observer` sees each statement before it runs, which is how the debugger follows a program.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+  ast::{BinaryOperation, Expr, Function, FunctionCall, Location, Operand, Statement, VarType},
+  runtime::{call_builtin, Io, Memory, RuntimeError, Value, BUILTINS},
+};
+
+pub const DEFAULT_MAX_STEPS: u64 = 1_000_000;
+
+/// A call of a user function that has not returned yet
+#[derive(Clone, Debug)]
+pub struct Frame<'program> {
+  pub function: &'program Function,
+  /// Where it was called from
+  pub call: Location,
+}
+
+pub trait Observer {
+  /// Called before `statement` runs, an error stops the program.
+  fn statement(
+    &mut self,
+    interpreter: &Interpreter,
+    locals: &HashMap<String, Value>,
+    statement: &Statement,
+  ) -> Result<(), RuntimeError>;
+}
+
+// Plain runs observe nothing
+impl Observer for () {
+  fn statement(
+    &mut self,
+    _: &Interpreter,
+    _: &HashMap<String, Value>,
+    _: &Statement,
+  ) -> Result<(), RuntimeError> {
+    Ok(())
+  }
+}
+
+// Several observers see each statement, in order
+impl Observer for Vec<&mut dyn Observer> {
+  fn statement(
+    &mut self,
+    interpreter: &Interpreter,
+    locals: &HashMap<String, Value>,
+    statement: &Statement,
+  ) -> Result<(), RuntimeError> {
+    self
+      .iter_mut()
+      .try_for_each(|observer| observer.statement(interpreter, locals, statement))
+  }
+}
+
+pub struct Interpreter<'program> {
+  program: &'program [Statement],
+  functions: HashMap<&'program str, &'program Function>,
+  /// Variables in the order of their names, once they are assigned
+  pub globals: BTreeMap<String, Value>,
+  pub memory: Memory,
+  /// The calls being run, the innermost last. It is kept when the program fails.
+  pub stack: Vec<Frame<'program>>,
+  /// Statements executed so far
+  pub steps: u64,
+  /// The program fails when it executes more statements than this
+  pub max_steps: u64,
+}
+
+impl<'program> Interpreter<'program> {
+  pub fn new(program: &'program [Statement]) -> Self {
+    let mut functions = HashMap::new();
+    collect_functions(program, &mut functions);
+
+    Self {
+      program,
+      functions,
+      globals: BTreeMap::new(),
+      memory: Memory::default(),
+      stack: vec![],
+      steps: 0,
+      max_steps: DEFAULT_MAX_STEPS,
+    }
+  }
+
+  /// Runs the top level statements until the end of the program.
+  pub fn run(&mut self, io: &mut Io) -> Result<(), RuntimeError> {
+    self.run_observed(io, &mut ())
+  }
+
+  /// Runs the program like `run`, showing each statement to the observer first.
+  pub fn run_observed(
+    &mut self,
+    io: &mut Io,
+    observer: &mut dyn Observer,
+  ) -> Result<(), RuntimeError> {
+    let program = self.program;
+    self.body(program, &mut HashMap::new(), io, observer)
+  }
+
+  fn body(
+    &mut self,
+    statements: &'program [Statement],
+    locals: &mut HashMap<String, Value>,
+    io: &mut Io,
+    observer: &mut dyn Observer,
+  ) -> Result<(), RuntimeError> {
+    let labels: HashMap<&str, usize> = statements
+      .iter()
+      .enumerate()
+      .filter_map(|(index, statement)| match statement {
+        Statement::Label { name, .. } => Some((name.as_str(), index)),
+        _ => None,
+      })
+      .collect();
+    let jump = |label: &str| {
+      labels
+        .get(label)
+        .copied()
+        .ok_or_else(|| RuntimeError::UnknownLabel(label.to_string()))
+    };
+
+    let mut pc = 0;
+    while let Some(statement) = statements.get(pc) {
+      pc += 1;
+
+      self.steps += 1;
+      if self.steps > self.max_steps {
+        return Err(RuntimeError::StepLimitExceeded(self.max_steps));
+      }
+      observer.statement(self, locals, statement)?;
+
+      match statement {
+        Statement::VariableDeclaration(variable) => {
+          let value = self.expr(&variable.value, locals, io, observer)?;
+          match locals.get_mut(&variable.name) {
+            Some(local) => *local = value.cast(local.var_type()),
+            None => {
+              self.globals.insert(variable.name.clone(), value);
+            }
+          }
+        }
+        Statement::ConditionalJump {
+          condition, label, ..
+        } => {
+          if self.expr(condition, locals, io, observer)?.is_truthy() {
+            pc = jump(label)?;
+          }
+        }
+        Statement::UnconditionalJump { label, .. } => pc = jump(label)?,
+        Statement::Store { at, from, .. } => {
+          let address = match at {
+            Operand::Dereference(name) => self.variable(name, locals)?.as_address()?,
+            _ => {
+              return Err(RuntimeError::InvalidOperation(format!(
+                "Invalid operands for store operation {} and {}",
+                at, from
+              )))
+            }
+          };
+          let value = self.operand(from, locals)?;
+          self.memory.store(address, value)?;
+        }
+        Statement::Call(call) => {
+          self.call(call, locals, io, observer)?;
+        }
+        Statement::Label { .. } | Statement::FunctionDefinition(_) | Statement::NoOperation => {}
+      }
+    }
+
+    Ok(())
+  }
+
+  fn expr(
+    &mut self,
+    expr: &'program Expr,
+    locals: &HashMap<String, Value>,
+    io: &mut Io,
+    observer: &mut dyn Observer,
+  ) -> Result<Value, RuntimeError> {
+    match expr {
+      Expr::FunctionCall(call) => self.call(call, locals, io, observer),
+      expr => self.evaluate(expr, locals),
+    }
+  }
+
+  /// Evaluates an expression that does not call a function, in the scope of `locals`.
+  pub fn evaluate(
+    &self,
+    expr: &Expr,
+    locals: &HashMap<String, Value>,
+  ) -> Result<Value, RuntimeError> {
+    match expr {
+      Expr::Operand(operand) => self.operand(operand, locals),
+      Expr::FunctionCall(call) => Err(RuntimeError::InvalidOperation(format!(
+        "`{call}` can only be run by the program"
+      ))),
+      Expr::BinaryOperation(BinaryOperation::Arithmetic {
+        lhs,
+        operator,
+        rhs,
+        operation_type,
+      }) => {
+        let (lhs, rhs) = (self.operand(lhs, locals)?, self.operand(rhs, locals)?);
+        Value::arithmetic(operator, *operation_type, &lhs, &rhs)
+      }
+      Expr::BinaryOperation(BinaryOperation::Conditional {
+        lhs,
+        condition,
+        rhs,
+        ..
+      }) => {
+        let (lhs, rhs) = (self.operand(lhs, locals)?, self.operand(rhs, locals)?);
+        Value::compare(condition, &lhs, &rhs)
+      }
+    }
+  }
+
+  /// The value of a local of the current call, or else of a global.
+  pub fn variable(
+    &self,
+    name: &str,
+    locals: &HashMap<String, Value>,
+  ) -> Result<Value, RuntimeError> {
+    locals
+      .get(name)
+      .or_else(|| self.globals.get(name))
+      .cloned()
+      .ok_or_else(|| RuntimeError::UnknownVariable(name.to_string()))
+  }
+
+  fn operand(
+    &self,
+    operand: &Operand,
+    locals: &HashMap<String, Value>,
+  ) -> Result<Value, RuntimeError> {
+    match operand {
+      Operand::Identifier(name) => self.variable(name, locals),
+      Operand::Dereference(name) => {
+        let address = self.variable(name, locals)?.as_address()?;
+        self.memory.load(address)
+      }
+      literal => Ok(Value::from_literal(literal).expect("operand is a literal")),
+    }
+  }
+
+  fn call(
+    &mut self,
+    call: &'program FunctionCall,
+    locals: &HashMap<String, Value>,
+    io: &mut Io,
+    observer: &mut dyn Observer,
+  ) -> Result<Value, RuntimeError> {
+    let args = call
+      .params
+      .iter()
+      .map(|param| self.operand(param, locals))
+      .collect::<Result<Vec<_>, _>>()?;
+
+    if BUILTINS.contains(&call.name.as_str()) {
+      return call_builtin(&call.name, &args, io);
+    }
+
+    let function = *self
+      .functions
+      .get(call.name.as_str())
+      .ok_or_else(|| RuntimeError::UnknownFunction(call.name.clone()))?;
+
+    let mut locals = function
+      .args
+      .iter()
+      .zip(args)
+      .map(|(argument, value)| (argument.name.clone(), value.cast(argument.var_type)))
+      .collect();
+    self.stack.push(Frame {
+      function,
+      call: call.location.clone(),
+    });
+    self.body(&function.body, &mut locals, io, observer)?;
+    self.stack.pop();
+
+    Ok(match function.return_type {
+      VarType::Void => Value::Void,
+      return_type => Value::zero(return_type),
+    })
+  }
+}
+
+fn collect_functions<'program>(
+  statements: &'program [Statement],
+  functions: &mut HashMap<&'program str, &'program Function>,
+) {
+  for statement in statements {
+    if let Statement::FunctionDefinition(function) = statement {
+      functions.insert(&function.name, function);
+      collect_functions(&function.body, functions);
+    }
+  }
+}
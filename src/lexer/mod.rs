@@ -1,9 +1,16 @@
+use std::ops::Range;
+
 use logos::{Logos, SpannedIter};
 
-use self::tokens::Token;
+use self::{
+  tokens::{LexingError, Token},
+  types::Spanned,
+};
+use crate::diagnostics::suggestions::did_you_mean;
 
 pub mod tokens;
 pub mod traits;
+pub mod trivia;
 pub mod types;
 
 pub struct Lexer<'input> {
@@ -18,9 +25,21 @@ pub struct ErrorTip {
   pub location: std::ops::Range<usize>,
 }
 
+/// A replacement of a span of the source that fixes an error, safe to apply without asking.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FixIt {
+  pub location: std::ops::Range<usize>,
+  pub replacement: String,
+}
+
 #[derive(Debug)]
 pub enum LexicalError {
-  InvalidToken,
+  InvalidToken {
+    kind: LexingError,
+    error: Vec<ErrorTip>,
+    help: Option<String>,
+    fix: Option<FixIt>,
+  },
   WrongType {
     error: Vec<ErrorTip>,
     help: Option<String>,
@@ -28,10 +47,12 @@ pub enum LexicalError {
   UnknownVariable {
     error: Vec<ErrorTip>,
     help: Option<String>,
+    fix: Option<FixIt>,
   },
   UnknownFunction {
     error: Vec<ErrorTip>,
     help: Option<String>,
+    fix: Option<FixIt>,
   },
   WrongArgumentCount {
     error: Vec<ErrorTip>,
@@ -45,6 +66,61 @@ pub enum LexicalError {
     error: Vec<ErrorTip>,
     help: Option<String>,
   },
+  UnknownLabel {
+    error: Vec<ErrorTip>,
+    help: Option<String>,
+    fix: Option<FixIt>,
+  },
+}
+
+impl LexicalError {
+  /// The labelled spans of the error and its help, if any
+  pub fn tips(&self) -> (&[ErrorTip], Option<&String>) {
+    match self {
+      LexicalError::InvalidToken { error, help, .. }
+      | LexicalError::WrongType { error, help }
+      | LexicalError::UnknownVariable { error, help, .. }
+      | LexicalError::UnknownFunction { error, help, .. }
+      | LexicalError::WrongArgumentCount { error, help }
+      | LexicalError::FunctionIsBuiltin { error, help }
+      | LexicalError::UnusedValue { error, help }
+      | LexicalError::UnknownLabel { error, help, .. } => (error, help.as_ref()),
+    }
+  }
+
+  /// The fix of the error, when there is a single way to fix it
+  pub fn fix(&self) -> Option<&FixIt> {
+    match self {
+      LexicalError::InvalidToken { fix, .. }
+      | LexicalError::UnknownVariable { fix, .. }
+      | LexicalError::UnknownFunction { fix, .. }
+      | LexicalError::UnknownLabel { fix, .. } => fix.as_ref(),
+      _ => None,
+    }
+  }
+
+  /// What kind of error it is, the message its labels detail
+  pub fn title(&self) -> &'static str {
+    match self {
+      LexicalError::InvalidToken { kind, .. } => match kind {
+        LexingError::IntegerOverflow(_) => "Integer literal out of range",
+        LexingError::InvalidInteger(_) => "Invalid integer literal",
+        LexingError::InvalidPrefix(_) => "Invalid literal",
+        LexingError::UnterminatedString => "Unterminated string",
+        LexingError::NonAsciiCharacter(_) => "Non-ASCII character",
+        LexingError::UnknownSuffix(_) => "Unknown literal suffix",
+        LexingError::UnknownType(_) => "Unknown type",
+        LexingError::UnexpectedCharacter => "Invalid token",
+      },
+      LexicalError::WrongType { .. } => "Mismatched types",
+      LexicalError::UnknownVariable { .. } => "Unknown variable",
+      LexicalError::UnknownFunction { .. } => "Unknown function",
+      LexicalError::WrongArgumentCount { .. } => "Wrong number of arguments",
+      LexicalError::FunctionIsBuiltin { .. } => "Function is already defined",
+      LexicalError::UnusedValue { .. } => "Unused value",
+      LexicalError::UnknownLabel { .. } => "Unknown label",
+    }
+  }
 }
 
 impl<'input> Lexer<'input> {
@@ -59,48 +135,364 @@ impl<'input> Lexer<'input> {
 
     Ok(lexer)
   }
+
+  /// A lexer that does not check the source first, for tools that report invalid tokens
+  /// themselves. `parser::parse_program` reports them with its other errors, `parser::parse_expr`
+  /// stops at the first of them.
+  pub fn unchecked(source_code: &'input str, filepath: &'input str) -> Self {
+    Self {
+      token_stream: Token::lexer(source_code).spanned(),
+      filepath,
+      source_code,
+    }
+  }
 }
 
 impl<'input> Lexer<'input> {
+  // Reporting the invalid tokens is up to the caller, see `lexer::errors`
   fn validate(&self) -> Result<(), LexicalError> {
-    let token_stream = self.token_stream.clone();
-    let mut error = false;
-
-    let filename = self.filepath.split('/').last().unwrap();
-
-    for (token, span) in token_stream.spanned() {
-      if token.is_err() {
-        use ariadne::{Color, ColorGenerator, Config, Fmt, Label, Report, ReportKind, Source};
-
-        let mut colors = ColorGenerator::default();
-        let color = colors.next();
-
-        Report::build(ReportKind::Error, filename, 12)
-          .with_code(3)
-          .with_config(Config::default().with_tab_width(2))
-          .with_message("Invalid token".fg(Color::Red))
-          .with_label(
-            Label::new((filename, span))
-              .with_message("Invalid token")
-              .with_color(color),
-          )
-          .with_help("You probably added a character that is not allowed in the language")
-          .with_note(format!(
-            "If you think this is a bug, please file an issue at {}",
-            "github.com/celestial-hub/compass/issues".fg(Color::Blue)
-          ))
-          .finish()
-          .print((filename, Source::from(self.source_code)))
-          .unwrap();
-
-        error = true;
+    match errors(self.source_code).into_iter().next() {
+      Some((kind, location)) => Err(LexicalError::invalid_token(
+        kind,
+        location,
+        self.source_code,
+      )),
+      None => Ok(()),
+    }
+  }
+}
+
+const INTEGER_TYPES: [(&str, i128, i128); 8] = [
+  ("i8", i8::MIN as i128, i8::MAX as i128),
+  ("i16", i16::MIN as i128, i16::MAX as i128),
+  ("i32", i32::MIN as i128, i32::MAX as i128),
+  ("i64", i64::MIN as i128, i64::MAX as i128),
+  ("u8", 0, u8::MAX as i128),
+  ("u16", 0, u16::MAX as i128),
+  ("u32", 0, u32::MAX as i128),
+  ("u64", 0, u64::MAX as i128),
+];
+
+// Only hexadecimal literals have a prefix, the others are what the literal was likely meant to be
+const PREFIXES: [(&str, u32); 3] = [("0x", 16), ("0b", 2), ("0o", 8)];
+
+const SUFFIXES: [&str; 10] = [
+  "i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "f32", "f64",
+];
+
+const TYPES: [&str; 12] = [
+  "i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "f32", "f64", "bool", "str",
+];
+
+/// Every token of the source the lexer rejects, with what is wrong with it. A literal followed by
+/// letters is rejected too, the letters are not one of its suffixes, and so is a name where a type
+/// is expected.
+pub fn errors(source: &str) -> Vec<(LexingError, Range<usize>)> {
+  let tokens: Vec<_> = Token::lexer(source).spanned().collect();
+  let mut errors = vec![];
+
+  for (index, (token, span)) in tokens.iter().enumerate() {
+    // Part of the last error, such as the `16` of `1.5f16`
+    if errors
+      .last()
+      .is_some_and(|(_, location): &(_, Range<usize>)| span.start < location.end)
+    {
+      continue;
+    }
+
+    match token {
+      Err(error) => errors.push(classify(error.clone(), span.clone(), source)),
+      Ok(
+        Token::LiteralI8(_)
+        | Token::LiteralI16(_)
+        | Token::LiteralI32(_)
+        | Token::LiteralI64(_)
+        | Token::LiteralU8(_)
+        | Token::LiteralU16(_)
+        | Token::LiteralU32(_)
+        | Token::LiteralU64(_)
+        | Token::LiteralF32(_)
+        | Token::LiteralF64(_),
+      ) => {
+        if let Some((Ok(Token::Identifier(suffix) | Token::Type(suffix)), next)) =
+          tokens.get(index + 1)
+        {
+          if next.start == span.end {
+            let prefix = PREFIXES
+              .iter()
+              .find(|(prefix, _)| &source[span.clone()] == "0" && suffix.starts_with(&prefix[1..]));
+            let error = match prefix {
+              // Such as `0xZZ`, a `0` followed by `xZZ`
+              Some((prefix, _)) => LexingError::InvalidPrefix(prefix),
+              None => LexingError::UnknownSuffix(suffix.clone()),
+            };
+            errors.push((error, span.start..next.end));
+          }
+        }
+      }
+      // After `name:`, as a label is, a name starts a statement and is followed by its own `:`
+      Ok(Token::Identifier(name)) => {
+        let after_colon = index
+          .checked_sub(1)
+          .is_some_and(|previous| tokens[previous].0 == Ok(Token::Colon));
+        let before_colon = tokens
+          .get(index + 1)
+          .is_some_and(|(next, _)| *next == Ok(Token::Colon));
+        if after_colon && !before_colon {
+          errors.push((LexingError::UnknownType(name.clone()), span.clone()));
+        }
+      }
+      Ok(_) => {}
+    }
+  }
+
+  errors
+}
+
+/// The tokens of the source for a parser that recovers from errors. The tokens within each of the
+/// `skipped` ranges become a single `Token::Invalid`, which no rule accepts, so the parser reports
+/// and skips them at once.
+pub(crate) fn with_error_tokens<'a>(
+  source: &'a str,
+  skipped: &'a [Range<usize>],
+) -> impl Iterator<Item = Spanned<Token, usize, LexicalError>> + 'a {
+  let mut tokens = Token::lexer(source).spanned().peekable();
+
+  std::iter::from_fn(move || {
+    let (token, span) = tokens.next()?;
+    let range = skipped
+      .iter()
+      .find(|range| range.start <= span.start && span.start < range.end);
+
+    Some(match (range, token) {
+      (Some(range), _) => {
+        let mut end = range.end.max(span.end);
+        while let Some((_, next)) = tokens.next_if(|(_, next)| next.start < end) {
+          end = end.max(next.end);
+        }
+        Ok((range.start, Token::Invalid, end))
+      }
+      (None, Ok(token)) => Ok((span.start, token, span.end)),
+      (None, Err(kind)) => {
+        let (kind, location) = classify(kind, span, source);
+        Err(LexicalError::invalid_token(kind, location, source))
+      }
+    })
+  })
+}
+
+/// The error of a rejected token, telling a character that is not ASCII from other ones.
+pub fn classify(
+  error: LexingError,
+  span: Range<usize>,
+  source: &str,
+) -> (LexingError, Range<usize>) {
+  let rest = &source[span.start..];
+  let character = rest.chars().next().unwrap_or_default();
+
+  match error {
+    LexingError::UnexpectedCharacter if !character.is_ascii() => (
+      LexingError::NonAsciiCharacter(character),
+      span.start..span.start + character.len_utf8(),
+    ),
+    // A number the lexer stopped in, such as `1.5f16` at its `f`
+    LexingError::UnexpectedCharacter if character.is_ascii_digit() || character == '.' => {
+      let word = rest
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '_')
+        .map_or(rest, |end| &rest[..end]);
+      let number = match word.strip_prefix("0x") {
+        Some(digits) => {
+          2 + digits.len()
+            - digits
+              .trim_start_matches(|c: char| c.is_ascii_hexdigit())
+              .len()
+        }
+        None => {
+          word.len()
+            - word
+              .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.')
+              .len()
+        }
+      };
+
+      match &word[number..] {
+        "" => (error, span),
+        suffix => (
+          LexingError::UnknownSuffix(suffix.to_string()),
+          span.start..span.start + word.len(),
+        ),
       }
     }
+    error => (error, span),
+  }
+}
+
+impl LexicalError {
+  /// The error of a token the lexer rejects, with a help and a fix for it when there is one.
+  pub fn invalid_token(kind: LexingError, location: Range<usize>, source: &str) -> Self {
+    let slice = &source[location.clone()];
+    let tip = |message: String, location: Range<usize>| vec![ErrorTip { message, location }];
+
+    let (error, help, fix) = match &kind {
+      LexingError::IntegerOverflow(var_type) => {
+        let digits = slice.strip_suffix(var_type).unwrap_or(slice);
+        let (_, min, max) = INTEGER_TYPES
+          .iter()
+          .find(|(name, ..)| name == var_type)
+          .copied()
+          .unwrap_or(("i32", i32::MIN as i128, i32::MAX as i128));
+        let range = format!("`{var_type}` holds values from {min} to {max}");
+
+        let (help, fix) = match wider(digits, var_type) {
+          Some(wider) => (
+            format!("{range}, write `{digits}{wider}` to use `{wider}` instead"),
+            Some(FixIt {
+              location: location.clone(),
+              replacement: format!("{digits}{wider}"),
+            }),
+          ),
+          None => (range, None),
+        };
+        (
+          tip(format!("`{slice}` does not fit in `{var_type}`"), location),
+          Some(help),
+          fix,
+        )
+      }
+      LexingError::InvalidInteger(var_type) => (
+        tip(format!("`{slice}` is not a `{var_type}`"), location),
+        Some("Write it with decimal digits, or with hexadecimal ones after `0x`".to_string()),
+        None,
+      ),
+      LexingError::InvalidPrefix(prefix) => {
+        let digits = &slice[prefix.len()..];
+        let radix = PREFIXES
+          .iter()
+          .find(|(known, _)| known == prefix)
+          .map_or(16, |(_, radix)| *radix);
+        let (help, fix) = match (radix, i32::from_str_radix(digits, radix)) {
+          (16, _) => (
+            "Write hexadecimal digits after `0x`, such as `0xff`".to_string(),
+            None,
+          ),
+          (_, Ok(value)) => (
+            format!("Only hexadecimal literals have a prefix, write `{value}` instead"),
+            Some(FixIt {
+              location: location.clone(),
+              replacement: value.to_string(),
+            }),
+          ),
+          (_, Err(_)) => (
+            "Only hexadecimal literals have a prefix, write it in decimal or after `0x`"
+              .to_string(),
+            None,
+          ),
+        };
+        (
+          tip(format!("`{slice}` is not a valid literal"), location),
+          Some(help),
+          fix,
+        )
+      }
+      LexingError::UnterminatedString => (
+        tip(
+          "this string is never closed".to_string(),
+          location.start..location.start + 1,
+        ),
+        Some("Close it with a `\"`, strings cannot contain one".to_string()),
+        None,
+      ),
+      LexingError::NonAsciiCharacter(character) => {
+        let lookalike = match character {
+          '“' | '”' | '„' => Some("\""),
+          '‘' | '’' => Some("'"),
+          '−' | '–' | '—' => Some("-"),
+          '×' => Some("*"),
+          '÷' => Some("/"),
+          '≤' => Some("<="),
+          '≥' => Some(">="),
+          '≠' => Some("!="),
+          '\u{a0}' => Some(" "),
+          _ => None,
+        };
+        let (help, fix) = match lookalike {
+          Some(replacement) => (
+            format!("Did you mean `{replacement}`?"),
+            Some(FixIt {
+              location: location.clone(),
+              replacement: replacement.to_string(),
+            }),
+          ),
+          None => (
+            "Only strings and comments can have characters that are not ASCII".to_string(),
+            None,
+          ),
+        };
+        (
+          tip(format!("`{character}` is not ASCII"), location),
+          Some(help),
+          fix,
+        )
+      }
+      LexingError::UnknownSuffix(suffix) => {
+        let literal = &slice[..slice.len() - suffix.len()];
+        let suffix_location = location.end - suffix.len()..location.end;
+        let candidates = match literal.contains('.') {
+          true => &SUFFIXES[8..],
+          false => &SUFFIXES[..],
+        };
 
-    if error {
-      return Err(LexicalError::InvalidToken);
+        let (help, fix) = did_you_mean(suffix, candidates.iter().copied(), suffix_location.clone());
+        let help = help.unwrap_or_else(|| {
+          format!(
+            "Literals can end with one of {}, or be apart from the next word",
+            candidates.join(", ")
+          )
+        });
+        (
+          tip(format!("`{suffix}` is not a type"), suffix_location),
+          Some(help),
+          fix,
+        )
+      }
+      LexingError::UnknownType(name) => {
+        let (help, fix) = did_you_mean(name, TYPES, location.clone());
+        let help = help.unwrap_or_else(|| format!("The types are {}", TYPES.join(", ")));
+        (
+          tip(format!("`{name}` is not a type"), location),
+          Some(help),
+          fix,
+        )
+      }
+      LexingError::UnexpectedCharacter => (
+        tip("Invalid token".to_string(), location),
+        Some("You probably added a character that is not allowed in the language".to_string()),
+        None,
+      ),
+    };
+
+    LexicalError::InvalidToken {
+      kind,
+      error,
+      help,
+      fix,
     }
+  }
+}
 
-    Ok(())
+// The narrowest type wider than `var_type`, of the same signedness, that holds the literal
+fn wider(digits: &str, var_type: &str) -> Option<&'static str> {
+  let value = match digits.strip_prefix("0x") {
+    Some(digits) => i128::from_str_radix(digits, 16),
+    None => digits.parse(),
   }
+  .ok()?;
+
+  INTEGER_TYPES
+    .iter()
+    .filter(|(name, ..)| name[..1] == var_type[..1])
+    .skip_while(|(name, ..)| *name != var_type)
+    .skip(1)
+    .find(|(_, min, max)| (*min..=*max).contains(&value))
+    .map(|(name, ..)| *name)
 }
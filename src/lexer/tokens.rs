@@ -1,23 +1,42 @@
-use std::num::ParseIntError;
+use std::num::{IntErrorKind, ParseIntError};
 
 use logos::Logos;
 
 #[derive(Default, Debug, Clone, PartialEq)]
 pub enum LexingError {
-  InvalidInteger(String),
+  /// An integer literal out of the range of its type
+  IntegerOverflow(&'static str),
+  /// An integer literal that is not a number of its type
+  InvalidInteger(&'static str),
+  /// A `0x`, `0b` or `0o` prefix without hexadecimal digits after it, such as `0xZZ`
+  InvalidPrefix(&'static str),
+  /// A `"` that no other one closes
+  UnterminatedString,
+  NonAsciiCharacter(char),
+  /// Letters after a literal that are not a type, such as the `u7` of `300u7`
+  UnknownSuffix(String),
+  /// A name where a type is expected, such as the `i33` of `a: i33 = 1`
+  UnknownType(String),
   #[default]
-  NonAsciiCharacter,
+  UnexpectedCharacter,
 }
 
-/// Error type returned by calling `lex.slice().parse()` to u8.
-impl From<ParseIntError> for LexingError {
-  fn from(err: ParseIntError) -> Self {
-    use std::num::IntErrorKind::*;
-    match err.kind() {
-      PosOverflow | NegOverflow => LexingError::InvalidInteger("overflow error".to_owned()),
-      _ => LexingError::InvalidInteger("other error".to_owned()),
-    }
-  }
+/// The value of an integer literal of a type, decimal or hexadecimal, with or without its suffix.
+fn integer<T>(
+  slice: &str,
+  var_type: &'static str,
+  parse: fn(&str, u32) -> Result<T, ParseIntError>,
+) -> Result<T, LexingError> {
+  let digits = slice.strip_suffix(var_type).unwrap_or(slice);
+  let result = match digits.strip_prefix("0x") {
+    Some(digits) => parse(digits, 16),
+    None => parse(digits, 10),
+  };
+
+  result.map_err(|error| match error.kind() {
+    IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => LexingError::IntegerOverflow(var_type),
+    _ => LexingError::InvalidInteger(var_type),
+  })
 }
 
 #[derive(Logos, Debug, PartialEq, Clone)]
@@ -91,38 +110,38 @@ pub enum Token {
   Dereference(String),
 
   // Integer Literals
-  #[regex("[+-]?[0-9]+", |lex| lex.slice().parse().ok())]
-  #[regex("[+-]?[0-9]+i32", |lex| lex.slice().strip_suffix("i32").unwrap().parse().ok())]
-  #[regex("0x[0-9a-fA-F]+", |lex| i32::from_str_radix(&lex.slice()[2..], 16).ok())]
-  #[regex("0x[0-9a-fA-F]+i32", |lex| i32::from_str_radix(lex.slice()[2..].strip_suffix("i32").unwrap(), 16).ok())]
+  #[regex("[+-]?[0-9]+", |lex| integer(lex.slice(), "i32", i32::from_str_radix))]
+  #[regex("[+-]?[0-9]+i32", |lex| integer(lex.slice(), "i32", i32::from_str_radix))]
+  #[regex("0x[0-9a-fA-F]+", |lex| integer(lex.slice(), "i32", i32::from_str_radix))]
+  #[regex("0x[0-9a-fA-F]+i32", |lex| integer(lex.slice(), "i32", i32::from_str_radix))]
   LiteralI32(i32),
 
-  #[regex("[+-]?[0-9]+i8", |lex| lex.slice().strip_suffix("i8").unwrap().parse().ok())]
-  #[regex("0x[0-9a-fA-F]+i8", |lex| i8::from_str_radix(lex.slice()[2..].strip_suffix("i8").unwrap(), 16).ok())]
+  #[regex("[+-]?[0-9]+i8", |lex| integer(lex.slice(), "i8", i8::from_str_radix))]
+  #[regex("0x[0-9a-fA-F]+i8", |lex| integer(lex.slice(), "i8", i8::from_str_radix))]
   LiteralI8(i8),
 
-  #[regex("[+-]?[0-9]+i16", |lex| lex.slice().strip_suffix("i16").unwrap().parse().ok())]
-  #[regex("0x[0-9a-fA-F]+i16", |lex| i16::from_str_radix(lex.slice()[2..].strip_suffix("i16").unwrap(), 16).ok())]
+  #[regex("[+-]?[0-9]+i16", |lex| integer(lex.slice(), "i16", i16::from_str_radix))]
+  #[regex("0x[0-9a-fA-F]+i16", |lex| integer(lex.slice(), "i16", i16::from_str_radix))]
   LiteralI16(i16),
 
-  #[regex("[+-]?[0-9]+i64", |lex| lex.slice().strip_suffix("i64").unwrap().parse().ok())]
-  #[regex("0x[0-9a-fA-F]+i64", |lex| i64::from_str_radix(lex.slice()[2..].strip_suffix("i64").unwrap(), 16).ok())]
+  #[regex("[+-]?[0-9]+i64", |lex| integer(lex.slice(), "i64", i64::from_str_radix))]
+  #[regex("0x[0-9a-fA-F]+i64", |lex| integer(lex.slice(), "i64", i64::from_str_radix))]
   LiteralI64(i64),
 
-  #[regex("[0-9]+u8", |lex| lex.slice().strip_suffix("u8").unwrap().parse().ok())]
-  #[regex("0x[0-9a-fA-F]+u8", |lex| u8::from_str_radix(lex.slice()[2..].strip_suffix("u8").unwrap(), 16).ok())]
+  #[regex("[0-9]+u8", |lex| integer(lex.slice(), "u8", u8::from_str_radix))]
+  #[regex("0x[0-9a-fA-F]+u8", |lex| integer(lex.slice(), "u8", u8::from_str_radix))]
   LiteralU8(u8),
 
-  #[regex("[0-9]+u16", |lex| lex.slice().strip_suffix("u16").unwrap().parse().ok())]
-  #[regex("0x[0-9a-fA-F]+u16", |lex| u16::from_str_radix(lex.slice()[2..].strip_suffix("u16").unwrap(), 16).ok())]
+  #[regex("[0-9]+u16", |lex| integer(lex.slice(), "u16", u16::from_str_radix))]
+  #[regex("0x[0-9a-fA-F]+u16", |lex| integer(lex.slice(), "u16", u16::from_str_radix))]
   LiteralU16(u16),
 
-  #[regex("[0-9]+u32", |lex| lex.slice().strip_suffix("u32").unwrap().parse().ok())]
-  #[regex("0x[0-9a-fA-F]+u32", |lex| u32::from_str_radix(lex.slice()[2..].strip_suffix("u32").unwrap(), 16).ok())]
+  #[regex("[0-9]+u32", |lex| integer(lex.slice(), "u32", u32::from_str_radix))]
+  #[regex("0x[0-9a-fA-F]+u32", |lex| integer(lex.slice(), "u32", u32::from_str_radix))]
   LiteralU32(u32),
 
-  #[regex("[0-9]+u64", |lex| lex.slice().strip_suffix("u64").unwrap().parse().ok())]
-  #[regex("0x[0-9a-fA-F]+u64", |lex| u64::from_str_radix(lex.slice()[2..].strip_suffix("u64").unwrap(), 16).ok())]
+  #[regex("[0-9]+u64", |lex| integer(lex.slice(), "u64", u64::from_str_radix))]
+  #[regex("0x[0-9a-fA-F]+u64", |lex| integer(lex.slice(), "u64", u64::from_str_radix))]
   LiteralU64(u64),
 
   // Float 32 Literals
@@ -168,6 +187,7 @@ pub enum Token {
 
   // String literal
   #[regex("\"[^\"]*\"", |lex| lex.slice().parse().ok())]
+  #[regex("\"[^\"]*", |_| Err(LexingError::UnterminatedString))]
   LiteralString(String),
 
   // Boolean literals
@@ -188,4 +208,7 @@ pub enum Token {
   CloseParen,
   #[token(":")]
   Colon,
+
+  /// Tokens the parser skips over as a whole, see `lexer::with_error_tokens`
+  Invalid,
 }
@@ -3,8 +3,7 @@ use crate::lexer::{self, tokens::Token, Lexer, LexicalError};
 impl std::fmt::Display for LexicalError {
   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
     match self {
-      LexicalError::InvalidToken => write!(f, "Invalid token"),
-      LexicalError::WrongType { error, help } => {
+      LexicalError::InvalidToken { error, help, .. } | LexicalError::WrongType { error, help } => {
         for lexer::ErrorTip { message, location } in error {
           writeln!(f, "error: {message:?} at {location:?}")?;
         }
@@ -13,7 +12,7 @@ impl std::fmt::Display for LexicalError {
           None => Ok(()),
         }
       }
-      LexicalError::UnknownVariable { error, help } => {
+      LexicalError::UnknownVariable { error, help, .. } => {
         for lexer::ErrorTip { message, location } in error {
           writeln!(f, "error: {message:?} at {location:?}")?;
         }
@@ -22,7 +21,8 @@ impl std::fmt::Display for LexicalError {
           None => Ok(()),
         }
       }
-      LexicalError::UnknownFunction { error, help } => {
+      LexicalError::UnknownFunction { error, help, .. }
+      | LexicalError::UnknownLabel { error, help, .. } => {
         for lexer::ErrorTip { message, location } in error {
           writeln!(f, "error: {message:?} at {location:?}")?;
         }
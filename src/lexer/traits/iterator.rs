@@ -1,12 +1,21 @@
-use crate::lexer::{tokens::Token, types::Spanned, Lexer, LexicalError};
+use crate::lexer::{self, tokens::Token, types::Spanned, Lexer, LexicalError};
 
 impl<'input> Iterator for Lexer<'input> {
   type Item = Spanned<Token, usize, LexicalError>;
 
   fn next(&mut self) -> Option<Self::Item> {
-    self
-      .token_stream
-      .next()
-      .map(|(tok, span)| Ok((span.start, tok.expect("Invalid token"), span.end)))
+    let (token, span) = self.token_stream.next()?;
+
+    Some(match token {
+      Ok(token) => Ok((span.start, token, span.end)),
+      Err(kind) => {
+        let (kind, location) = lexer::classify(kind, span, self.source_code);
+        Err(LexicalError::invalid_token(
+          kind,
+          location,
+          self.source_code,
+        ))
+      }
+    })
   }
 }
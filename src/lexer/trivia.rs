@@ -0,0 +1,70 @@
+// Token stream that keeps what the parser skips: comments and line breaks.
+//
+// Tools that rewrite the source, such as the formatter, need it to give back every comment. The
+// tokens come from the same `logos` lexer as the parser's, the trivia from the gaps between them.
+
+use logos::Logos;
+
+use super::tokens::Token;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Piece<'input> {
+  /// A token, with the text it was written with
+  Token(Token, &'input str),
+  /// A `#` comment, up to the end of its line
+  Comment(&'input str),
+  Newline,
+}
+
+pub type Spanned<'input> = (Piece<'input>, std::ops::Range<usize>);
+
+/// Lexes the whole source, other whitespace than line breaks is dropped.
+pub fn lex(source: &str) -> Result<Vec<Spanned<'_>>, String> {
+  let mut pieces = vec![];
+  let mut end = 0;
+
+  for (token, span) in Token::lexer(source).spanned() {
+    let token = token.map_err(|_| {
+      format!(
+        "Invalid token `{}` at {}..{}",
+        &source[span.clone()],
+        span.start,
+        span.end
+      )
+    })?;
+
+    gap(source, end..span.start, &mut pieces);
+    pieces.push((Piece::Token(token, &source[span.clone()]), span.clone()));
+    end = span.end;
+  }
+  gap(source, end..source.len(), &mut pieces);
+
+  Ok(pieces)
+}
+
+// Only whitespace and comments are skipped by the lexer, so that is all a gap holds
+fn gap<'input>(
+  source: &'input str,
+  span: std::ops::Range<usize>,
+  pieces: &mut Vec<Spanned<'input>>,
+) {
+  let mut offset = span.start;
+
+  while offset < span.end {
+    let rest = &source[offset..span.end];
+    match rest.chars().next() {
+      Some('\n') => {
+        pieces.push((Piece::Newline, offset..offset + 1));
+        offset += 1;
+      }
+      Some('#') => {
+        let length = rest.find('\n').unwrap_or(rest.len());
+        let comment = rest[..length].trim_end();
+        pieces.push((Piece::Comment(comment), offset..offset + comment.len()));
+        offset += length;
+      }
+      Some(c) => offset += c.len_utf8(),
+      None => break,
+    }
+  }
+}
@@ -1,7 +1,23 @@
 pub mod ast;
+pub mod bytecode;
 pub mod codegen;
+pub mod compiler;
+pub mod coverage;
+pub mod debugger;
+pub mod diagnostics;
+pub mod differential;
+pub mod formatter;
+pub mod generator;
+pub mod interpreter;
 pub mod lexer;
+pub mod lints;
+pub mod lsp;
 pub mod parser;
+pub mod profiler;
+pub mod repl;
+pub mod runtime;
+pub mod testing;
+pub mod tracer;
 
 // TODO: later add this through features
 pub mod cli;
@@ -0,0 +1,559 @@
+// Warnings and notes about programs that compile but are probably not what was meant.
+//
+// Each lint has a level, its default unless it is changed with `-A`, `-W` and `-D` or with a
+// `# compass: allow(<lint>)` pragma. A pragma alone on its line applies to the whole file, one
+// after a statement only to its line, and pragmas win over the command line. The `warnings` group
+// changes every lint that is not allowed. Only denied lints are errors.
+
+use std::collections::HashMap;
+
+use logos::Logos;
+
+use crate::{
+  ast::{BinaryOperation, Condition, Expr, Location, Operand, Statement},
+  diagnostics::{suggestions::did_you_mean, Diagnostic, Severity, Span},
+  lexer::{
+    tokens::Token,
+    trivia::{self, Piece},
+  },
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Level {
+  Allow,
+  Note,
+  Warn,
+  Deny,
+}
+
+impl Level {
+  /// The severity of the diagnostics of a lint at this level, none when it is allowed
+  pub fn severity(&self) -> Option<Severity> {
+    match self {
+      Level::Allow => None,
+      Level::Note => Some(Severity::Note),
+      Level::Warn => Some(Severity::Warning),
+      Level::Deny => Some(Severity::Error),
+    }
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Lint {
+  /// The name that selects it, which is also the code of its diagnostics
+  pub name: &'static str,
+  pub description: &'static str,
+  pub default: Level,
+}
+
+pub const UNUSED_VARIABLE: Lint = Lint {
+  name: "unused-variable",
+  description: "A variable that is never read",
+  default: Level::Warn,
+};
+pub const UNUSED_LABEL: Lint = Lint {
+  name: "unused-label",
+  description: "A label that no jump goes to",
+  default: Level::Warn,
+};
+pub const SHADOWED_VARIABLE: Lint = Lint {
+  name: "shadowed-variable",
+  description: "A variable of a function with the name of a global one",
+  default: Level::Note,
+};
+pub const CONSTANT_CONDITION: Lint = Lint {
+  name: "constant-condition",
+  description: "A conditional jump that is always or never taken",
+  default: Level::Warn,
+};
+pub const UNREACHABLE_STATEMENT: Lint = Lint {
+  name: "unreachable-statement",
+  description: "A statement after a `goto` that no label leads to",
+  default: Level::Warn,
+};
+
+pub const LINTS: &[&Lint] = &[
+  &UNUSED_VARIABLE,
+  &UNUSED_LABEL,
+  &SHADOWED_VARIABLE,
+  &CONSTANT_CONDITION,
+  &UNREACHABLE_STATEMENT,
+];
+
+/// The group of every lint.
+pub const WARNINGS: &str = "warnings";
+
+pub fn find(name: &str) -> Option<&'static Lint> {
+  LINTS.iter().find(|lint| lint.name == name).copied()
+}
+
+/// The levels set for lints or for the `warnings` group, the last one set wins.
+#[derive(Clone, Debug, Default)]
+pub struct Levels(Vec<(String, Level)>);
+
+impl Levels {
+  pub fn set(&mut self, name: &str, level: Level) -> Result<(), String> {
+    if name != WARNINGS && find(name).is_none() {
+      return Err(format!("Unknown lint `{name}`"));
+    }
+
+    self.0.push((name.to_string(), level));
+    Ok(())
+  }
+
+  pub fn level(&self, lint: &Lint) -> Level {
+    self
+      .0
+      .iter()
+      .fold(lint.default, |current, (name, level)| match name.as_str() {
+        WARNINGS if current != Level::Allow => *level,
+        name if name == lint.name => *level,
+        _ => current,
+      })
+  }
+}
+
+// The levels of the pragmas of a source, for the whole file and for single lines
+struct Pragmas {
+  file: Levels,
+  lines: HashMap<usize, Levels>,
+  diagnostics: Vec<Diagnostic>,
+}
+
+impl Pragmas {
+  fn new(source: &str) -> Self {
+    let mut pragmas = Pragmas {
+      file: Levels::default(),
+      lines: HashMap::new(),
+      diagnostics: vec![],
+    };
+    // An invalid token is reported by the parser, lints only run on programs that parse
+    let Ok(pieces) = trivia::lex(source) else {
+      return pragmas;
+    };
+
+    let mut after_token = false;
+    for (piece, location) in pieces {
+      match piece {
+        Piece::Token(..) => after_token = true,
+        Piece::Newline => after_token = false,
+        Piece::Comment(comment) => {
+          let line = line(source, location.start);
+          let levels = match after_token {
+            true => pragmas.lines.entry(line).or_default(),
+            false => &mut pragmas.file,
+          };
+          if let Some(diagnostic) = pragma(comment, location.start, levels) {
+            pragmas.diagnostics.push(diagnostic);
+          }
+        }
+      }
+    }
+
+    pragmas
+  }
+
+  fn level(&self, lint: &Lint, levels: &Levels, line: usize) -> Level {
+    let mut all = levels.clone();
+    all.0.extend(self.file.0.iter().cloned());
+    if let Some(levels) = self.lines.get(&line) {
+      all.0.extend(levels.0.iter().cloned());
+    }
+    all.level(lint)
+  }
+}
+
+// Adds the levels of a `# compass: allow(a, b)` comment, and reports a lint it does not know
+fn pragma(comment: &str, offset: usize, levels: &mut Levels) -> Option<Diagnostic> {
+  let text = comment.trim_start_matches('#').trim_start();
+  let body = text.strip_prefix("compass:")?.trim();
+  let (level, names) = body.split_once('(')?;
+  let level = match level.trim() {
+    "allow" => Level::Allow,
+    "warn" => Level::Warn,
+    "deny" => Level::Deny,
+    _ => return None,
+  };
+  let names = names.split(')').next()?;
+  let names_offset = offset + comment.find(names)?;
+
+  let mut unknown = None;
+  let mut start = 0;
+  for name in names.split(',') {
+    let location = names_offset + start + (name.len() - name.trim_start().len());
+    start += name.len() + 1;
+    let name = name.trim();
+    if levels.set(name, level).is_ok() || unknown.is_some() {
+      continue;
+    }
+
+    let location = location..location + name.len();
+    let candidates = LINTS.iter().map(|lint| lint.name).chain([WARNINGS]);
+    let (help, fix) = did_you_mean(name, candidates, location.clone());
+    unknown = Some(Diagnostic {
+      severity: Severity::Warning,
+      help,
+      fix,
+      spans: vec![Span {
+        location,
+        label: "not a lint of compass".to_string(),
+      }],
+      ..Diagnostic::error(format!("Unknown lint `{name}`"))
+    });
+  }
+
+  unknown
+}
+
+// The line of a byte offset, counted from 0
+fn line(source: &str, offset: usize) -> usize {
+  source[..offset.min(source.len())].matches('\n').count()
+}
+
+/// The warnings and notes of a program, as errors for the denied lints.
+pub fn check(ast: &[Statement], source: &str, levels: &Levels) -> Vec<Diagnostic> {
+  let pragmas = Pragmas::new(source);
+
+  let mut found = vec![];
+  unused_variables(ast, &mut found);
+  unused_labels(ast, &mut found);
+  shadowed_variables(ast, &mut found);
+  constant_conditions(ast, source, &mut found);
+  unreachable_statements(ast, &mut found);
+
+  let mut diagnostics = pragmas.diagnostics.clone();
+  for (lint, diagnostic) in found {
+    let offset = diagnostic
+      .spans
+      .first()
+      .map_or(0, |span| span.location.start);
+    let Some(severity) = pragmas.level(lint, levels, line(source, offset)).severity() else {
+      continue;
+    };
+
+    diagnostics.push(Diagnostic {
+      code: Some(lint.name.to_string()),
+      severity,
+      ..diagnostic
+    });
+  }
+
+  diagnostics.sort_by_key(|diagnostic| {
+    diagnostic
+      .spans
+      .first()
+      .map(|span| (span.location.start, span.location.end))
+  });
+  diagnostics
+}
+
+fn diagnostic(message: &str, location: Location, label: impl Into<String>) -> Diagnostic {
+  Diagnostic {
+    spans: vec![Span {
+      location,
+      label: label.into(),
+    }],
+    ..Diagnostic::error(message)
+  }
+}
+
+// The statements of the program, then of each function, one body after the other
+fn bodies(ast: &[Statement]) -> Vec<&[Statement]> {
+  let mut bodies = vec![ast];
+  for statement in ast {
+    if let Statement::FunctionDefinition(function) = statement {
+      bodies.push(&function.body);
+    }
+  }
+  bodies
+}
+
+fn operand_reads<'a>(operand: &'a Operand, reads: &mut Vec<&'a str>) {
+  if let Operand::Identifier(name) | Operand::Dereference(name) = operand {
+    reads.push(name);
+  }
+}
+
+fn expr_reads<'a>(expr: &'a Expr, reads: &mut Vec<&'a str>) {
+  match expr {
+    Expr::Operand(operand) => operand_reads(operand, reads),
+    Expr::FunctionCall(call) => call
+      .params
+      .iter()
+      .for_each(|param| operand_reads(param, reads)),
+    Expr::BinaryOperation(
+      BinaryOperation::Arithmetic { lhs, rhs, .. } | BinaryOperation::Conditional { lhs, rhs, .. },
+    ) => {
+      operand_reads(lhs, reads);
+      operand_reads(rhs, reads);
+    }
+  }
+}
+
+// The names each statement of a body reads, not those of the functions it defines
+fn reads(body: &[Statement]) -> Vec<&str> {
+  let mut reads = vec![];
+  for statement in body {
+    match statement {
+      Statement::VariableDeclaration(variable) => expr_reads(&variable.value, &mut reads),
+      Statement::ConditionalJump { condition, .. } => expr_reads(condition, &mut reads),
+      Statement::Store { at, from, .. } => {
+        operand_reads(at, &mut reads);
+        operand_reads(from, &mut reads);
+      }
+      Statement::Call(call) => call
+        .params
+        .iter()
+        .for_each(|param| operand_reads(param, &mut reads)),
+      _ => {}
+    }
+  }
+  reads
+}
+
+// A name starting with `_` is meant to be unused
+fn unused_variables(ast: &[Statement], found: &mut Vec<(&'static Lint, Diagnostic)>) {
+  // Globals can be read by every function
+  let all_reads: Vec<&str> = bodies(ast).into_iter().flat_map(reads).collect();
+
+  for (index, body) in bodies(ast).into_iter().enumerate() {
+    let reads = match index {
+      0 => all_reads.clone(),
+      _ => reads(body),
+    };
+
+    let mut declared: Vec<&str> = vec![];
+    for statement in body {
+      let Statement::VariableDeclaration(variable) = statement else {
+        continue;
+      };
+      let name = variable.name.as_str();
+      if name.starts_with('_') || declared.contains(&name) || reads.contains(&name) {
+        continue;
+      }
+      declared.push(name);
+
+      let diagnostic = diagnostic(
+        "Unused variable",
+        variable.location.clone(),
+        format!("`{name}` is never read"),
+      );
+      found.push((
+        &UNUSED_VARIABLE,
+        Diagnostic {
+          help: Some(format!(
+            "Remove it, or name it `_{name}` if it is meant to be unused"
+          )),
+          ..diagnostic
+        },
+      ));
+    }
+  }
+}
+
+fn unused_labels(ast: &[Statement], found: &mut Vec<(&'static Lint, Diagnostic)>) {
+  let mut targets = vec![];
+  for body in bodies(ast) {
+    for statement in body {
+      if let Statement::ConditionalJump { label, .. } | Statement::UnconditionalJump { label, .. } =
+        statement
+      {
+        targets.push(label.as_str());
+      }
+    }
+  }
+
+  for body in bodies(ast) {
+    for statement in body {
+      let Statement::Label { name, location } = statement else {
+        continue;
+      };
+      if name.starts_with('_') || targets.contains(&name.as_str()) {
+        continue;
+      }
+
+      found.push((
+        &UNUSED_LABEL,
+        Diagnostic {
+          help: Some("Remove it, or jump to it with `goto`".to_string()),
+          ..diagnostic(
+            "Unused label",
+            location.clone(),
+            format!("no jump goes to `{name}`"),
+          )
+        },
+      ));
+    }
+  }
+}
+
+fn shadowed_variables(ast: &[Statement], found: &mut Vec<(&'static Lint, Diagnostic)>) {
+  let mut globals: Vec<(&str, &Location)> = vec![];
+
+  for statement in ast {
+    match statement {
+      Statement::VariableDeclaration(variable)
+        if globals.iter().all(|(name, _)| *name != variable.name) =>
+      {
+        globals.push((&variable.name, &variable.location));
+      }
+      Statement::FunctionDefinition(function) => {
+        let mut shadowed = vec![];
+        for statement in &function.body {
+          let Statement::VariableDeclaration(variable) = statement else {
+            continue;
+          };
+          let Some((_, global)) = globals.iter().find(|(name, _)| *name == variable.name) else {
+            continue;
+          };
+          if shadowed.contains(&variable.name) {
+            continue;
+          }
+          shadowed.push(variable.name.clone());
+
+          let diagnostic = diagnostic(
+            "Shadowed variable",
+            variable.location.clone(),
+            format!("`{}` hides the global variable", variable.name),
+          );
+          found.push((
+            &SHADOWED_VARIABLE,
+            Diagnostic {
+              help: Some("Rename one of them if they are meant to be different".to_string()),
+              ..diagnostic.with_span((*global).clone(), "declared here")
+            },
+          ));
+        }
+      }
+      _ => {}
+    }
+  }
+}
+
+// The value of a condition that does not depend on any variable, if it is known
+fn constant(condition: &Expr) -> Option<Option<bool>> {
+  let literal =
+    |operand: &Operand| !matches!(operand, Operand::Identifier(_) | Operand::Dereference(_));
+
+  let Expr::BinaryOperation(BinaryOperation::Conditional {
+    lhs,
+    condition,
+    rhs,
+    ..
+  }) = condition
+  else {
+    return None;
+  };
+  match (condition, lhs, rhs) {
+    (Condition::Or, Operand::LiteralBool(true), _)
+    | (Condition::Or, _, Operand::LiteralBool(true)) => Some(Some(true)),
+    (Condition::And, Operand::LiteralBool(false), _)
+    | (Condition::And, _, Operand::LiteralBool(false)) => Some(Some(false)),
+    _ if literal(lhs) && literal(rhs) => Some(None),
+    _ => None,
+  }
+}
+
+fn constant_condition(value: Option<bool>, label: &str, location: Location) -> Diagnostic {
+  let (message, help) = match value {
+    Some(true) => (
+      "this condition is always `true`",
+      format!("Use `goto {label}` to always jump"),
+    ),
+    Some(false) => (
+      "this condition is always `false`",
+      "Remove the jump, it is never taken".to_string(),
+    ),
+    None => (
+      "this condition only compares literals",
+      "Compare a variable, or replace the condition with its result".to_string(),
+    ),
+  };
+
+  Diagnostic {
+    help: Some(help),
+    ..diagnostic("Constant condition", location, message)
+  }
+}
+
+// The parser turns `if true goto` into a `goto` and drops `if false goto`, so these are found in
+// the tokens
+fn constant_conditions(
+  ast: &[Statement],
+  source: &str,
+  found: &mut Vec<(&'static Lint, Diagnostic)>,
+) {
+  let tokens: Vec<(Token, Location)> = Token::lexer(source)
+    .spanned()
+    .filter_map(|(token, span)| Some((token.ok()?, span)))
+    .collect();
+  for window in tokens.windows(4) {
+    let [(Token::If, _), (literal, start), (Token::Goto, _), (Token::Identifier(label), end)] =
+      window
+    else {
+      continue;
+    };
+    let value = match literal {
+      Token::LiteralTrue => true,
+      Token::LiteralFalse => false,
+      _ => continue,
+    };
+    let diagnostic = constant_condition(Some(value), label, start.start..end.end);
+    found.push((&CONSTANT_CONDITION, diagnostic));
+  }
+
+  for body in bodies(ast) {
+    for statement in body {
+      let Statement::ConditionalJump {
+        condition,
+        label,
+        location,
+      } = statement
+      else {
+        continue;
+      };
+      if let Some(value) = constant(condition) {
+        let diagnostic = constant_condition(value, label, location.clone());
+        found.push((&CONSTANT_CONDITION, diagnostic));
+      }
+    }
+  }
+}
+
+// Statements after a `goto` up to the next label, functions are not part of the flow
+fn unreachable_statements(ast: &[Statement], found: &mut Vec<(&'static Lint, Diagnostic)>) {
+  for body in bodies(ast) {
+    let mut jump: Option<&Location> = None;
+    let mut reported = false;
+
+    for statement in body {
+      match statement {
+        Statement::Label { .. } => {
+          jump = None;
+          reported = false;
+        }
+        Statement::FunctionDefinition(_) | Statement::NoOperation => {}
+        statement => {
+          if let (Some(jump), false, Some(location)) = (jump, reported, statement.location()) {
+            reported = true;
+            found.push((
+              &UNREACHABLE_STATEMENT,
+              Diagnostic {
+                help: Some("Remove it, or add a label before it to jump to".to_string()),
+                ..diagnostic(
+                  "Unreachable statement",
+                  location.clone(),
+                  "this statement never runs",
+                )
+                .with_span(jump.clone(), "any statement after this jump is unreachable")
+              },
+            ));
+          }
+          if let Statement::UnconditionalJump { location, .. } = statement {
+            jump = jump.or(Some(location));
+          }
+        }
+      }
+    }
+  }
+}
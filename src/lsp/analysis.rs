@@ -0,0 +1,142 @@
+// What the language server knows about one document, computed from scratch on every change.
+//
+// Diagnostics are those of `compiler::compile`: the parser's, which also type checks, and the lints. Symbols are found in the token stream
+// instead of the AST, so hover and go-to-definition keep working while the file does not parse.
+// Everything here works on byte offsets, the server translates them to LSP positions.
+
+use std::ops::Range;
+
+use logos::Logos;
+
+use crate::{
+  ast::{context::Context, Function, VarType},
+  compiler::{self, CompileOptions},
+  diagnostics::Diagnostic,
+  lexer::tokens::Token,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Symbol {
+  Label,
+  Variable,
+  Function,
+}
+
+pub struct Analysis {
+  tokens: Vec<(Token, Range<usize>)>,
+  context: Context,
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Analysis {
+  pub fn new(source: &str) -> Self {
+    let mut context = Context::new(0);
+    let output = compiler::compile_in(&mut context, source, "", &CompileOptions::default());
+
+    Self {
+      tokens: Token::lexer(source)
+        .spanned()
+        .filter_map(|(token, span)| Some((token.ok()?, span)))
+        .collect(),
+      context,
+      diagnostics: output.diagnostics,
+    }
+  }
+
+  /// The type of the variable or the signature of the function under the cursor.
+  pub fn hover(&self, offset: usize) -> Option<String> {
+    let index = self.token_at(offset)?;
+    let name = name(&self.tokens[index].0)?;
+
+    match self.symbol(index) {
+      Symbol::Label => None,
+      Symbol::Variable => {
+        let declaration = self.variable(name)?;
+        let Token::Type(var_type) = &self.tokens[declaration + 2].0 else {
+          return None;
+        };
+        let var_type: VarType = var_type.parse().ok()?;
+        Some(format!("{name}: {var_type}"))
+      }
+      Symbol::Function => self
+        .context
+        .get_function(name)
+        .map(|function| function.signature()),
+    }
+  }
+
+  /// Where the label, variable or function under the cursor is defined, builtins have no source.
+  pub fn definition(&self, offset: usize) -> Option<Range<usize>> {
+    let index = self.token_at(offset)?;
+    let name = name(&self.tokens[index].0)?;
+
+    let definition = match self.symbol(index) {
+      Symbol::Label => (0..self.tokens.len())
+        .find(|&index| self.is_label(index) && name_at(&self.tokens, index) == Some(name))?,
+      Symbol::Variable => self.variable(name)?,
+      Symbol::Function => {
+        self
+          .tokens
+          .windows(2)
+          .position(|window| window[0].0 == Token::Function && name_at(window, 1) == Some(name))?
+          + 1
+      }
+    };
+
+    Some(self.tokens[definition].1.clone())
+  }
+
+  /// What can follow `call`.
+  pub fn completions(&self) -> Vec<Function> {
+    self.context.builtins()
+  }
+
+  fn token_at(&self, offset: usize) -> Option<usize> {
+    // A cursor right after an identifier still points at it
+    self
+      .tokens
+      .iter()
+      .position(|(_, span)| span.contains(&offset))
+      .or_else(|| self.tokens.iter().position(|(_, span)| span.end == offset))
+  }
+
+  fn symbol(&self, index: usize) -> Symbol {
+    let previous = index.checked_sub(1).map(|index| &self.tokens[index].0);
+
+    match previous {
+      Some(Token::Goto) => Symbol::Label,
+      Some(Token::Call | Token::Function) => Symbol::Function,
+      _ if self.is_label(index) => Symbol::Label,
+      _ => Symbol::Variable,
+    }
+  }
+
+  // `name:` not followed by a type
+  fn is_label(&self, index: usize) -> bool {
+    name_at(&self.tokens, index).is_some()
+      && self.tokens.get(index + 1).map(|(token, _)| token) == Some(&Token::Colon)
+      && !matches!(self.tokens.get(index + 2), Some((Token::Type(_), _)))
+  }
+
+  // The first `name: type`, like the parser looks variables up
+  fn variable(&self, variable: &str) -> Option<usize> {
+    self.tokens.windows(3).position(|window| {
+      name(&window[0].0) == Some(variable)
+        && window[1].0 == Token::Colon
+        && matches!(window[2].0, Token::Type(_))
+    })
+  }
+}
+
+// `end` is a keyword, but also a common label name
+fn name(token: &Token) -> Option<&str> {
+  match token {
+    Token::Identifier(name) => Some(name),
+    Token::End => Some("end"),
+    _ => None,
+  }
+}
+
+fn name_at(tokens: &[(Token, Range<usize>)], index: usize) -> Option<&str> {
+  tokens.get(index).and_then(|(token, _)| name(token))
+}
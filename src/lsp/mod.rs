@@ -0,0 +1,331 @@
+// Language Server Protocol server for ETAC, spoken over stdio.
+//
+// Messages are JSON-RPC with `Content-Length` headers. Documents are synchronised whole, and each
+// change is analysed again and answered with its diagnostics.
+
+pub mod analysis;
+
+use std::{
+  collections::HashMap,
+  io::{BufRead, Write},
+};
+
+use serde_json::{json, Value};
+
+use self::analysis::Analysis;
+use crate::diagnostics::Severity;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+// Completion item kind, as numbered by the specification
+const FUNCTION_KIND: u8 = 3;
+
+#[derive(Default)]
+pub struct Server {
+  documents: HashMap<String, String>,
+}
+
+impl Server {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Answers one message, with the responses and notifications to send back.
+  pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+    let method = message["method"].as_str().unwrap_or_default();
+    let params = &message["params"];
+
+    let result = match method {
+      "initialize" => Ok(json!({
+        "capabilities": {
+          // Full document synchronisation
+          "textDocumentSync": 1,
+          "hoverProvider": true,
+          "definitionProvider": true,
+          "completionProvider": { "triggerCharacters": [" "] },
+          "codeActionProvider": true,
+        },
+        "serverInfo": { "name": "compass", "version": env!("CARGO_PKG_VERSION") },
+      })),
+      "shutdown" => Ok(Value::Null),
+      "textDocument/didOpen" => {
+        let document = &params["textDocument"];
+        return self.update(&document["uri"], &document["text"]);
+      }
+      "textDocument/didChange" => {
+        let changes = params["contentChanges"].as_array();
+        let Some(change) = changes.and_then(|changes| changes.last()) else {
+          return vec![];
+        };
+        return self.update(&params["textDocument"]["uri"], &change["text"]);
+      }
+      "textDocument/didClose" => {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        self.documents.remove(uri);
+        return vec![diagnostics(uri, vec![])];
+      }
+      "textDocument/hover" => self.at(params, |analysis, _, offset| {
+        analysis.hover(offset).map_or(Value::Null, |hover| {
+          json!({
+            "contents": { "kind": "markdown", "value": format!("```etac\n{hover}\n```") },
+          })
+        })
+      }),
+      "textDocument/definition" => self.at(params, |analysis, source, offset| {
+        analysis.definition(offset).map_or(Value::Null, |location| {
+          json!({
+            "uri": params["textDocument"]["uri"],
+            "range": range(source, &location),
+          })
+        })
+      }),
+      "textDocument/completion" => self.at(params, |analysis, _, _| {
+        let items: Vec<Value> = analysis
+          .completions()
+          .iter()
+          .map(|function| {
+            json!({
+              "label": function.name,
+              "kind": FUNCTION_KIND,
+              "detail": function.signature(),
+            })
+          })
+          .collect();
+        json!(items)
+      }),
+      "textDocument/codeAction" => self.fixes(params),
+      _ => Err((METHOD_NOT_FOUND, format!("Unknown method `{method}`"))),
+    };
+
+    // Notifications have no id and are never answered
+    let Some(id) = message.get("id") else {
+      return vec![];
+    };
+
+    match result {
+      Ok(result) => vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })],
+      Err((code, message)) => vec![json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+      })],
+    }
+  }
+
+  fn update(&mut self, uri: &Value, text: &Value) -> Vec<Value> {
+    let (Some(uri), Some(text)) = (uri.as_str(), text.as_str()) else {
+      return vec![];
+    };
+
+    let found = Analysis::new(text)
+      .diagnostics
+      .iter()
+      .map(|diagnostic| {
+        // The primary span is the range, the others are related to it
+        let (location, mut message) = match diagnostic.spans.first() {
+          Some(span) => (
+            span.location.clone(),
+            format!("{}: {}", diagnostic.message, span.label),
+          ),
+          None => (0..0, diagnostic.message.clone()),
+        };
+        if let Some(help) = &diagnostic.help {
+          message.push_str(&format!("\nhelp: {help}"));
+        }
+        let related: Vec<Value> = diagnostic
+          .spans
+          .iter()
+          .skip(1)
+          .map(|span| {
+            json!({
+              "location": { "uri": uri, "range": range(text, &span.location) },
+              "message": span.label,
+            })
+          })
+          .collect();
+
+        json!({
+          "range": range(text, &location),
+          "severity": severity(diagnostic.severity),
+          "code": diagnostic.code,
+          "source": "compass",
+          "message": message,
+          "relatedInformation": related,
+        })
+      })
+      .collect();
+
+    self.documents.insert(uri.to_string(), text.to_string());
+    vec![diagnostics(uri, found)]
+  }
+
+  // The text of the document of the request
+  fn document(&self, params: &Value) -> Result<&str, (i64, String)> {
+    let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+    self
+      .documents
+      .get(uri)
+      .map(String::as_str)
+      .ok_or_else(|| (INVALID_PARAMS, format!("Unknown document `{uri}`")))
+  }
+
+  // Runs a query on the document and position of the request
+  fn at(
+    &self,
+    params: &Value,
+    query: impl FnOnce(&Analysis, &str, usize) -> Value,
+  ) -> Result<Value, (i64, String)> {
+    let source = self.document(params)?;
+    let offset = offset(source, &params["position"])
+      .ok_or_else(|| (INVALID_PARAMS, "Invalid position".to_string()))?;
+
+    Ok(query(&Analysis::new(source), source, offset))
+  }
+
+  // Quick fixes of the diagnostics within the range of the request
+  fn fixes(&self, params: &Value) -> Result<Value, (i64, String)> {
+    let source = self.document(params)?;
+    let bound = |position| {
+      offset(source, &params["range"][position])
+        .ok_or_else(|| (INVALID_PARAMS, "Invalid range".to_string()))
+    };
+    let (start, end) = (bound("start")?, bound("end")?);
+
+    let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+    let actions: Vec<Value> = Analysis::new(source)
+      .diagnostics
+      .iter()
+      .filter_map(|diagnostic| diagnostic.fix.as_ref())
+      .filter(|fix| fix.location.start <= end && start <= fix.location.end)
+      .map(|fix| {
+        json!({
+          "title": format!("Replace with `{}`", fix.replacement),
+          "kind": "quickfix",
+          "isPreferred": true,
+          "edit": {
+            "changes": {
+              uri: [{ "range": range(source, &fix.location), "newText": fix.replacement }],
+            },
+          },
+        })
+      })
+      .collect();
+
+    Ok(json!(actions))
+  }
+}
+
+/// Serves the messages of `input` until the client asks to exit.
+pub fn serve(input: &mut impl BufRead, output: &mut impl Write) -> Result<(), String> {
+  let mut server = Server::new();
+
+  while let Some(message) = read_message(input)? {
+    if message["method"] == "exit" {
+      break;
+    }
+
+    for reply in server.handle(&message) {
+      write_message(output, &reply)?;
+    }
+  }
+
+  Ok(())
+}
+
+/// Reads the next message, `None` once the input is closed.
+pub fn read_message(input: &mut impl BufRead) -> Result<Option<Value>, String> {
+  let mut length = None;
+
+  loop {
+    let mut header = String::new();
+    if input.read_line(&mut header).map_err(|e| e.to_string())? == 0 {
+      return Ok(None);
+    }
+
+    let header = header.trim_end();
+    if header.is_empty() {
+      break;
+    }
+
+    if let Some((name, value)) = header.split_once(':') {
+      if name.eq_ignore_ascii_case("Content-Length") {
+        length = Some(
+          value
+            .trim()
+            .parse::<usize>()
+            .map_err(|e| format!("Invalid Content-Length: {e}"))?,
+        );
+      }
+    }
+  }
+
+  let length = length.ok_or("Missing Content-Length header")?;
+  let mut content = vec![0; length];
+  input.read_exact(&mut content).map_err(|e| e.to_string())?;
+
+  serde_json::from_slice(&content)
+    .map(Some)
+    .map_err(|e| format!("Invalid message: {e}"))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Value) -> Result<(), String> {
+  let content = message.to_string();
+
+  write!(output, "Content-Length: {}\r\n\r\n{content}", content.len())
+    .and_then(|_| output.flush())
+    .map_err(|e| e.to_string())
+}
+
+fn diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+  json!({
+    "jsonrpc": "2.0",
+    "method": "textDocument/publishDiagnostics",
+    "params": { "uri": uri, "diagnostics": diagnostics },
+  })
+}
+
+// As numbered by the specification, notes are information
+fn severity(severity: Severity) -> u8 {
+  match severity {
+    Severity::Error => 1,
+    Severity::Warning => 2,
+    Severity::Note => 3,
+  }
+}
+
+// LSP positions count lines, and UTF-16 code units within them
+fn position(source: &str, offset: usize) -> Value {
+  let before = &source[..offset.min(source.len())];
+  let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+  json!({
+    "line": before.matches('\n').count(),
+    "character": before[line_start..].encode_utf16().count(),
+  })
+}
+
+fn range(source: &str, location: &std::ops::Range<usize>) -> Value {
+  json!({ "start": position(source, location.start), "end": position(source, location.end) })
+}
+
+fn offset(source: &str, position: &Value) -> Option<usize> {
+  let line = position["line"].as_u64()? as usize;
+  let character = position["character"].as_u64()? as usize;
+
+  let line_start = match line {
+    0 => 0,
+    _ => source.match_indices('\n').nth(line - 1)?.0 + 1,
+  };
+  let text = source[line_start..].split('\n').next().unwrap_or_default();
+
+  let mut units = 0;
+  for (index, c) in text.char_indices() {
+    if units >= character {
+      return Some(line_start + index);
+    }
+    units += c.len_utf16();
+  }
+
+  Some(line_start + text.len())
+}
@@ -1,19 +1,23 @@
-use celestial_hub_compass::cli::{Cli, Commands};
-use celestial_hub_compass::codegen::Codegen;
+use celestial_hub_compass::cli::{
+  build, debug, emit, eval, explain, fmt, gen, lsp, repl, sim, test, vm, Cli, Commands,
+};
 use clap::Parser;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
   let cli = Cli::parse();
 
-  let ast = match &cli.command {
-    Commands::Emit(options) => celestial_hub_compass::cli::emit::ast(options),
-  }?;
-
-  let mips_codegen = celestial_hub_compass::codegen::mips::MipsCodegen;
-
-  let result = mips_codegen.generate(ast, &mut Default::default())?;
-
-  println!("{}", result);
-
-  Ok(())
+  match &cli.command {
+    Commands::Emit(options) => emit::run(options),
+    Commands::Build(options) => build::build(options),
+    Commands::Debug(options) => debug::run(options),
+    Commands::Eval(options) => eval::run(options),
+    Commands::Explain(options) => explain::run(options),
+    Commands::Fmt(options) => fmt::run(options),
+    Commands::Gen(options) => gen::run(options),
+    Commands::Lsp(options) => lsp::run(options),
+    Commands::Repl(options) => repl::run(options),
+    Commands::Vm(options) => vm::run(options),
+    Commands::Sim(options) => sim::run(options),
+    Commands::Test(options) => test::run(options),
+  }
 }
@@ -1,119 +1,229 @@
-use ariadne::ReportBuilder;
+use std::ops::Range;
+
 use lalrpop_util::{lalrpop_mod, ParseError};
+use logos::Logos;
 
 use crate::{
   ast::{self, context::Context},
-  lexer::{Lexer, LexicalError},
+  diagnostics::{self, suggestions::did_you_mean},
+  lexer::{
+    self,
+    tokens::{LexingError, Token},
+    ErrorTip, Lexer, LexicalError,
+  },
 };
 
 pub struct Parser;
 
-lalrpop_mod!(pub compass_grammar, "/parser/compass_grammar.rs");
+lalrpop_mod!(
+  #[allow(clippy::all)]
+  pub compass_grammar,
+  "/parser/compass_grammar.rs"
+);
+
+pub mod recovery;
+
+/// Every error of a source, sorted by where they are.
+#[derive(Debug)]
+pub struct ParseErrors(pub Vec<recovery::Error>);
+
+impl std::fmt::Display for ParseErrors {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self.0.len() {
+      1 => write!(f, "1 error"),
+      count => write!(f, "{count} errors"),
+    }
+  }
+}
+
+impl std::error::Error for ParseErrors {}
 
 impl Parser {
   pub fn new() -> Self {
     Self
   }
 
+  /// Parses the program, printing a report for each of its errors and how many there are. Use
+  /// `compiler::compile` to get them as diagnostics instead.
   pub fn parse(&self, lexer: Lexer) -> Result<Vec<ast::Statement>, Box<dyn std::error::Error>> {
-    use ariadne::{Color, ColorGenerator, Config, Fmt, Label, Report, ReportKind, Source};
-
-    let mut colors = ColorGenerator::default();
+    use ariadne::{Color, Fmt};
 
-    let filename = lexer.filepath.split('/').last().unwrap();
+    let filename = lexer.filepath.split('/').next_back().unwrap();
     let source = lexer.source_code;
 
-    let mut report: ReportBuilder<(&str, std::ops::Range<usize>)> =
-      Report::build(ReportKind::Error, filename, 12)
-        .with_code(3)
-        .with_config(Config::default().with_tab_width(2))
-        .with_note(format!(
-          "If you think this is a bug, please file an issue at {}",
-          "github.com/celestial-hub/compass/issues".fg(Color::Blue)
-        ));
-
     // NOTE: I should probably use this context in the codegen module
     let mut context = Context::new(0);
-    match compass_grammar::ProgramParser::new().parse(&mut context, lexer) {
-      Ok(ast) => Ok(ast),
-      Err(err) => match err {
-        ParseError::InvalidToken { location } => {
-          report
-            .with_message("Invalid token".fg(Color::Red))
-            .with_label(
-              Label::new((filename, location..location))
-                .with_message("Invalid token")
-                .with_color(colors.next()),
-            )
-            .finish()
-            .print((filename, Source::from(source)))
-            .unwrap();
-
-          Err(Box::new(err))
-        }
-        ParseError::User { ref error } => {
-          let (errors, help) = match error {
-            LexicalError::WrongType { error, help } => (error, help),
-            LexicalError::UnknownVariable { error, help } => (error, help),
-            LexicalError::UnknownFunction { error, help } => (error, help),
-            LexicalError::WrongArgumentCount { error, help } => (error, help),
-            LexicalError::FunctionIsBuiltin { error, help } => (error, help),
-            LexicalError::UnusedValue { error, help } => (error, help),
-            LexicalError::InvalidToken => unreachable!(),
-          };
-
-          report = report.with_message("Error".fg(Color::Red));
-
-          for error in errors {
-            report = report.with_label(
-              Label::new((filename, error.location.clone()))
-                .with_message(error.message.clone())
-                .with_color(colors.next()),
-            );
-          }
+    let errors = match parse_program(&mut context, lexer) {
+      Ok(ast) => return Ok(ast),
+      Err(errors) => errors,
+    };
+
+    for error in &errors {
+      diagnostics::print(&diagnostics::Diagnostic::from(error), filename, source);
+    }
+
+    let errors = ParseErrors(errors);
+    eprintln!("{}", errors.to_string().fg(Color::Red));
+    eprintln!("For more information about an error, try `compass explain <code>`.");
+
+    Err(Box::new(errors))
+  }
+}
+
+/// Parses a program, going on after its errors to find the next ones.
+pub fn parse_program(
+  context: &mut Context,
+  lexer: Lexer,
+) -> Result<Vec<ast::Statement>, Vec<recovery::Error>> {
+  match parse_partially(context, lexer) {
+    (Some(ast), errors) if errors.is_empty() => Ok(ast),
+    (_, errors) => Err(errors),
+  }
+}
+
+/// Parses a program like `parse_program`, also giving back what could be parsed of it when it has
+/// errors. The statements with invalid tokens or syntax errors are left out.
+///
+/// From a syntax error, the parser skips to the first token it can start a statement with, which
+/// may be a name in the middle of the line. So the source is parsed again with the rest of the
+/// line of its first new syntax error skipped, until there is none. Invalid tokens are skipped
+/// the same way from the start.
+pub fn parse_partially(
+  context: &mut Context,
+  lexer: Lexer,
+) -> (Option<Vec<ast::Statement>>, Vec<recovery::Error>) {
+  let source = lexer.source_code;
+  let mut skipped = vec![];
+  let mut reported: Vec<recovery::Error> = vec![];
+  for (kind, location) in lexer::errors(source) {
+    // The `name:` before an unknown type would be read as a label
+    let start = match kind {
+      LexingError::UnknownType(_) => source[..location.start]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1),
+      _ => location.start,
+    };
+    skipped.push(rest_of_line(source, start..location.end));
+    let error = LexicalError::invalid_token(kind, location, source);
+    reported.push(ParseError::User { error });
+  }
+
+  loop {
+    let mut attempt = context.clone();
+    let mut recovered = vec![];
+    let tokens = lexer::with_error_tokens(source, &skipped);
+    let result = compass_grammar::ProgramParser::new().parse(&mut attempt, &mut recovered, tokens);
+
+    let mut errors: Vec<recovery::Error> = recovered
+      .into_iter()
+      .map(|recovery| recovery.error)
+      .collect();
+    let ast = match result {
+      Ok(ast) => Some(ast),
+      Err(error) => {
+        errors.push(error);
+        None
+      }
+    };
 
-          if let Some(help) = help {
-            report = report.with_help(help.clone());
+    let (new, others): (Vec<_>, Vec<_>) = errors
+      .into_iter()
+      // Those at a skipped part are already reported
+      .filter(|error| {
+        !matches!(
+          error,
+          ParseError::UnrecognizedToken {
+            token: (_, Token::Invalid, _),
+            ..
           }
+        )
+      })
+      .partition(|error| {
+        matches!(
+          error,
+          ParseError::UnrecognizedToken { .. } | ParseError::ExtraToken { .. }
+        )
+      });
 
-          report
-            .finish()
-            .print((filename, Source::from(source)))
-            .unwrap();
-
-          Err(Box::new(err))
-        }
-        ParseError::UnrecognizedToken {
-          ref token,
-          ref expected,
-        } => {
-          report
-            .with_message("Unrecognized token".fg(Color::Red))
-            .with_label(
-              Label::new((filename, token.0..token.2))
-                .with_message("Unrecognized token")
-                .with_color(colors.next()),
-            )
-            .with_help(format!(
-              "Expected one of the following: {}",
-              expected
-                .iter()
-                .map(|token| {
-                  // Remove surrounding quotes
-                  let token = &token[1..token.len() - 1];
-
-                  format!("{}", token.fg(Color::Yellow))
-                })
-                .collect::<Vec<String>>()
-                .join(", ")
-            ))
-            .finish()
-            .print((filename, Source::from(source)))
-            .unwrap();
-          Err(Box::new(err))
-        }
-        _ => Err(Box::new(err)),
-      },
+    // What follows the first error may only be a cascade of it, so it is parsed again
+    let Some(error) = new
+      .into_iter()
+      .min_by_key(|error| recovery::location(error).start)
+    else {
+      *context = attempt;
+      reported.extend(others);
+      reported.sort_by_key(|error| {
+        let location = recovery::location(error);
+        (location.start, location.end)
+      });
+      return (ast, reported);
+    };
+    skipped.push(rest_of_line(source, recovery::location(&error)));
+    reported.push(error);
+  }
+}
+
+// From the start of `location` to the end of its line, or of the function when it is in the line
+// of a `func`, whose `begin` and `end` would not make sense without it
+fn rest_of_line(source: &str, location: Range<usize>) -> Range<usize> {
+  let line_end = |offset: usize| {
+    source[offset..]
+      .find('\n')
+      .map_or(source.len(), |end| offset + end)
+  };
+  let line_start = source[..location.start]
+    .rfind('\n')
+    .map_or(0, |newline| newline + 1);
+
+  let mut end = line_end(location.start);
+  if source[line_start..].trim_start().starts_with("func") {
+    let mut tokens = Token::lexer(&source[end..]).spanned().peekable();
+    while let Some((token, span)) = tokens.next() {
+      let label = matches!(tokens.peek(), Some((Ok(Token::Colon), _)));
+      if token == Ok(Token::End) && !label {
+        end = line_end(end + span.end);
+        break;
+      }
+    }
+  }
+
+  location.start..end.max(location.end)
+}
+
+/// Parses a single expression, such as a REPL input bound to `it`, the argument of the REPL's
+/// `:type` or that of the debugger's `print`.
+pub fn parse_expr(context: &mut Context, lexer: Lexer) -> Result<ast::Expr, Vec<recovery::Error>> {
+  let mut errors = vec![];
+  let result = compass_grammar::ExprParser::new().parse(context, &mut errors, lexer);
+  collect(result, errors)
+}
+
+// The recovered errors and the one the parser stopped at, sorted by where they are
+fn collect<T>(
+  result: Result<T, recovery::Error>,
+  recovered: recovery::Errors,
+) -> Result<T, Vec<recovery::Error>> {
+  let mut errors: Vec<recovery::Error> = recovered
+    .into_iter()
+    .map(|recovery| recovery.error)
+    .collect();
+
+  let value = match result {
+    Ok(value) => Some(value),
+    Err(error) => {
+      errors.push(error);
+      None
+    }
+  };
+
+  match (value, errors.is_empty()) {
+    (Some(value), true) => Ok(value),
+    _ => {
+      errors.sort_by_key(|error| {
+        let location = recovery::location(error);
+        (location.start, location.end)
+      });
+      Err(errors)
     }
   }
 }
@@ -123,3 +233,57 @@ impl Default for Parser {
     Self::new()
   }
 }
+
+/// Records each jump of `body` to a label it does not define. The labels of a function are its
+/// own, and those of the program are outside of any function.
+pub(crate) fn check_labels(body: &[ast::Statement], errors: &mut recovery::Errors) {
+  let labels: Vec<&str> = body
+    .iter()
+    .filter_map(|statement| match statement {
+      ast::Statement::Label { name, .. } => Some(name.as_str()),
+      _ => None,
+    })
+    .collect();
+
+  for statement in body {
+    let (ast::Statement::ConditionalJump {
+      label, location, ..
+    }
+    | ast::Statement::UnconditionalJump { label, location }) = statement
+    else {
+      continue;
+    };
+    if labels.contains(&label.as_str()) {
+      continue;
+    }
+
+    // The label ends the jump
+    let location = location.end - label.len()..location.end;
+    let (help, fix) = did_you_mean(label, labels.iter().copied(), location.clone());
+    let error = LexicalError::UnknownLabel {
+      error: vec![ErrorTip {
+        message: format!("unknown label `{label}`"),
+        location,
+      }],
+      help: help.or_else(|| {
+        Some(format!(
+          "Define it with `{label}:`, in the same function as the jump"
+        ))
+      }),
+      fix,
+    };
+    recovery::report(errors, ParseError::User { error });
+  }
+}
+
+pub(crate) fn expected_tokens(expected: &[String]) -> String {
+  let expected: Vec<&str> = expected
+    .iter()
+    // Remove surrounding quotes
+    .map(|token| &token[1..token.len() - 1])
+    // A statement can start with skipped tokens, but a program never misses them
+    .filter(|token| *token != "invalid")
+    .collect();
+
+  format!("Expected one of the following: {}", expected.join(", "))
+}
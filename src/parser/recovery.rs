@@ -0,0 +1,63 @@
+// Errors the parser goes on after, so that a single run finds all of them.
+//
+// Syntax errors are recovered by the grammar's `!` rule, which skips to the next statement. The
+// type checker records its errors here and parses the statement anyway, so the names it declares
+// are known to the statements after it.
+
+use std::ops::Range;
+
+use lalrpop_util::{ErrorRecovery, ParseError};
+
+use crate::lexer::{tokens::Token, LexicalError};
+
+pub type Error = ParseError<usize, Token, LexicalError>;
+pub type Errors = Vec<ErrorRecovery<usize, Token, LexicalError>>;
+
+/// Records an error of the type checker.
+pub fn report(errors: &mut Errors, error: Error) {
+  errors.push(ErrorRecovery {
+    error,
+    dropped_tokens: vec![],
+  });
+}
+
+/// The result of a check, or `fallback` once its error is recorded.
+pub fn recover<T>(
+  errors: &mut Errors,
+  result: Result<T, Error>,
+  fallback: impl FnOnce() -> T,
+) -> T {
+  result.unwrap_or_else(|error| {
+    report(errors, error);
+    fallback()
+  })
+}
+
+/// Whether an error was recorded within `span`, which the checks of what contains it would repeat.
+pub fn reported(errors: &Errors, span: Range<usize>) -> bool {
+  errors.iter().any(|recovery| {
+    let location = location(&recovery.error);
+    span.start <= location.start && location.end <= span.end
+  })
+}
+
+/// Where an error is, its first label for the errors of the type checker.
+pub fn location(error: &Error) -> Range<usize> {
+  match error {
+    ParseError::User { error } => error
+      .tips()
+      .0
+      .first()
+      .map_or(0..0, |tip| tip.location.clone()),
+    ParseError::InvalidToken { location } | ParseError::UnrecognizedEof { location, .. } => {
+      *location..*location
+    }
+    ParseError::UnrecognizedToken {
+      token: (start, _, end),
+      ..
+    }
+    | ParseError::ExtraToken {
+      token: (start, _, end),
+    } => *start..*end,
+  }
+}
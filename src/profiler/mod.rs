@@ -0,0 +1,198 @@
+// Counts of what a program ran, to find where its steps go.
+//
+// Functions count their calls, labels how many times they were reached, and basic blocks how many
+// times they were entered. All of them also count the statements run inside, which is what the
+// folded stacks weigh: one line per function and label nesting, as flamegraph tools read them.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::{
+  ast::Statement,
+  interpreter::{Interpreter, Observer},
+  runtime::{RuntimeError, Value},
+  utils::line_of,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+  Function,
+  Label,
+  Block,
+}
+
+impl std::fmt::Display for Kind {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      Kind::Function => write!(f, "function"),
+      Kind::Label => write!(f, "label"),
+      Kind::Block => write!(f, "block"),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Counts {
+  /// Calls of a function, or runs of a label or block
+  pub count: u64,
+  pub statements: u64,
+}
+
+// What is running in one call
+#[derive(Default)]
+struct Frame {
+  label: Option<String>,
+  block: Option<String>,
+}
+
+pub struct Profiler<'source> {
+  source: &'source str,
+  /// Where the statements that start a basic block start
+  leaders: HashSet<usize>,
+  frames: Vec<Frame>,
+  pub counts: BTreeMap<(Kind, String), Counts>,
+  /// Statements run under each stack of functions and labels
+  pub stacks: BTreeMap<String, u64>,
+}
+
+impl<'source> Profiler<'source> {
+  pub fn new(source: &'source str, program: &[Statement]) -> Self {
+    let mut leaders = HashSet::new();
+    find_leaders(program, &mut leaders);
+
+    Self {
+      source,
+      leaders,
+      frames: vec![],
+      counts: BTreeMap::new(),
+      stacks: BTreeMap::new(),
+    }
+  }
+
+  /// Everything counted, most run first.
+  pub fn table(&self) -> String {
+    let mut rows: Vec<(&(Kind, String), &Counts)> = self.counts.iter().collect();
+    rows.sort_by(|(a, a_counts), (b, b_counts)| {
+      b_counts
+        .count
+        .cmp(&a_counts.count)
+        .then(b_counts.statements.cmp(&a_counts.statements))
+        .then(a.cmp(b))
+    });
+
+    let mut table = format!(
+      "{:>10}  {:>10}  {:<8}  name\n",
+      "count", "statements", "kind"
+    );
+    for ((kind, name), counts) in rows {
+      table += &format!(
+        "{:>10}  {:>10}  {:<8}  {name}\n",
+        counts.count,
+        counts.statements,
+        kind.to_string()
+      );
+    }
+
+    table
+  }
+
+  /// `main;loop;f 12`, the statements run under each stack.
+  pub fn folded(&self) -> String {
+    self
+      .stacks
+      .iter()
+      .map(|(stack, statements)| format!("{stack} {statements}\n"))
+      .collect()
+  }
+
+  fn count(&mut self, kind: Kind, name: String) -> &mut Counts {
+    self.counts.entry((kind, name)).or_default()
+  }
+}
+
+impl Observer for Profiler<'_> {
+  fn statement(
+    &mut self,
+    interpreter: &Interpreter,
+    _locals: &HashMap<String, Value>,
+    statement: &Statement,
+  ) -> Result<(), RuntimeError> {
+    if matches!(statement, Statement::FunctionDefinition(_)) {
+      return Ok(());
+    }
+    let Some(location) = statement.location() else {
+      return Ok(());
+    };
+
+    // The interpreter's stack has no frame for `main`
+    let depth = interpreter.stack.len();
+    let function = interpreter
+      .stack
+      .last()
+      .map_or("main", |frame| frame.function.name.as_str())
+      .to_string();
+
+    // Frames deeper than this one have returned, and one more is a new call
+    self.frames.truncate(depth + 1);
+    if self.frames.len() == depth {
+      self.frames.push(Frame::default());
+      self.count(Kind::Function, function.clone()).count += 1;
+    }
+
+    if let Statement::Label { name, .. } = statement {
+      self.frames[depth].label = Some(name.clone());
+      self.count(Kind::Label, format!("{function}:{name}")).count += 1;
+    }
+    if self.leaders.contains(&location.start) {
+      let line = line_of(self.source, location.start);
+      let block = match statement {
+        Statement::Label { name, .. } => format!("{function}:{line} ({name})"),
+        _ => format!("{function}:{line}"),
+      };
+      self.frames[depth].block = Some(block.clone());
+      self.count(Kind::Block, block).count += 1;
+    }
+
+    self.count(Kind::Function, function.clone()).statements += 1;
+    if let Some(label) = self.frames[depth].label.clone() {
+      self
+        .count(Kind::Label, format!("{function}:{label}"))
+        .statements += 1;
+    }
+    if let Some(block) = self.frames[depth].block.clone() {
+      self.count(Kind::Block, block).statements += 1;
+    }
+
+    let mut stack = vec!["main"];
+    for (frame, called) in self.frames.iter().zip(&interpreter.stack) {
+      stack.extend(frame.label.as_deref());
+      stack.push(&called.function.name);
+    }
+    stack.extend(self.frames[depth].label.as_deref());
+    *self.stacks.entry(stack.join(";")).or_default() += 1;
+
+    Ok(())
+  }
+}
+
+// The first statement of each body, labels and the statements after jumps
+fn find_leaders(statements: &[Statement], leaders: &mut HashSet<usize>) {
+  let mut after_jump = true;
+
+  for statement in statements {
+    if let Statement::FunctionDefinition(function) = statement {
+      find_leaders(&function.body, leaders);
+      continue;
+    }
+    let Some(location) = statement.location() else {
+      continue;
+    };
+
+    if after_jump || matches!(statement, Statement::Label { .. }) {
+      leaders.insert(location.start);
+    }
+    after_jump = matches!(
+      statement,
+      Statement::ConditionalJump { .. } | Statement::UnconditionalJump { .. }
+    );
+  }
+}
@@ -0,0 +1,369 @@
+// Interactive sessions, where ETAC is typed and run one input at a time.
+//
+// Every input is parsed with the `Context` the previous ones left, so it can use their variables
+// and functions, and run by the reference interpreter with their values. An input that is only an
+// expression is bound to `it`, as if `it: i32 = 1 + 2` had been typed.
+
+use std::{
+  collections::BTreeMap,
+  io::{BufRead, Write},
+};
+
+use celestial_hub_astrolabe::ast as mips_ast;
+use logos::Logos;
+
+use crate::{
+  ast::{context::Context, BinaryOperation, Expr, Statement, VarType, Variable},
+  codegen::{
+    self,
+    mips::{self, MipsCodegen},
+    Artifact, Codegen,
+  },
+  compiler::{self, CompileOptions},
+  diagnostics::{self, Diagnostic},
+  interpreter::Interpreter,
+  lexer::{self, tokens::Token, Lexer},
+  lints::{Level, WARNINGS},
+  parser::parse_expr,
+  runtime::{Io, Memory, Value},
+};
+
+pub const PROMPT: &str = "compass> ";
+/// Shown while a `func` waits for its `end`
+pub const CONTINUATION: &str = "....> ";
+
+/// Name of the variable that holds the value of the last expression
+pub const IT: &str = "it";
+
+const HELP: &str = "Type declarations, calls and expressions, a `func` is read until its `end`.
+The value of an expression on its own is kept in `it`.
+
+:ast        the AST of the last input
+:asm        the MIPS generated for the last input
+:type expr  the type of an expression, or the signature of a function
+:help       this message
+:quit       leaves the REPL";
+
+#[derive(Default)]
+pub struct Repl {
+  context: Context,
+  /// Every statement run so far, in order
+  history: Vec<Statement>,
+  /// The statements of the last input
+  last: Vec<Statement>,
+  globals: BTreeMap<String, Value>,
+  memory: Memory,
+}
+
+impl Repl {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Runs a command or a complete input, and describes the result. The builtins use `io`.
+  pub fn eval(&mut self, input: &str, io: &mut Io) -> Result<String, String> {
+    let input = input.trim();
+
+    if let Some(command) = input.strip_prefix(':') {
+      return self.command(command);
+    }
+    if input.is_empty() {
+      return Ok(String::new());
+    }
+
+    let statements = self.parse(input)?;
+    self.run(statements, io)
+  }
+
+  fn parse(&mut self, input: &str) -> Result<Vec<Statement>, String> {
+    // A failed compilation may have declared some variables already
+    let mut context = self.context.clone();
+    let output = compiler::compile_in(&mut context, input, "repl", &options());
+    let has_errors = output.has_errors();
+    let errors = diagnostics::plain(input, &output.diagnostics);
+
+    match output.ast {
+      Some(statements) if !has_errors => {
+        self.context = context;
+        return Ok(statements);
+      }
+      Some(_) => return Err(errors),
+      // Nor can an expression be made of tokens the lexer rejects
+      None if !lexer::errors(input).is_empty() => return Err(errors),
+      None => {}
+    }
+
+    match parse_expr(&mut self.context.clone(), Lexer::unchecked(input, "repl")) {
+      Ok(expr) => Ok(vec![self.bind(input, expr)?]),
+      // Only report the expression's errors when it got far enough to be type checked
+      Err(expr_errors)
+        if expr_errors
+          .iter()
+          .all(|error| matches!(error, lalrpop_util::ParseError::User { .. })) =>
+      {
+        Err(diagnostics::plain(
+          input,
+          &diagnostics::from_errors(&expr_errors),
+        ))
+      }
+      Err(_) => Err(errors),
+    }
+  }
+
+  // `it: <type> = <expr>`, replacing the previous `it`
+  fn bind(&mut self, input: &str, expr: Expr) -> Result<Statement, String> {
+    let var_type = self.expr_type(input, &expr)?;
+    if let (VarType::Void, Expr::FunctionCall(call)) = (var_type, &expr) {
+      return Ok(Statement::Call(call.clone()));
+    }
+
+    for variables in self.context.variables.values_mut() {
+      variables.retain(|variable| variable.name != IT);
+    }
+
+    let statement = Statement::VariableDeclaration(Variable {
+      name: IT.to_string(),
+      value: expr,
+      var_type,
+      location: 0..0,
+    });
+    self.context.add_variable(statement.clone());
+
+    Ok(statement)
+  }
+
+  // The grammar only checks the variables of operations, a lone operand is checked here
+  fn expr_type(&self, input: &str, expr: &Expr) -> Result<VarType, String> {
+    match expr {
+      Expr::Operand(operand) => operand
+        .get_type(&self.context, 0..input.len())
+        .map_err(|error| diagnostics::plain(input, &[Diagnostic::from(&error)])),
+      Expr::BinaryOperation(
+        BinaryOperation::Arithmetic { operation_type, .. }
+        | BinaryOperation::Conditional { operation_type, .. },
+      ) => Ok(*operation_type),
+      Expr::FunctionCall(call) => Ok(call.return_type),
+    }
+  }
+
+  fn run(&mut self, statements: Vec<Statement>, io: &mut Io) -> Result<String, String> {
+    // Functions are defined by earlier inputs, which are not run again
+    let mut program: Vec<Statement> = self
+      .history
+      .iter()
+      .filter(|statement| matches!(statement, Statement::FunctionDefinition(_)))
+      .cloned()
+      .collect();
+    program.extend(statements.iter().cloned());
+
+    let mut interpreter = Interpreter::new(&program);
+    interpreter.globals = std::mem::take(&mut self.globals);
+    interpreter.memory = std::mem::take(&mut self.memory);
+    let result = interpreter.run(io);
+    self.globals = std::mem::take(&mut interpreter.globals);
+    self.memory = std::mem::take(&mut interpreter.memory);
+
+    self.history.extend(statements.iter().cloned());
+    self.last = statements;
+    result.map_err(|error| format!("error: {error}"))?;
+
+    Ok(self.describe())
+  }
+
+  // The values of the variables and the signatures of the functions of the last input
+  fn describe(&self) -> String {
+    let mut described: Vec<&str> = vec![];
+    let mut lines = vec![];
+
+    for statement in &self.last {
+      match statement {
+        Statement::VariableDeclaration(variable) if !described.contains(&&*variable.name) => {
+          described.push(&variable.name);
+          if let Some(value) = self.globals.get(&variable.name) {
+            lines.push(format!(
+              "{}: {} = {value}",
+              variable.name, variable.var_type
+            ));
+          }
+        }
+        Statement::FunctionDefinition(function) => lines.push(function.signature()),
+        _ => {}
+      }
+    }
+
+    lines.join("\n")
+  }
+
+  fn command(&mut self, command: &str) -> Result<String, String> {
+    let (name, argument) = command
+      .split_once(char::is_whitespace)
+      .map_or((command, ""), |(name, argument)| (name, argument.trim()));
+
+    match name {
+      "ast" | "asm" if self.last.is_empty() => Err("Nothing was run yet".to_string()),
+      "ast" => Ok(format!("{:#?}", self.last)),
+      "asm" => self.asm(),
+      "type" => self.type_of(argument),
+      "help" => Ok(HELP.to_string()),
+      _ => Err(format!("Unknown command `:{name}`, `:help` lists them")),
+    }
+  }
+
+  // The code of the last input, generated after everything before it
+  fn asm(&self) -> Result<String, String> {
+    mips::target::INFO
+      .check(&self.last)
+      .map_err(|error| error.to_string())?;
+
+    let mut context = codegen::context::Context::default();
+    let before = self.history.len() - self.last.len();
+    MipsCodegen
+      .generate(self.history[..before].to_vec(), &mut context)
+      .map_err(|error| error.to_string())?;
+
+    let data = context.data_section.variables.len();
+    context.text_section.statements.clear();
+    let generated = MipsCodegen.generate(self.last.clone(), &mut context);
+    let Artifact::Mips(program) = generated.map_err(|error| error.to_string())? else {
+      unreachable!("the mips backend generates mips");
+    };
+
+    let mut text = program.text_section.statements;
+    // The entry point and the final `halt` are the same for every input
+    text.retain(
+      |statement| !matches!(statement, mips_ast::Statement::Label(label) if label == "main"),
+    );
+    if matches!(
+      text.last(),
+      Some(mips_ast::Statement::Instruction(
+        mips_ast::Instruction::Halt
+      ))
+    ) {
+      text.pop();
+    }
+
+    let lines: Vec<String> = program.data_section.variables[data..]
+      .iter()
+      .map(ToString::to_string)
+      .chain(text.iter().map(ToString::to_string))
+      .collect();
+
+    Ok(lines.join("\n"))
+  }
+
+  fn type_of(&self, argument: &str) -> Result<String, String> {
+    if argument.is_empty() {
+      return Err("`:type` needs an expression, such as `:type x`".to_string());
+    }
+
+    if self.context.get_variable(argument.to_string()).is_none() {
+      if let Some(function) = self.context.get_function(argument) {
+        return Ok(function.signature());
+      }
+    }
+
+    let invalid = diagnostics::invalid_tokens(argument);
+    if !invalid.is_empty() {
+      return Err(diagnostics::plain(argument, &invalid));
+    }
+
+    let expr = parse_expr(
+      &mut self.context.clone(),
+      Lexer::unchecked(argument, "repl"),
+    )
+    .map_err(|errors| diagnostics::plain(argument, &diagnostics::from_errors(&errors)))?;
+
+    Ok(format!("{argument}: {}", self.expr_type(argument, &expr)?))
+  }
+}
+
+// Each input is linted on its own, while its variables are often only read by the next ones
+fn options() -> CompileOptions {
+  let mut options = CompileOptions::default();
+  options
+    .lints
+    .set(WARNINGS, Level::Allow)
+    .expect("`warnings` to be a lint group");
+  options
+}
+
+/// Whether the input can be run, or a `func` still waits for its `end`.
+pub fn is_complete(input: &str) -> bool {
+  let tokens: Vec<Token> = Token::lexer(input).filter_map(Result::ok).collect();
+  let mut pending = false;
+  let mut open = 0usize;
+
+  for (index, token) in tokens.iter().enumerate() {
+    match token {
+      Token::Function => pending = true,
+      Token::Begin => {
+        pending = false;
+        open += 1;
+      }
+      // `end` is also a label name, in `goto end` and `end:`
+      Token::End
+        if index.checked_sub(1).map(|index| &tokens[index]) != Some(&Token::Goto)
+          && tokens.get(index + 1) != Some(&Token::Colon) =>
+      {
+        open = open.saturating_sub(1);
+      }
+      _ => {}
+    }
+  }
+
+  !pending && open == 0
+}
+
+/// Reads inputs from `input` until it is closed or `:quit` is typed.
+pub fn run(input: &mut impl BufRead, output: &mut impl Write) -> Result<(), String> {
+  let mut repl = Repl::new();
+  let mut buffer = String::new();
+
+  writeln!(
+    output,
+    "Compass {}, `:help` lists the commands",
+    env!("CARGO_PKG_VERSION")
+  )
+  .map_err(|e| e.to_string())?;
+
+  loop {
+    let prompt = match buffer.is_empty() {
+      true => PROMPT,
+      false => CONTINUATION,
+    };
+    write!(output, "{prompt}")
+      .and_then(|_| output.flush())
+      .map_err(|e| e.to_string())?;
+
+    let mut line = String::new();
+    if input.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+      writeln!(output).map_err(|e| e.to_string())?;
+      break;
+    }
+    if buffer.is_empty() && matches!(line.trim(), ":quit" | ":q") {
+      break;
+    }
+
+    buffer += &line;
+    if !buffer.trim_start().starts_with(':') && !is_complete(&buffer) {
+      continue;
+    }
+
+    // What the builtins print comes first, on lines of its own
+    let mut printed = vec![];
+    let result = repl.eval(&buffer, &mut Io::new(input, &mut printed));
+    buffer.clear();
+
+    if !printed.is_empty() && !printed.ends_with(b"\n") {
+      printed.push(b'\n');
+    }
+    let text = result.unwrap_or_else(|error| error);
+    if !text.is_empty() {
+      printed.extend(text.as_bytes());
+      printed.push(b'\n');
+    }
+    output.write_all(&printed).map_err(|e| e.to_string())?;
+  }
+
+  Ok(())
+}
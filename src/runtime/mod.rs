@@ -0,0 +1,411 @@
+// Values and builtins shared by everything that executes ETAC programs inside Compass.
+
+use std::{
+  collections::HashMap,
+  io::{BufRead, Write},
+};
+
+use crate::ast::{Condition, Operand, Operator, VarType};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+  I8(i8),
+  I16(i16),
+  I32(i32),
+  I64(i64),
+  U8(u8),
+  U16(u16),
+  U32(u32),
+  U64(u64),
+  Bool(bool),
+  F32(f32),
+  F64(f64),
+  Str(String),
+  Ptr(u32),
+  Void,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RuntimeError {
+  DivisionByZero,
+  UnknownVariable(String),
+  UnknownFunction(String),
+  UnknownLabel(String),
+  InvalidOperation(String),
+  InvalidAddress(u32),
+  Io(String),
+  StepLimitExceeded(u64),
+  /// Stopped from outside, such as by quitting the debugger
+  Interrupted,
+}
+
+impl std::fmt::Display for RuntimeError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      RuntimeError::DivisionByZero => write!(f, "division by zero"),
+      RuntimeError::UnknownVariable(name) => write!(f, "unknown variable `{name}`"),
+      RuntimeError::UnknownFunction(name) => write!(f, "unknown function `{name}`"),
+      RuntimeError::UnknownLabel(name) => write!(f, "unknown label `{name}`"),
+      RuntimeError::InvalidOperation(message) => write!(f, "{message}"),
+      RuntimeError::InvalidAddress(address) => write!(f, "invalid address {address:#010x}"),
+      RuntimeError::Io(message) => write!(f, "I/O error: {message}"),
+      RuntimeError::StepLimitExceeded(limit) => {
+        write!(f, "program did not finish after {limit} steps")
+      }
+      RuntimeError::Interrupted => write!(f, "program was interrupted"),
+    }
+  }
+}
+
+impl std::error::Error for RuntimeError {}
+
+impl From<std::io::Error> for RuntimeError {
+  fn from(error: std::io::Error) -> Self {
+    RuntimeError::Io(error.to_string())
+  }
+}
+
+// Applies the same expression to the payload of every numeric variant.
+macro_rules! numeric {
+  ($value:expr, $inner:ident => $body:expr) => {
+    match $value {
+      Value::I8($inner) => Some($body),
+      Value::I16($inner) => Some($body),
+      Value::I32($inner) => Some($body),
+      Value::I64($inner) => Some($body),
+      Value::U8($inner) => Some($body),
+      Value::U16($inner) => Some($body),
+      Value::U32($inner) => Some($body),
+      Value::U64($inner) => Some($body),
+      Value::F32($inner) => Some($body),
+      Value::F64($inner) => Some($body),
+      Value::Ptr($inner) => Some($body),
+      Value::Bool($inner) => Some(*$inner as u8 as _),
+      Value::Str(_) | Value::Void => None,
+    }
+  };
+}
+
+impl Value {
+  pub fn var_type(&self) -> VarType {
+    match self {
+      Value::I8(_) => VarType::I8,
+      Value::I16(_) => VarType::I16,
+      Value::I32(_) => VarType::I32,
+      Value::I64(_) => VarType::I64,
+      Value::U8(_) => VarType::U8,
+      Value::U16(_) => VarType::U16,
+      Value::U32(_) => VarType::U32,
+      Value::U64(_) => VarType::U64,
+      Value::Bool(_) => VarType::Bool,
+      Value::F32(_) => VarType::F32,
+      Value::F64(_) => VarType::F64,
+      Value::Str(_) => VarType::Str,
+      Value::Ptr(_) => VarType::Ptr,
+      Value::Void => VarType::Void,
+    }
+  }
+
+  /// The value every variable of the given type starts with.
+  ///
+  /// It is also what every function returns: ETAC has no `return` statement yet, so each backend
+  /// yields this value of the return type when a function ends.
+  pub fn zero(var_type: VarType) -> Self {
+    match var_type {
+      VarType::I8 => Value::I8(0),
+      VarType::I16 => Value::I16(0),
+      VarType::I32 => Value::I32(0),
+      VarType::I64 => Value::I64(0),
+      VarType::U8 => Value::U8(0),
+      VarType::U16 => Value::U16(0),
+      VarType::U32 => Value::U32(0),
+      VarType::U64 => Value::U64(0),
+      VarType::Bool => Value::Bool(false),
+      VarType::F32 => Value::F32(0.0),
+      VarType::F64 => Value::F64(0.0),
+      VarType::Str => Value::Str(String::new()),
+      VarType::Ptr => Value::Ptr(0),
+      VarType::Void => Value::Void,
+    }
+  }
+
+  /// The value of a literal operand, `None` for identifiers and dereferences.
+  pub fn from_literal(operand: &Operand) -> Option<Self> {
+    Some(match operand {
+      Operand::LiteralStr(value) => Value::Str(crate::utils::unquote_string_literal(value)),
+      Operand::LiteralBool(value) => Value::Bool(*value),
+      Operand::LiteralI8(value) => Value::I8(*value),
+      Operand::LiteralI16(value) => Value::I16(*value),
+      Operand::LiteralI32(value) => Value::I32(*value),
+      Operand::LiteralI64(value) => Value::I64(*value),
+      Operand::LiteralU8(value) => Value::U8(*value),
+      Operand::LiteralU16(value) => Value::U16(*value),
+      Operand::LiteralU32(value) => Value::U32(*value),
+      Operand::LiteralU64(value) => Value::U64(*value),
+      Operand::LiteralF32(value) => Value::F32(*value),
+      Operand::LiteralF64(value) => Value::F64(*value),
+      Operand::Identifier(_) | Operand::Dereference(_) => return None,
+    })
+  }
+
+  pub fn is_truthy(&self) -> bool {
+    match self {
+      Value::Str(value) => !value.is_empty(),
+      Value::Void => false,
+      Value::F32(value) => *value != 0.0,
+      Value::F64(value) => *value != 0.0,
+      _ => self.as_i64() != Some(0),
+    }
+  }
+
+  #[allow(clippy::unnecessary_cast)]
+  fn as_i64(&self) -> Option<i64> {
+    numeric!(self, value => *value as i64)
+  }
+
+  #[allow(clippy::unnecessary_cast)]
+  fn as_u64(&self) -> Option<u64> {
+    numeric!(self, value => *value as u64)
+  }
+
+  #[allow(clippy::unnecessary_cast)]
+  fn as_f64(&self) -> Option<f64> {
+    numeric!(self, value => *value as f64)
+  }
+
+  /// Numeric conversion with the semantics of Rust's `as`, the identity for any other type.
+  pub fn cast(&self, var_type: VarType) -> Value {
+    if self.var_type() == var_type {
+      return self.clone();
+    }
+
+    let converted = match var_type {
+      VarType::I8 => self.as_i64().map(|value| Value::I8(value as i8)),
+      VarType::I16 => self.as_i64().map(|value| Value::I16(value as i16)),
+      VarType::I32 => self.as_i64().map(|value| Value::I32(value as i32)),
+      VarType::I64 => self.as_i64().map(Value::I64),
+      VarType::U8 => self.as_u64().map(|value| Value::U8(value as u8)),
+      VarType::U16 => self.as_u64().map(|value| Value::U16(value as u16)),
+      VarType::U32 => self.as_u64().map(|value| Value::U32(value as u32)),
+      VarType::U64 => self.as_u64().map(Value::U64),
+      VarType::Ptr => self.as_u64().map(|value| Value::Ptr(value as u32)),
+      VarType::F32 => self.as_f64().map(|value| Value::F32(value as f32)),
+      VarType::F64 => self.as_f64().map(Value::F64),
+      VarType::Bool => Some(Value::Bool(self.is_truthy())),
+      VarType::Str | VarType::Void => None,
+    };
+
+    converted.unwrap_or_else(|| self.clone())
+  }
+
+  /// The value used as a memory address by `store`.
+  pub fn as_address(&self) -> Result<u32, RuntimeError> {
+    self.as_u64().map(|value| value as u32).ok_or_else(|| {
+      RuntimeError::InvalidOperation(format!(
+        "`{}` cannot be used as an address",
+        self.var_type()
+      ))
+    })
+  }
+
+  /// Evaluates an arithmetic operation of the given type, integers wrap around on overflow.
+  pub fn arithmetic(
+    operator: &Operator,
+    var_type: VarType,
+    lhs: &Value,
+    rhs: &Value,
+  ) -> Result<Value, RuntimeError> {
+    macro_rules! integer {
+      ($variant:ident, $lhs:expr, $rhs:expr) => {{
+        let (lhs, rhs) = ($lhs, $rhs);
+        Value::$variant(match operator {
+          Operator::Add => lhs.wrapping_add(rhs),
+          Operator::Sub => lhs.wrapping_sub(rhs),
+          Operator::Mul => lhs.wrapping_mul(rhs),
+          Operator::Div if rhs == 0 => return Err(RuntimeError::DivisionByZero),
+          Operator::Div => lhs.wrapping_div(rhs),
+        })
+      }};
+    }
+
+    macro_rules! float {
+      ($variant:ident, $lhs:expr, $rhs:expr) => {{
+        let (lhs, rhs) = ($lhs, $rhs);
+        Value::$variant(match operator {
+          Operator::Add => lhs + rhs,
+          Operator::Sub => lhs - rhs,
+          Operator::Mul => lhs * rhs,
+          Operator::Div => lhs / rhs,
+        })
+      }};
+    }
+
+    Ok(match (lhs.cast(var_type), rhs.cast(var_type)) {
+      (Value::I8(lhs), Value::I8(rhs)) => integer!(I8, lhs, rhs),
+      (Value::I16(lhs), Value::I16(rhs)) => integer!(I16, lhs, rhs),
+      (Value::I32(lhs), Value::I32(rhs)) => integer!(I32, lhs, rhs),
+      (Value::I64(lhs), Value::I64(rhs)) => integer!(I64, lhs, rhs),
+      (Value::U8(lhs), Value::U8(rhs)) => integer!(U8, lhs, rhs),
+      (Value::U16(lhs), Value::U16(rhs)) => integer!(U16, lhs, rhs),
+      (Value::U32(lhs), Value::U32(rhs)) => integer!(U32, lhs, rhs),
+      (Value::U64(lhs), Value::U64(rhs)) => integer!(U64, lhs, rhs),
+      (Value::Ptr(lhs), Value::Ptr(rhs)) => integer!(Ptr, lhs, rhs),
+      (Value::Bool(lhs), Value::Bool(rhs)) => match integer!(U8, lhs as u8, rhs as u8) {
+        Value::U8(value) => Value::Bool(value != 0),
+        _ => unreachable!(),
+      },
+      (Value::F32(lhs), Value::F32(rhs)) => float!(F32, lhs, rhs),
+      (Value::F64(lhs), Value::F64(rhs)) => float!(F64, lhs, rhs),
+      (lhs, rhs) => {
+        return Err(RuntimeError::InvalidOperation(format!(
+          "cannot perform `{}` on `{}` and `{}`",
+          operator,
+          lhs.var_type(),
+          rhs.var_type()
+        )))
+      }
+    })
+  }
+
+  /// Evaluates a comparison, the right hand side is converted to the type of the left one.
+  pub fn compare(condition: &Condition, lhs: &Value, rhs: &Value) -> Result<Value, RuntimeError> {
+    let rhs = rhs.cast(lhs.var_type());
+
+    let ordering = match (lhs, &rhs) {
+      (Value::Str(lhs), Value::Str(rhs)) => lhs.partial_cmp(rhs),
+      (Value::F32(_) | Value::F64(_), _) => lhs.as_f64().partial_cmp(&rhs.as_f64()),
+      (
+        Value::U8(_)
+        | Value::U16(_)
+        | Value::U32(_)
+        | Value::U64(_)
+        | Value::Ptr(_)
+        | Value::Bool(_),
+        _,
+      ) => lhs.as_u64().partial_cmp(&rhs.as_u64()),
+      _ => lhs.as_i64().partial_cmp(&rhs.as_i64()),
+    };
+
+    use std::cmp::Ordering::*;
+    Ok(Value::Bool(match condition {
+      Condition::And => lhs.is_truthy() && rhs.is_truthy(),
+      Condition::Or => lhs.is_truthy() || rhs.is_truthy(),
+      Condition::Equal => ordering == Some(Equal),
+      Condition::NotEqual => ordering != Some(Equal),
+      Condition::LessThan => ordering == Some(Less),
+      Condition::GreaterThan => ordering == Some(Greater),
+      Condition::LessThanOrEqual => matches!(ordering, Some(Less | Equal)),
+      Condition::GreaterThanOrEqual => matches!(ordering, Some(Greater | Equal)),
+    }))
+  }
+}
+
+impl std::fmt::Display for Value {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Value::I8(value) => write!(f, "{value}"),
+      Value::I16(value) => write!(f, "{value}"),
+      Value::I32(value) => write!(f, "{value}"),
+      Value::I64(value) => write!(f, "{value}"),
+      Value::U8(value) => write!(f, "{value}"),
+      Value::U16(value) => write!(f, "{value}"),
+      Value::U32(value) => write!(f, "{value}"),
+      Value::U64(value) => write!(f, "{value}"),
+      Value::Bool(value) => write!(f, "{value}"),
+      Value::F32(value) => write!(f, "{value}"),
+      Value::F64(value) => write!(f, "{value}"),
+      Value::Str(value) => write!(f, "{value:?}"),
+      Value::Ptr(value) => write!(f, "{value:#010x}"),
+      Value::Void => write!(f, "void"),
+    }
+  }
+}
+
+/// Where the builtins read from and write to.
+pub struct Io<'io> {
+  pub input: &'io mut dyn BufRead,
+  pub output: &'io mut dyn Write,
+}
+
+impl<'io> Io<'io> {
+  pub fn new(input: &'io mut dyn BufRead, output: &'io mut dyn Write) -> Self {
+    Self { input, output }
+  }
+}
+
+pub const BUILTINS: [&str; 4] = ["write_string", "write_int", "read_int", "read_string"];
+
+/// Runs a builtin with the semantics of the SPIM/MARS syscall the MIPS backend uses for it.
+pub fn call_builtin(name: &str, args: &[Value], io: &mut Io) -> Result<Value, RuntimeError> {
+  let argument = |index: usize| {
+    args.get(index).ok_or_else(|| {
+      RuntimeError::InvalidOperation(format!("missing argument {index} for `{name}`"))
+    })
+  };
+
+  match name {
+    "write_string" => {
+      match argument(0)? {
+        Value::Str(value) => write!(io.output, "{value}")?,
+        other => write!(io.output, "{other}")?,
+      }
+      io.output.flush()?;
+      Ok(Value::Void)
+    }
+    "write_int" => {
+      write!(io.output, "{}", argument(0)?.cast(VarType::I32))?;
+      io.output.flush()?;
+      Ok(Value::Void)
+    }
+    "read_int" => {
+      let mut line = String::new();
+      io.input.read_line(&mut line)?;
+      // Like SPIM, anything that is not a number reads as zero
+      Ok(Value::I32(line.trim().parse().unwrap_or(0)))
+    }
+    "read_string" => {
+      let size = match argument(0)?.cast(VarType::U32) {
+        Value::U32(size) => size as usize,
+        _ => 0,
+      };
+
+      let mut line = String::new();
+      io.input.read_line(&mut line)?;
+
+      // At most `size - 1` bytes are read, leaving room for the NUL terminator
+      let mut end = line.len().min(size.saturating_sub(1));
+      while !line.is_char_boundary(end) {
+        end -= 1;
+      }
+      line.truncate(end);
+
+      Ok(Value::Str(line))
+    }
+    _ => Err(RuntimeError::UnknownFunction(name.to_string())),
+  }
+}
+
+/// Word addressed memory written through `store *p x`.
+#[derive(Debug, Default)]
+pub struct Memory {
+  pub words: HashMap<u32, Value>,
+}
+
+impl Memory {
+  pub fn store(&mut self, address: u32, value: Value) -> Result<(), RuntimeError> {
+    if !address.is_multiple_of(4) {
+      return Err(RuntimeError::InvalidAddress(address));
+    }
+
+    self.words.insert(address, value);
+    Ok(())
+  }
+
+  pub fn load(&self, address: u32) -> Result<Value, RuntimeError> {
+    if !address.is_multiple_of(4) {
+      return Err(RuntimeError::InvalidAddress(address));
+    }
+
+    Ok(self.words.get(&address).cloned().unwrap_or(Value::I32(0)))
+  }
+}
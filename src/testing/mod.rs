@@ -0,0 +1,252 @@
+// Golden tests, written as annotations in the comments of the ETAC files they test.
+//
+//   # stdin: 10
+//   # expect-stdout: 55
+//   # expect-error: unknown variable
+//   # mode: sim
+//
+// `stdin` and `expect-stdout` can be repeated, one line each. Without `expect-error` the program
+// must finish, and the error it fails with must contain the expected text, ignoring case.
+// Programs run in the interpreter, `mode: sim` runs them compiled for MIPS in the simulator and
+// `mode: compile` only checks that they compile.
+
+use std::{
+  io::Write,
+  path::{Path, PathBuf},
+};
+
+use crate::{
+  ast::Statement,
+  compiler::{self, CompileOptions},
+  diagnostics::{self, Diagnostic, Severity},
+  differential::{interpret, simulate},
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Mode {
+  #[default]
+  Run,
+  Sim,
+  Compile,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Expectations {
+  pub mode: Mode,
+  pub stdin: String,
+  pub stdout: Option<String>,
+  pub error: Option<String>,
+}
+
+/// The annotations of a file, comments that are not annotations are left alone.
+pub fn expectations(source: &str) -> Result<Expectations, String> {
+  let mut expectations = Expectations::default();
+
+  for (index, line) in source.lines().enumerate() {
+    let Some(comment) = line.trim_start().strip_prefix('#') else {
+      continue;
+    };
+    let Some((key, value)) = comment.trim_start().split_once(':') else {
+      continue;
+    };
+    let value = value
+      .strip_prefix(' ')
+      .unwrap_or(value)
+      .trim_end_matches('\r');
+
+    match key {
+      "stdin" => expectations.stdin += &format!("{value}\n"),
+      "expect-stdout" => {
+        let stdout = expectations.stdout.get_or_insert_with(String::new);
+        *stdout += &format!("{value}\n");
+      }
+      "expect-error" => expectations.error = Some(value.trim().to_string()),
+      "mode" => {
+        expectations.mode = match value.trim() {
+          "run" => Mode::Run,
+          "sim" => Mode::Sim,
+          "compile" => Mode::Compile,
+          mode => {
+            return Err(format!(
+              "line {}: unknown mode `{mode}`, it is `run`, `sim` or `compile`",
+              index + 1
+            ))
+          }
+        }
+      }
+      key if key.starts_with("expect-") => {
+        return Err(format!("line {}: unknown annotation `{key}`", index + 1))
+      }
+      _ => {}
+    }
+  }
+
+  Ok(expectations)
+}
+
+/// Runs or compiles the program as annotated, and describes how it did not meet its expectations.
+pub fn check(source: &str) -> Result<(), Vec<String>> {
+  let expectations = expectations(source).map_err(|error| vec![error])?;
+
+  let (stdout, error) = match run(source, &expectations) {
+    Ok(stdout) => (stdout, None),
+    Err((stdout, error)) => (stdout, Some(error)),
+  };
+
+  let mut failures = vec![];
+  match (&expectations.error, &error) {
+    (None, Some(error)) => failures.push(format!("failed with: {error}")),
+    (Some(expected), None) => failures.push(format!(
+      "expected an error containing `{expected}`, but it finished"
+    )),
+    (Some(expected), Some(error)) if !error.to_lowercase().contains(&expected.to_lowercase()) => {
+      failures.push(format!(
+        "expected an error containing `{expected}`, but it failed with: {error}"
+      ))
+    }
+    _ => {}
+  }
+
+  // Trailing newlines are not worth a failure
+  if let Some(expected) = &expectations.stdout {
+    let (expected, actual) = (
+      expected.trim_end_matches('\n'),
+      stdout.trim_end_matches('\n'),
+    );
+    if expected != actual {
+      failures.push(format!("stdout differs:\n{}", diff(expected, actual)));
+    }
+  }
+
+  match failures.is_empty() {
+    true => Ok(()),
+    false => Err(failures),
+  }
+}
+
+// What the program wrote, and the error it stopped with
+fn run(source: &str, expectations: &Expectations) -> Result<String, (String, String)> {
+  let options = match expectations.mode {
+    Mode::Compile => CompileOptions::target("mips"),
+    Mode::Run | Mode::Sim => CompileOptions::default(),
+  };
+  let ast = compile(source, &options).map_err(|error| (String::new(), error))?;
+
+  let outcome = match expectations.mode {
+    Mode::Run => interpret(&ast, &expectations.stdin),
+    Mode::Sim => simulate(&ast, &expectations.stdin),
+    Mode::Compile => return Ok(String::new()),
+  }
+  .map_err(|error| (String::new(), error))?;
+
+  match outcome.error {
+    Some(error) => Err((outcome.output, error)),
+    None => Ok(outcome.output),
+  }
+}
+
+// The program, or the errors of its compilation, lints only warn
+fn compile(source: &str, options: &CompileOptions) -> Result<Vec<Statement>, String> {
+  let output = compiler::compile(source, "test", options);
+
+  match output.ast {
+    Some(ast) if !output.has_errors() => Ok(ast),
+    _ => {
+      let errors: Vec<Diagnostic> = output
+        .diagnostics
+        .into_iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .collect();
+      Err(diagnostics::plain(source, &errors))
+    }
+  }
+}
+
+/// The lines of `expected` missing from `actual` with `-`, and the ones added with `+`.
+pub fn diff(expected: &str, actual: &str) -> String {
+  let expected: Vec<&str> = expected.lines().collect();
+  let actual: Vec<&str> = actual.lines().collect();
+
+  // Longest common subsequence of the lines that follow each pair of positions
+  let mut common = vec![vec![0; actual.len() + 1]; expected.len() + 1];
+  for i in (0..expected.len()).rev() {
+    for j in (0..actual.len()).rev() {
+      common[i][j] = match expected[i] == actual[j] {
+        true => common[i + 1][j + 1] + 1,
+        false => common[i + 1][j].max(common[i][j + 1]),
+      };
+    }
+  }
+
+  let (mut i, mut j) = (0, 0);
+  let mut lines = vec![];
+  while i < expected.len() || j < actual.len() {
+    if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+      lines.push(format!("  {}", expected[i]));
+      (i, j) = (i + 1, j + 1);
+    } else if j == actual.len() || (i < expected.len() && common[i + 1][j] >= common[i][j + 1]) {
+      lines.push(format!("- {}", expected[i]));
+      i += 1;
+    } else {
+      lines.push(format!("+ {}", actual[j]));
+      j += 1;
+    }
+  }
+
+  lines.join("\n")
+}
+
+/// The `.etac` files under a directory, or the file itself.
+pub fn discover(path: &Path) -> Result<Vec<PathBuf>, String> {
+  if !path.is_dir() {
+    return Ok(vec![path.to_path_buf()]);
+  }
+
+  let mut files = vec![];
+  let entries = std::fs::read_dir(path).map_err(|e| format!("{}: {e}", path.display()))?;
+  for entry in entries {
+    let path = entry.map_err(|e| e.to_string())?.path();
+    if path.is_dir() {
+      files.extend(discover(&path)?);
+    } else if path
+      .extension()
+      .is_some_and(|extension| extension == "etac")
+    {
+      files.push(path);
+    }
+  }
+  files.sort();
+
+  Ok(files)
+}
+
+/// Checks every file under `path`, reporting each one, and counts the failed ones.
+pub fn run_all(path: &Path, output: &mut impl Write) -> Result<usize, String> {
+  let files = discover(path)?;
+  let mut failed = 0;
+
+  for file in &files {
+    let result = std::fs::read_to_string(file)
+      .map_err(|e| vec![e.to_string()])
+      .and_then(|source| check(&source));
+
+    match result {
+      Ok(()) => writeln!(output, "PASS {}", file.display()),
+      Err(failures) => {
+        failed += 1;
+        writeln!(output, "FAIL {}", file.display()).and_then(|_| {
+          failures.iter().try_for_each(|failure| {
+            let failure = failure.replace('\n', "\n    ");
+            writeln!(output, "    {failure}")
+          })
+        })
+      }
+    }
+    .map_err(|e| e.to_string())?;
+  }
+
+  writeln!(output, "\n{} passed, {failed} failed", files.len() - failed)
+    .map_err(|e| e.to_string())?;
+
+  Ok(failed)
+}
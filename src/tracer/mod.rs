@@ -0,0 +1,155 @@
+// Trace of the statements a program runs, for when it does not do what it should.
+//
+// Each statement is written right before it runs, with the function it runs in, its line, and
+// the values of the variables it reads. The trace is text for people, or JSON lines for tools.
+
+use std::{collections::HashMap, io::Write};
+
+use serde_json::json;
+
+use crate::{
+  ast::{BinaryOperation, Expr, Operand, Statement},
+  interpreter::{Interpreter, Observer},
+  runtime::{RuntimeError, Value},
+  utils::line_of,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TraceFormat {
+  /// `step  function:line  statement  # variables`
+  Text,
+  /// One JSON object per statement
+  Json,
+}
+
+pub struct Tracer<'trace> {
+  source: &'trace str,
+  output: &'trace mut dyn Write,
+  format: TraceFormat,
+}
+
+impl<'trace> Tracer<'trace> {
+  pub fn new(source: &'trace str, output: &'trace mut dyn Write, format: TraceFormat) -> Self {
+    Self {
+      source,
+      output,
+      format,
+    }
+  }
+}
+
+impl Observer for Tracer<'_> {
+  fn statement(
+    &mut self,
+    interpreter: &Interpreter,
+    locals: &HashMap<String, Value>,
+    statement: &Statement,
+  ) -> Result<(), RuntimeError> {
+    // Definitions do nothing when they are reached
+    if matches!(statement, Statement::FunctionDefinition(_)) {
+      return Ok(());
+    }
+    let Some(location) = statement.location() else {
+      return Ok(());
+    };
+
+    let line = line_of(self.source, location.start);
+    let function = interpreter
+      .stack
+      .last()
+      .map_or("main", |frame| frame.function.name.as_str());
+    let variables: Vec<(&str, Value)> = reads(statement)
+      .into_iter()
+      .filter_map(|name| Some((name, interpreter.variable(name, locals).ok()?)))
+      .collect();
+
+    match self.format {
+      TraceFormat::Text => {
+        let mut text = format!("{:>8}  {function}:{line}  {statement}", interpreter.steps);
+        if !variables.is_empty() {
+          let values: Vec<String> = variables
+            .iter()
+            .map(|(name, value)| format!("{name}: {} = {value}", value.var_type()))
+            .collect();
+          text += &format!("  # {}", values.join(", "));
+        }
+        writeln!(self.output, "{text}")?;
+      }
+      TraceFormat::Json => {
+        let variables: Vec<serde_json::Value> = variables
+          .iter()
+          .map(|(name, value)| {
+            json!({
+              "name": name,
+              "type": value.var_type().to_string(),
+              "value": to_json(value),
+            })
+          })
+          .collect();
+        let record = json!({
+          "step": interpreter.steps,
+          "function": function,
+          "line": line,
+          "statement": statement.to_string(),
+          "variables": variables,
+        });
+        writeln!(self.output, "{record}")?;
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// The variables a statement reads, in order and once each.
+pub fn reads(statement: &Statement) -> Vec<&str> {
+  let mut operands = vec![];
+  match statement {
+    Statement::VariableDeclaration(variable) => expr_operands(&variable.value, &mut operands),
+    Statement::ConditionalJump { condition, .. } => expr_operands(condition, &mut operands),
+    Statement::Store { at, from, .. } => operands.extend([at, from]),
+    Statement::Call(call) => operands.extend(&call.params),
+    _ => {}
+  }
+
+  let mut names = vec![];
+  for operand in operands {
+    if let Operand::Identifier(name) | Operand::Dereference(name) = operand {
+      if !names.contains(&name.as_str()) {
+        names.push(name.as_str());
+      }
+    }
+  }
+
+  names
+}
+
+fn expr_operands<'expr>(expr: &'expr Expr, operands: &mut Vec<&'expr Operand>) {
+  match expr {
+    Expr::Operand(operand) => operands.push(operand),
+    Expr::BinaryOperation(operation) => match operation {
+      BinaryOperation::Arithmetic { lhs, rhs, .. }
+      | BinaryOperation::Conditional { lhs, rhs, .. } => operands.extend([lhs, rhs]),
+    },
+    Expr::FunctionCall(call) => operands.extend(&call.params),
+  }
+}
+
+fn to_json(value: &Value) -> serde_json::Value {
+  match value {
+    Value::I8(value) => json!(value),
+    Value::I16(value) => json!(value),
+    Value::I32(value) => json!(value),
+    Value::I64(value) => json!(value),
+    Value::U8(value) => json!(value),
+    Value::U16(value) => json!(value),
+    Value::U32(value) => json!(value),
+    Value::U64(value) => json!(value),
+    Value::Bool(value) => json!(value),
+    Value::F32(value) => json!(value),
+    Value::F64(value) => json!(value),
+    Value::Str(value) => json!(value),
+    Value::Ptr(value) => json!(value),
+    Value::Void => serde_json::Value::Null,
+  }
+}
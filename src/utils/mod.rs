@@ -1,13 +1,90 @@
-use crate::{lexer::Lexer, parser::Parser};
+use crate::{
+  ast::{context::Context, Statement},
+  compiler::{self, CompileOptions},
+  diagnostics::{self, Diagnostic, Severity},
+  lexer::Lexer,
+  parser::parse_program,
+};
 
 pub fn ast_from_code_str(code: &str, test_name: &str) -> String {
-  let ast = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-    let lexer = Lexer::new(code, test_name).expect("Lexer to not fail in tests");
-    Parser::new().parse(lexer)
-  }));
+  let lexer = Lexer::new(code, test_name).expect("Lexer to not fail in tests");
 
-  match ast.unwrap() {
+  match parse_program(&mut Context::new(0), lexer) {
     Ok(ast) => format!("{:#?}", ast),
     Err(err) => format!("{:#?}", err),
   }
 }
+
+/// Parses a snippet without printing anything, or the diagnostics of its errors.
+pub fn parse_code_str(code: &str, test_name: &str) -> Result<Vec<Statement>, Vec<Diagnostic>> {
+  let lexer = Lexer::unchecked(code, test_name);
+
+  parse_program(&mut Context::new(0), lexer).map_err(|errors| diagnostics::from_errors(&errors))
+}
+
+/// The AST of a snippet that is expected to be valid, panicking with its errors otherwise.
+pub fn statements_from_code_str(code: &str, test_name: &str) -> Vec<Statement> {
+  parse_code_str(code, test_name)
+    .unwrap_or_else(|errors| panic!("{}", diagnostics::plain(code, &errors)))
+}
+
+/// Compiles a snippet for the `mips` target to its assembly, or the errors that stopped it.
+pub fn compile_to_asm(code: &str, test_name: &str) -> Result<String, Vec<Diagnostic>> {
+  let output = compiler::compile(code, test_name, &CompileOptions::target("mips"));
+
+  match output.artifact {
+    Some(artifact) => Ok(artifact.to_string()),
+    None => Err(
+      output
+        .diagnostics
+        .into_iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .collect(),
+    ),
+  }
+}
+
+/// Like `ast_from_code_str`, the assembly of a snippet or its diagnostics, for snapshots.
+pub fn asm_from_code_str(code: &str, test_name: &str) -> String {
+  match compile_to_asm(code, test_name) {
+    Ok(asm) => asm,
+    Err(errors) => diagnostics::plain(code, &errors),
+  }
+}
+
+/// Contents of a string literal as it is kept in the AST (surrounding quotes included), with the
+/// escape sequences understood by MARS and SPIM resolved.
+pub fn unquote_string_literal(literal: &str) -> String {
+  let inner = literal
+    .strip_prefix('"')
+    .and_then(|literal| literal.strip_suffix('"'))
+    .unwrap_or(literal);
+
+  let mut value = String::with_capacity(inner.len());
+  let mut chars = inner.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      value.push(c);
+      continue;
+    }
+
+    match chars.next() {
+      Some('n') => value.push('\n'),
+      Some('t') => value.push('\t'),
+      Some('0') => value.push('\0'),
+      Some('\\') => value.push('\\'),
+      Some(other) => {
+        value.push('\\');
+        value.push(other);
+      }
+      None => value.push('\\'),
+    }
+  }
+
+  value
+}
+
+/// The line, counted from 1, of a byte offset in the source.
+pub fn line_of(source: &str, offset: usize) -> usize {
+  source[..offset.min(source.len())].matches('\n').count() + 1
+}
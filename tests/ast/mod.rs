@@ -1,2 +1,4 @@
 pub mod conditionals;
+pub mod printer;
+pub mod recovery;
 pub mod variables;
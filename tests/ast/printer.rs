@@ -0,0 +1,123 @@
+use celestial_hub_compass::{
+  ast::{print, Expr, Statement},
+  generator::generate,
+  utils::statements_from_code_str,
+};
+
+// Locations point into the source, so they differ once the program is printed again
+fn without_locations(ast: Vec<Statement>) -> Vec<Statement> {
+  let call = |mut call: celestial_hub_compass::ast::FunctionCall| {
+    call.location = 0..0;
+    call
+  };
+
+  ast
+    .into_iter()
+    .map(|statement| match statement {
+      Statement::VariableDeclaration(mut variable) => {
+        variable.location = 0..0;
+        if let Expr::FunctionCall(function_call) = variable.value {
+          variable.value = Expr::FunctionCall(call(function_call));
+        }
+        Statement::VariableDeclaration(variable)
+      }
+      Statement::ConditionalJump {
+        condition, label, ..
+      } => Statement::ConditionalJump {
+        condition,
+        label,
+        location: 0..0,
+      },
+      Statement::UnconditionalJump { label, .. } => Statement::UnconditionalJump {
+        label,
+        location: 0..0,
+      },
+      Statement::Label { name, .. } => Statement::Label {
+        name,
+        location: 0..0,
+      },
+      Statement::FunctionDefinition(mut function) => {
+        function.location = 0..0;
+        for argument in &mut function.args {
+          argument.location = 0..0;
+        }
+        function.body = without_locations(function.body);
+        Statement::FunctionDefinition(function)
+      }
+      Statement::Store { at, from, .. } => Statement::Store {
+        at,
+        from,
+        location: 0..0,
+      },
+      Statement::Call(function_call) => Statement::Call(call(function_call)),
+      Statement::NoOperation => Statement::NoOperation,
+    })
+    .collect()
+}
+
+fn assert_round_trip(code: &str, test_name: &str) {
+  let ast = statements_from_code_str(code, test_name);
+  let printed = print(&ast);
+
+  assert_eq!(
+    without_locations(statements_from_code_str(&printed, test_name)),
+    without_locations(ast),
+    "{test_name} printed as\n{printed}"
+  );
+}
+
+#[test]
+fn should_print_canonical_etac() {
+  let ast = statements_from_code_str(
+    r#"
+    func    half(n: i32 flag: bool):i32
+    begin
+        x: u8 = 0xffu8
+      y: f64 = 2.f64
+    end
+    a: i32 = 13i32
+    b: i32 = a / 2
+    if a >= b goto done
+    if false goto done
+    c: f32 = 1f32
+    d: i32 = call half(a true)
+    done:
+    call write_string("done\n")
+    "#,
+    "printer/should_print_canonical_etac",
+  );
+
+  assert_eq!(
+    print(&ast),
+    r#"func half(n: i32 flag: bool): i32
+begin
+  x: u8 = 255u8
+  y: f64 = 2.0f64
+end
+
+a: i32 = 13
+b: i32 = a / 2
+if a >= b goto done
+if false goto _
+c: f32 = 1.0
+d: i32 = call half(a true)
+done:
+call write_string("done\n")
+"#
+  );
+}
+
+#[test]
+fn should_round_trip_assets() {
+  for asset in ["conditional.etac", "floats.etac", "test.etac", "vars.etac"] {
+    let code = std::fs::read_to_string(format!("assets/{asset}")).unwrap();
+    assert_round_trip(&code, asset);
+  }
+}
+
+#[test]
+fn should_round_trip_generated_programs() {
+  for seed in 0..100 {
+    assert_round_trip(&generate(seed).source, &format!("printer/{seed}"));
+  }
+}
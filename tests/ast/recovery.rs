@@ -0,0 +1,73 @@
+use celestial_hub_compass::{diagnostics, utils::parse_code_str};
+
+fn errors(code: &str) -> String {
+  let errors = parse_code_str(code, "recovery").expect_err("the program to be wrong");
+
+  diagnostics::plain(code, &errors)
+}
+
+#[test]
+fn should_report_every_error() {
+  assert_eq!(
+    errors(
+      "a: i32 = 1
+b: f32 = a
+c: i32 = = 3
+d: i32 = call nothing()
+e: i32 = d + 1
+if a goto done
+"
+    ),
+    "error: expected `f32`
+  b: f32 = a
+     ^^^
+error: found variable a which is `i32`
+  b: f32 = a
+           ^
+help: You can either try to cast the value to `f32` or change the variable type to `i32`
+error: Unrecognized token
+  c: i32 = = 3
+           ^
+help: Expected one of the following: call, identifier, literal_f32, literal_f64, literal_false, literal_i16, literal_i32, literal_i64, literal_i8, literal_string, literal_true, literal_u16, literal_u32, literal_u64, literal_u8
+error: unknown function `nothing`
+  d: i32 = call nothing()
+                ^^^^^^^
+error: expected `bool`
+  if a goto done
+  ^^
+error: found `i32`
+  if a goto done
+     ^
+help: You can either try to cast the value to `bool` or change the variable type to `bool`"
+  );
+}
+
+#[test]
+fn should_declare_variables_with_wrong_values() {
+  // `b` keeps the type it was given, so using it as an `f32` is not an error
+  let errors = errors("a: i32 = 1\nb: f32 = a\nc: f32 = b + 1.0\n");
+
+  assert_eq!(errors.matches("error:").count(), 2, "{errors}");
+}
+
+#[test]
+fn should_resync_at_the_end_of_a_function() {
+  insta::assert_snapshot!(errors(
+    "func f(a: i32): i32
+begin
+  return c
+end
+x: i32 = call f(1)
+call write_int(x)
+"
+  ));
+}
+
+#[test]
+fn should_report_invalid_tokens_with_the_other_errors() {
+  let errors = errors("a: i32 = 1 $ 2\nb: i32 = = 3\nc: f32 = a\n");
+
+  assert_eq!(errors.matches("error:").count(), 4, "{errors}");
+  assert!(errors.contains("Invalid token"), "{errors}");
+  assert!(errors.contains("Unrecognized token"), "{errors}");
+}
@@ -0,0 +1,150 @@
+use celestial_hub_compass::{
+  ast::VarType,
+  bytecode::{compiler::compile, vm::Vm, Global, Module},
+  runtime::Io,
+  utils::statements_from_code_str,
+};
+
+fn module_from_code_str(code: &str, test_name: &str) -> Module {
+  let ast = statements_from_code_str(code, test_name);
+
+  compile(&ast).expect("Compiler to not fail in tests")
+}
+
+fn run(module: &Module, input: &str) -> String {
+  let mut input = input.as_bytes();
+  let mut output = vec![];
+
+  Vm::new(module)
+    .run(&mut Io::new(&mut input, &mut output))
+    .expect("VM to not fail in tests");
+
+  String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn should_round_trip_through_bytes() {
+  let module = module_from_code_str(
+    r#"
+    func twice(x: i32): i32
+    begin
+      call write_int(2)
+    end
+
+    a: u8 = 250u8
+    b: f64 = 1.5f64
+    c: bool = a > 3u8
+    d: i32 = call twice(7)
+    call write_string("done\n")
+    "#,
+    "bytecode/should_round_trip_through_bytes",
+  );
+
+  let bytes = module.to_bytes().unwrap();
+  assert_eq!(&bytes[..4], b"CBC\0");
+  assert_eq!(Module::from_bytes(&bytes), Ok(module));
+}
+
+#[test]
+fn should_reject_invalid_files() {
+  assert!(Module::from_bytes(b"ELF").is_err());
+
+  let mut bytes = module_from_code_str("a: i32 = 1", "bytecode/should_reject_invalid_files")
+    .to_bytes()
+    .unwrap();
+  bytes[4] = 0xff;
+  assert!(Module::from_bytes(&bytes)
+    .unwrap_err()
+    .contains("Unsupported bytecode version"));
+}
+
+#[test]
+fn should_reject_functions_with_too_many_parameters() {
+  let mut module = module_from_code_str(
+    "func f() begin end",
+    "bytecode/should_reject_functions_with_too_many_parameters",
+  );
+  let param = Global {
+    name: 0,
+    var_type: VarType::I32,
+  };
+  module.functions[0].params = vec![param; 256];
+
+  assert_eq!(
+    module.to_bytes(),
+    Err("Function `f` has 256 parameters, bytecode functions take at most 255".to_string())
+  );
+}
+
+#[test]
+fn should_run_loop() {
+  let module = module_from_code_str(
+    r#"
+    i: i32 = 0
+    total: i32 = 0
+    loop:
+      total: i32 = total + i
+      i: i32 = i + 1
+      if i < 10 goto loop
+    call write_int(total)
+    call write_string("\n")
+    "#,
+    "bytecode/should_run_loop",
+  );
+
+  assert_eq!(run(&module, ""), "45\n");
+}
+
+#[test]
+fn should_wrap_on_overflow() {
+  let module = module_from_code_str(
+    r#"
+    a: i32 = 2147483647
+    b: i32 = a + 1
+    call write_int(b)
+    "#,
+    "bytecode/should_wrap_on_overflow",
+  );
+
+  assert_eq!(run(&module, ""), "-2147483648");
+}
+
+#[test]
+fn should_cast_stored_globals_to_their_type() {
+  let module = module_from_code_str(
+    r#"
+    a: u8 = 250u8
+    a: i32 = 300
+    if a == 44u8 goto wrapped
+    call write_string("kept")
+    goto done
+    wrapped:
+    call write_string("wrapped")
+    done:
+    "#,
+    "bytecode/should_cast_stored_globals_to_their_type",
+  );
+
+  assert_eq!(run(&module, ""), "wrapped");
+}
+
+#[test]
+fn should_call_functions_and_builtins() {
+  let module = module_from_code_str(
+    r#"
+    func greet()
+    begin
+      call write_string("hello ")
+    end
+
+    call greet()
+    name: str = call read_string(16u32)
+    call write_string(name)
+    n: i32 = call read_int()
+    call write_int(n)
+    "#,
+    "bytecode/should_call_functions_and_builtins",
+  );
+
+  assert_eq!(run(&module, "world\n42\n"), "hello world\n42");
+}
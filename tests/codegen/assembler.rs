@@ -0,0 +1,189 @@
+use celestial_hub_astrolabe::ast::Program;
+use celestial_hub_compass::{
+  codegen::{
+    mips::{
+      assembler::{
+        assemble, assemble_for, Branches, Endianness, Environment, Layout, RelocationKind,
+      },
+      elf,
+    },
+    target, Artifact,
+  },
+  utils::statements_from_code_str,
+};
+
+fn program_from_code_str(code: &str, test_name: &str) -> Program {
+  let ast = statements_from_code_str(code, test_name);
+
+  match target::generate("mips", ast).expect("Codegen to not fail in tests") {
+    Artifact::Mips(program) => program,
+    _ => unreachable!(),
+  }
+}
+
+#[test]
+fn should_encode_instructions() {
+  let program = program_from_code_str(
+    r#"
+    a: i32 = 10
+    b: i32 = a + a
+    c: i32 = 100000
+    "#,
+    "assembler/should_encode_instructions",
+  );
+
+  let assembly = assemble(&program, Layout::MARS).unwrap();
+  assert_eq!(
+    assembly.text,
+    vec![
+      0x2408_000a, // addiu $t0, $zero, 10
+      0x0108_4820, // add $t1, $t0, $t0
+      0x3c0a_0001, // lui $t2, 1
+      0x354a_86a0, // ori $t2, $t2, 0x86a0
+      0x2402_000a, // addiu $v0, $zero, 10 (halt)
+      0x0000_000c, // syscall
+    ]
+  );
+}
+
+#[test]
+fn should_resolve_labels_and_data() {
+  let program = program_from_code_str(
+    r#"
+    i: i32 = 0
+    loop:
+      i: i32 = i + 1
+      if i < 10 goto loop
+    call write_string("done\n")
+    "#,
+    "assembler/should_resolve_labels_and_data",
+  );
+
+  let assembly = assemble(&program, Layout::MARS).unwrap();
+
+  assert_eq!(assembly.address_of("main"), Some(0x0040_0000));
+  assert_eq!(assembly.address_of("loop"), Some(0x0040_0004));
+  assert_eq!(assembly.data, b"done\n\0");
+  assert!(assembly.ensure_linked().is_ok());
+
+  // blt $t0, 10, loop
+  assert_eq!(assembly.text[2], 0x2401_000a); // addiu $at, $zero, 10
+  assert_eq!(assembly.text[3], 0x0101_082a); // slt $at, $t0, $at
+  assert_eq!(assembly.text[4], 0x1420_fffc); // bne $at, $zero, loop
+
+  // la $t1, str_0 against .data at 0x10010000
+  assert_eq!(assembly.text[6], 0x3c01_1001);
+  assert_eq!(assembly.text[7], 0x2429_0000);
+  assert_eq!(
+    assembly
+      .relocations
+      .iter()
+      .map(|relocation| relocation.kind)
+      .collect::<Vec<_>>(),
+    vec![RelocationKind::Hi16, RelocationKind::Lo16]
+  );
+}
+
+#[test]
+fn should_reject_unknown_registers() {
+  let lexer = celestial_hub_astrolabe::lexer::Lexer::new(
+    ".data\n.text\n.global main\nmain:\nli $t10, 10\nhalt",
+    "assembler/should_reject_unknown_registers",
+  );
+  let program = celestial_hub_astrolabe::parser::Parser::new()
+    .parse(lexer)
+    .expect("Assembly to parse in tests");
+
+  assert_eq!(
+    assemble(&program, Layout::MARS).unwrap_err(),
+    "Unknown register `$t10` in `li $t10, 10`"
+  );
+}
+
+#[test]
+fn should_write_elf_objects() {
+  let program = program_from_code_str(
+    r#"call write_string("hi\n")"#,
+    "assembler/should_write_elf_objects",
+  );
+
+  for (endianness, data) in [(Endianness::Big, 2), (Endianness::Little, 1)] {
+    let object = elf::object(&program, endianness).unwrap();
+
+    assert_eq!(&object[..4], b"\x7fELF");
+    assert_eq!(object[4], 1, "ELFCLASS32");
+    assert_eq!(object[5], data);
+
+    let half = |offset: usize| match endianness {
+      Endianness::Big => u16::from_be_bytes([object[offset], object[offset + 1]]),
+      Endianness::Little => u16::from_le_bytes([object[offset], object[offset + 1]]),
+    };
+    assert_eq!(half(16), 1, "ET_REL");
+    assert_eq!(half(18), 8, "EM_MIPS");
+    assert_eq!(half(48), 7, "section count");
+  }
+}
+
+#[test]
+fn should_fill_the_delay_slots_of_elf_objects() {
+  let program = program_from_code_str(
+    r#"
+    func f()
+    begin
+      call write_int(1)
+    end
+
+    i: i32 = 0
+    loop:
+    i: i32 = i + 1
+    if i < 3 goto loop
+    call f()
+    goto done
+    done:
+    "#,
+    "assembler/should_fill_the_delay_slots_of_elf_objects",
+  );
+  let assembly = assemble_for(
+    &program,
+    Layout::RELOCATABLE,
+    Environment::Hosted,
+    Branches::Delayed,
+  )
+  .unwrap();
+
+  // The opcode, or the function of a SPECIAL instruction, of every branch and jump
+  let transfer = |word: u32| match (word >> 26, word & 0x3f) {
+    (0x00, 0x08) => Some("jr"),
+    (0x01, _) | (0x04..=0x07, _) => Some("branch"),
+    (0x02, _) => Some("j"),
+    (0x03, _) => Some("jal"),
+    _ => None,
+  };
+  let mut kinds = vec![];
+  for (index, word) in assembly.text.iter().enumerate() {
+    if let Some(kind) = transfer(*word) {
+      assert_eq!(
+        assembly.text.get(index + 1),
+        Some(&0),
+        "`{kind}` at {index} is not followed by a `nop`"
+      );
+      kinds.push(kind);
+    }
+  }
+  kinds.sort();
+  kinds.dedup();
+  assert_eq!(kinds, ["branch", "j", "jal", "jr"]);
+
+  // The object holds the same `.text`
+  let object = elf::object(&program, Endianness::Big).unwrap();
+  let text: Vec<u8> = assembly
+    .text
+    .iter()
+    .flat_map(|word| word.to_be_bytes())
+    .collect();
+  assert!(object.windows(text.len()).any(|window| window == text));
+
+  // The simulator takes branches at once, without slots to fill
+  let immediate = assemble(&program, Layout::RELOCATABLE).unwrap();
+  assert!(!immediate.text.contains(&0));
+}
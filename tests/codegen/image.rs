@@ -0,0 +1,185 @@
+use celestial_hub_astrolabe::ast::Program;
+use celestial_hub_compass::{
+  codegen::{
+    mips::{
+      assembler::{assemble_for, Branches, Endianness, Environment, Layout},
+      image::{images, ImageFormat},
+    },
+    target, Artifact,
+  },
+  utils::statements_from_code_str,
+};
+
+fn program_from_code_str(code: &str, test_name: &str) -> Program {
+  let ast = statements_from_code_str(code, test_name);
+
+  match target::generate("mips", ast).expect("Codegen to not fail in tests") {
+    Artifact::Mips(program) => program,
+    _ => unreachable!(),
+  }
+}
+
+const LAYOUT: Layout = Layout {
+  text_base: 0,
+  data_base: 0x2000,
+};
+
+#[test]
+fn should_lower_syscalls_to_memory_mapped_io() {
+  let program = program_from_code_str(
+    r#"
+    x: i32 = call read_int()
+    call write_int(x)
+    "#,
+    "image/should_lower_syscalls_to_memory_mapped_io",
+  );
+
+  let assembly = assemble_for(
+    &program,
+    LAYOUT,
+    Environment::BareMetal,
+    Branches::Immediate,
+  )
+  .unwrap();
+  assert_eq!(
+    assembly.text,
+    vec![
+      0x2402_0005, // li $v0, 5
+      0x8c02_fffc, // lw $v0, READ_INT($zero)
+      0x0040_4021, // move $t0, $v0
+      0x2402_0001, // li $v0, 1
+      0x0100_2021, // move $a0, $t0
+      0xac04_fff4, // sw $a0, PRINT_INT($zero)
+      0x1000_ffff, // halt: b .
+    ]
+  );
+  assert!(!assembly.text.contains(&0x0000_000c), "no syscall left");
+}
+
+#[test]
+fn should_start_at_the_entry_point() {
+  let program = program_from_code_str(
+    r#"
+    func answer()
+    begin
+      x: i32 = 42
+    end
+
+    y: i32 = 1
+    "#,
+    "image/should_start_at_the_entry_point",
+  );
+
+  let assembly = assemble_for(
+    &program,
+    LAYOUT,
+    Environment::BareMetal,
+    Branches::Immediate,
+  )
+  .unwrap();
+  let entry = assembly.entry_address().unwrap();
+
+  assert_ne!(entry, 0);
+  assert_eq!(assembly.text[0], 0x0800_0000 | (entry >> 2), "j main");
+}
+
+#[test]
+fn should_render_memory_images() {
+  let program = program_from_code_str(
+    r#"call write_string("hi\n")"#,
+    "image/should_render_memory_images",
+  );
+
+  let readmemh = images(
+    &program,
+    LAYOUT,
+    ImageFormat::Readmemh,
+    Endianness::Big,
+    Branches::Immediate,
+  )
+  .unwrap();
+  let data = String::from_utf8(readmemh.data).unwrap();
+  assert_eq!(data, "// .data at 0x00002000, 1 words\n68690a00\n");
+
+  let little = images(
+    &program,
+    LAYOUT,
+    ImageFormat::Readmemh,
+    Endianness::Little,
+    Branches::Immediate,
+  )
+  .unwrap();
+  assert!(String::from_utf8(little.data)
+    .unwrap()
+    .ends_with("\n000a6968\n"));
+
+  let logisim = images(
+    &program,
+    LAYOUT,
+    ImageFormat::Logisim,
+    Endianness::Big,
+    Branches::Immediate,
+  )
+  .unwrap();
+  let text = String::from_utf8(logisim.text).unwrap();
+  assert!(text.starts_with("v2.0 raw\n24020004 3c010000 24282000 "));
+
+  let mif = images(
+    &program,
+    LAYOUT,
+    ImageFormat::Mif,
+    Endianness::Big,
+    Branches::Immediate,
+  )
+  .unwrap();
+  let data = String::from_utf8(mif.data).unwrap();
+  assert!(data.contains("DEPTH = 1;\nWIDTH = 32;\n"));
+  assert!(data.contains("BEGIN\n0 : 68690a00;\nEND;\n"));
+
+  let bin = images(
+    &program,
+    LAYOUT,
+    ImageFormat::Bin,
+    Endianness::Little,
+    Branches::Immediate,
+  )
+  .unwrap();
+  assert_eq!(bin.data, b"hi\n\0");
+  assert_eq!(bin.text.len() % 4, 0);
+  assert_eq!(&bin.text[..4], &[0x04, 0x00, 0x02, 0x24]);
+}
+
+#[test]
+fn should_tell_whether_delay_slots_are_filled() {
+  let program = program_from_code_str(
+    r#"
+    i: i32 = 0
+    loop:
+    i: i32 = i + 1
+    if i < 3 goto loop
+    "#,
+    "image/should_tell_whether_delay_slots_are_filled",
+  );
+
+  let text = |branches| {
+    let images = images(
+      &program,
+      LAYOUT,
+      ImageFormat::Readmemh,
+      Endianness::Big,
+      branches,
+    );
+    String::from_utf8(images.unwrap().text).unwrap()
+  };
+
+  // `halt` is a branch to itself
+  let immediate = text(Branches::Immediate);
+  assert!(immediate.starts_with("// .text at 0x00000000, 6 words, branches without delay slots\n"));
+  assert!(immediate.ends_with("\n1000ffff\n"));
+
+  let delayed = text(Branches::Delayed);
+  assert!(delayed
+    .starts_with("// .text at 0x00000000, 8 words, a nop in the delay slot of each branch\n"));
+  assert!(delayed.contains("\n1420fffc\n00000000\n"));
+  assert!(delayed.ends_with("\n1000ffff\n00000000\n"));
+}
@@ -0,0 +1,220 @@
+use celestial_hub_compass::{
+  diagnostics::codes,
+  utils::{asm_from_code_str, compile_to_asm},
+};
+
+#[test]
+fn should_compile_literals() {
+  insta::assert_snapshot!(asm_from_code_str(
+    r#"
+    a: i32 = 13
+    b: i32 = -7i32
+    c: bool = true
+    d: bool = false
+    e: str = "hello\n"
+    "#,
+    "mips/should_compile_literals/default"
+  ));
+
+  insta::assert_snapshot!(asm_from_code_str(
+    r#"
+    a: i8 = 1i8
+    b: i16 = 2i16
+    c: u8 = 3u8
+    d: u16 = 4u16
+    e: u32 = 5u32
+    "#,
+    "mips/should_compile_literals/sized"
+  ));
+}
+
+#[test]
+fn should_compile_operands() {
+  insta::assert_snapshot!(asm_from_code_str(
+    r#"
+    a: i32 = 1
+    b: i32 = a
+    c: str = "text"
+    d: str = c
+    "#,
+    "mips/should_compile_operands/identifiers"
+  ));
+}
+
+#[test]
+fn should_compile_arithmetic() {
+  insta::assert_snapshot!(asm_from_code_str(
+    r#"
+    a: i32 = 6
+    b: i32 = 3
+    c: i32 = a + b
+    d: i32 = a - b
+    e: i32 = a * b
+    f: i32 = a / b
+    "#,
+    "mips/should_compile_arithmetic/identifiers"
+  ));
+
+  insta::assert_snapshot!(asm_from_code_str(
+    r#"
+    a: i32 = 6
+    b: i32 = a + 1
+    d: i32 = 2 * 3
+    e: i32 = a / 2
+    "#,
+    "mips/should_compile_arithmetic/literals"
+  ));
+
+  insta::assert_snapshot!(asm_from_code_str(
+    r#"
+    a: i32 = 6
+    b: i32 = 10 - a
+    "#,
+    "mips/should_compile_arithmetic/literal_lhs"
+  ));
+}
+
+#[test]
+fn should_compile_conditions() {
+  insta::assert_snapshot!(asm_from_code_str(
+    r#"
+    a: i32 = 1
+    b: i32 = 2
+    c: bool = a < b
+    d: bool = a > b
+    e: bool = a <= b
+    f: bool = a >= b
+    g: bool = a == b
+    h: bool = a != b
+    "#,
+    "mips/should_compile_conditions/comparisons"
+  ));
+
+  insta::assert_snapshot!(asm_from_code_str(
+    r#"
+    a: i32 = 1
+    c: bool = true
+    if a < 2 goto less
+    if a >= 2 goto less
+    if a == 1 goto less
+    if c goto less
+    less:
+    "#,
+    "mips/should_compile_conditions/jumps"
+  ));
+}
+
+#[test]
+fn should_compile_jumps() {
+  insta::assert_snapshot!(asm_from_code_str(
+    r#"
+    i: i32 = 0
+    loop:
+      i: i32 = i + 1
+      if i < 10 goto loop
+    goto done
+    i: i32 = 0
+    done:
+    "#,
+    "mips/should_compile_jumps/loop"
+  ));
+
+  insta::assert_snapshot!(asm_from_code_str(
+    r#"
+    if false goto skip
+    if true goto skip
+    skip:
+    "#,
+    "mips/should_compile_jumps/constant_conditions"
+  ));
+}
+
+#[test]
+fn should_compile_stores() {
+  insta::assert_snapshot!(asm_from_code_str(
+    r#"
+    p: i32 = 268500992
+    v: i32 = 42
+    store *p v
+    "#,
+    "mips/should_compile_stores/default"
+  ));
+}
+
+#[test]
+fn should_compile_function_definitions() {
+  insta::assert_snapshot!(asm_from_code_str(
+    r#"
+    func answer(): i32
+    begin
+      call write_int(42)
+    end
+
+    func add(a: i32 b: i32): i32
+    begin
+      call write_string("add\n")
+    end
+
+    x: i32 = call answer()
+    y: i32 = call add(x 2)
+    "#,
+    "mips/should_compile_function_definitions/default"
+  ));
+}
+
+#[test]
+fn should_compile_builtins() {
+  insta::assert_snapshot!(asm_from_code_str(
+    r#"
+    n: i32 = call read_int()
+    s: str = call read_string(16u32)
+    call write_int(n)
+    call write_int(7)
+    call write_string(s)
+    call write_string("done\n")
+    "#,
+    "mips/should_compile_builtins/default"
+  ));
+}
+
+#[test]
+fn should_reject_unsupported_code() {
+  insta::assert_snapshot!(asm_from_code_str(
+    r#"a: i64 = 1i64"#,
+    "mips/should_reject_unsupported_code/i64"
+  ));
+
+  insta::assert_snapshot!(asm_from_code_str(
+    r#"a: f32 = 1.5"#,
+    "mips/should_reject_unsupported_code/f32"
+  ));
+
+  // Only the condition of an `if` can be `&&` or `||`
+  insta::assert_snapshot!(asm_from_code_str(
+    r#"
+    c: bool = true
+    d: bool = false
+    e: bool = c && d
+    "#,
+    "mips/should_reject_unsupported_code/logical"
+  ));
+}
+
+#[test]
+fn should_report_errors_without_panicking() {
+  let code = |source: &str| {
+    compile_to_asm(source, "mips/should_report_errors").unwrap_err()[0]
+      .code
+      .clone()
+  };
+
+  assert_eq!(code("a: i32 = $"), Some(codes::INVALID_TOKEN.to_string()));
+  assert_eq!(
+    code("a: i32 = b"),
+    Some(codes::UNKNOWN_VARIABLE.to_string())
+  );
+  assert_eq!(
+    code("a: i64 = 1i64"),
+    Some(codes::UNSUPPORTED_TYPE.to_string())
+  );
+}
@@ -0,0 +1,6 @@
+pub mod assembler;
+pub mod image;
+pub mod mips;
+pub mod simulator;
+pub mod target;
+pub mod wasm;
@@ -0,0 +1,195 @@
+use celestial_hub_astrolabe::ast::Program;
+use celestial_hub_compass::{
+  codegen::{
+    mips::simulator::{simulate, Exit, Fault, Report, DEFAULT_MAX_STEPS},
+    target, Artifact,
+  },
+  runtime::Io,
+  utils::statements_from_code_str,
+};
+
+fn program_from_code_str(code: &str, test_name: &str) -> Program {
+  let ast = statements_from_code_str(code, test_name);
+
+  match target::generate("mips", ast).expect("Codegen to not fail in tests") {
+    Artifact::Mips(program) => program,
+    _ => unreachable!(),
+  }
+}
+
+fn program_from_asm_str(code: &str, test_name: &str) -> Program {
+  let lexer = celestial_hub_astrolabe::lexer::Lexer::new(code, test_name);
+  celestial_hub_astrolabe::parser::Parser::new()
+    .parse(lexer)
+    .expect("Assembly to parse in tests")
+}
+
+fn run(program: &Program, input: &str, max_steps: u64) -> (Report, String) {
+  let mut input = input.as_bytes();
+  let mut output = vec![];
+  let report = simulate(program, max_steps, &mut Io::new(&mut input, &mut output)).unwrap();
+
+  (report, String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn should_run_compiled_programs() {
+  let program = program_from_code_str(
+    r#"
+    n: i32 = call read_int()
+    i: i32 = 0
+    loop:
+      i: i32 = i + 1
+      call write_int(i)
+      if i < n goto loop
+    call write_string(" done\n")
+    "#,
+    "simulator/should_run_compiled_programs",
+  );
+
+  let (report, output) = run(&program, "5\n", DEFAULT_MAX_STEPS);
+  assert_eq!(output, "12345 done\n");
+  assert_eq!(report.exit, Exit::Exited(0));
+  assert_eq!(report.instructions, 41);
+  assert_eq!(
+    report.to_string(),
+    "Exited with code 0 after 41 instructions"
+  );
+}
+
+#[test]
+fn should_run_arithmetic_with_literal_lhs() {
+  let program = program_from_code_str(
+    r#"
+    a: i32 = 6
+    b: i32 = 10 - a
+    c: i32 = 12 / b
+    call write_int(c)
+    "#,
+    "simulator/should_run_arithmetic_with_literal_lhs",
+  );
+
+  let (report, output) = run(&program, "", DEFAULT_MAX_STEPS);
+  assert_eq!(output, "3");
+  assert_eq!(report.exit, Exit::Exited(0));
+}
+
+#[test]
+fn should_branch_on_true_variables() {
+  let program = program_from_code_str(
+    r#"
+    n: i32 = call read_int()
+    positive: bool = n > 0
+    if positive goto yes
+    call write_string("no")
+    goto done
+    yes:
+    call write_string("yes")
+    done:
+    "#,
+    "simulator/should_branch_on_true_variables",
+  );
+
+  assert_eq!(run(&program, "0\n", DEFAULT_MAX_STEPS).1, "no");
+  assert_eq!(run(&program, "7\n", DEFAULT_MAX_STEPS).1, "yes");
+}
+
+#[test]
+fn should_return_from_nested_calls() {
+  let program = program_from_code_str(
+    r#"
+    func inner(): i32
+    begin
+      call write_string("inner ")
+    end
+
+    func outer(): i32
+    begin
+      r: i32 = call inner()
+      call write_string("outer ")
+    end
+
+    x: i32 = call outer()
+    call write_int(x)
+    "#,
+    "simulator/should_return_from_nested_calls",
+  );
+
+  let (report, output) = run(&program, "", DEFAULT_MAX_STEPS);
+  assert_eq!(output, "inner outer 0");
+  assert_eq!(report.exit, Exit::Exited(0));
+}
+
+#[test]
+fn should_read_strings_into_variables() {
+  let program = program_from_code_str(
+    r#"
+    name: str = call read_string(8u32)
+    call write_string(name)
+    "#,
+    "simulator/should_read_strings_into_variables",
+  );
+
+  assert_eq!(run(&program, "ada\n", DEFAULT_MAX_STEPS).1, "ada\n");
+}
+
+#[test]
+fn should_report_faults() {
+  let fault = |code: &str| {
+    let program = program_from_asm_str(
+      &format!(".data\n.text\n.global main\nmain:\n{code}"),
+      "simulator/should_report_faults",
+    );
+    match run(&program, "", 100).0.exit {
+      Exit::Faulted { pc, fault } => (pc, fault),
+      exit => panic!("expected a fault, got {exit:?}"),
+    }
+  };
+
+  assert_eq!(
+    fault("li $t0, 8\nlw $t1, 0($t0)\nhalt"),
+    (0x0040_0004, Fault::Unmapped(8))
+  );
+  assert_eq!(
+    fault("li $t0, 0x7fffffff\nadd $t0, $t0, 1\nhalt"),
+    (0x0040_0008, Fault::Overflow)
+  );
+  assert_eq!(
+    fault("li $t0, 1"),
+    (0x0040_0004, Fault::InvalidPc(0x0040_0004))
+  );
+  assert_eq!(
+    fault("loop:\nj loop"),
+    (0x0040_0000, Fault::StepLimitExceeded(100))
+  );
+}
+
+#[test]
+fn should_serve_syscalls() {
+  let program = program_from_asm_str(
+    r#"
+    .data
+    buffer: .space 8
+    .text
+    .global main
+    main:
+      li $v0, 8
+      la $a0, buffer
+      li $a1, 4
+      syscall
+      li $v0, 4
+      syscall
+      li $v0, 11
+      li $a0, 33
+      syscall
+      li $v0, 17
+      li $a0, 3
+      syscall
+    "#,
+    "simulator/should_serve_syscalls",
+  );
+
+  let (report, output) = run(&program, "hello\n", DEFAULT_MAX_STEPS);
+  assert_eq!(output, "hel!");
+  assert_eq!(report.exit, Exit::Exited(3));
+}
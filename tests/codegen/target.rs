@@ -0,0 +1,155 @@
+use celestial_hub_compass::{
+  codegen::{
+    error::CodegenError,
+    target::{self, CallingConvention, TARGETS},
+    Artifact,
+  },
+  compiler::{self, CompileOptions},
+  diagnostics::codes,
+  utils::statements_from_code_str,
+};
+
+fn generate(code: &str, test_name: &str, target: &str) -> Result<Artifact, CodegenError> {
+  let ast = statements_from_code_str(code, test_name);
+
+  target::generate(target, ast)
+}
+
+#[test]
+fn should_find_every_target_by_name() {
+  for expected in TARGETS {
+    let found = target::find(expected.info().name).expect("Target to be registered");
+    assert_eq!(found.info().name, expected.info().name);
+  }
+
+  assert!(target::find("z80").is_none());
+}
+
+#[test]
+fn should_describe_mips() {
+  let info = target::find("mips").unwrap().info();
+
+  assert_eq!(info.word_size, 4);
+  assert_eq!(info.pointer_size, 4);
+  assert!(matches!(
+    info.calling_convention,
+    CallingConvention::Registers {
+      return_value: "$v0",
+      ..
+    }
+  ));
+}
+
+#[test]
+fn should_generate_structured_artifacts() {
+  let code = r#"call write_string("hi\n")"#;
+
+  assert!(matches!(
+    generate(code, "target/mips", "mips"),
+    Ok(Artifact::Mips(_))
+  ));
+  assert!(matches!(
+    generate(code, "target/wat", "wat"),
+    Ok(Artifact::Wat(_))
+  ));
+  assert!(matches!(
+    generate(code, "target/bytecode", "bytecode"),
+    Ok(Artifact::Bytecode(_))
+  ));
+}
+
+#[test]
+fn should_reject_unsupported_types() {
+  let code = "b: i32 = 1\na: f32 = 1.5";
+  let error = generate(code, "target/unsupported", "mips").unwrap_err();
+  assert_eq!(
+    error.to_string(),
+    "Type `f32` is not supported by the `mips` target"
+  );
+  assert_eq!(&code[error.location().unwrap().clone()], "a");
+
+  assert!(generate("a: f32 = 1.5", "target/supported", "wat").is_ok());
+}
+
+#[test]
+fn should_report_unsupported_constructs_at_their_statement() {
+  let code = "ready: bool = true\ndone: bool = false\nboth: bool = ready && done";
+  let error = generate(code, "target/unsupported-construct", "mips").unwrap_err();
+
+  assert!(matches!(
+    error,
+    CodegenError::Unsupported { target: "mips", .. }
+  ));
+  assert_eq!(&code[error.location().unwrap().clone()], "both");
+}
+
+#[test]
+fn should_report_unsupported_argument_types_at_the_argument() {
+  let code = "func half(n: i32 x: f32): i32\nbegin\nend";
+  let error = generate(code, "target/unsupported-argument", "mips").unwrap_err();
+
+  assert_eq!(&code[error.location().unwrap().clone()], "x: f32");
+}
+
+#[test]
+fn should_report_running_out_of_registers_at_the_statement() {
+  let code: String = (0..11).map(|i| format!("v{i}: i32 = {i}\n")).collect();
+  let error = generate(&code, "target/out-of-registers", "mips").unwrap_err();
+
+  assert_eq!(
+    error.to_string(),
+    "Unsupported on the `mips` target: more than 10 variables and temporary values, every `$t` register is taken"
+  );
+  assert_eq!(&code[error.location().unwrap().clone()], "v10");
+}
+
+#[test]
+fn should_report_string_literal_conditions_as_unsupported() {
+  let code = "s: str = \"a\"\nif s == \"b\" goto done\ndone:";
+  let error = generate(code, "target/string-condition", "mips").unwrap_err();
+
+  assert_eq!(
+    error.to_string(),
+    "Unsupported on the `mips` target: comparisons with string literals"
+  );
+  assert_eq!(
+    &code[error.location().unwrap().clone()],
+    "s == \"b\" goto done"
+  );
+}
+
+#[test]
+fn should_reject_the_labels_the_backend_generates() {
+  // The assembler would find them defined twice
+  for (code, label) in [
+    ("func f()\nbegin\nend\n__f:\ncall f()", "__f"),
+    ("str_0:\ncall write_string(\"a\")\ngoto str_0", "str_0"),
+  ] {
+    let error = generate(code, "target/generated-label", "mips").unwrap_err();
+
+    assert_eq!(
+      error.to_string(),
+      format!("Cannot use '{label}' as a label name, the backend generates it")
+    );
+    assert_eq!(&code[error.location().unwrap().clone()], label);
+  }
+}
+
+#[test]
+fn should_reject_missing_labels_before_any_backend() {
+  let code = "_a: i32 = 1\ngoto lopp\nloop:";
+
+  for target in TARGETS {
+    let options = CompileOptions::target(target.info().name);
+    let output = compiler::compile(code, "target/missing-label", &options);
+    assert!(output.artifact.is_none());
+    assert_eq!(
+      output.diagnostics[0].code.as_deref(),
+      Some(codes::UNKNOWN_LABEL)
+    );
+    assert_eq!(
+      &code[output.diagnostics[0].spans[0].location.clone()],
+      "lopp"
+    );
+  }
+}
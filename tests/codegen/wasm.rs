@@ -0,0 +1,153 @@
+use celestial_hub_compass::{
+  codegen::{wasm::WatCodegen, Codegen},
+  utils::statements_from_code_str,
+};
+
+fn wat_from_code_str(code: &str, test_name: &str) -> String {
+  let ast = statements_from_code_str(code, test_name);
+
+  WatCodegen
+    .generate(ast, &mut Default::default())
+    .expect("Codegen to not fail in tests")
+    .to_string()
+}
+
+fn assert_valid(wat: &str) {
+  let binary = wat::parse_str(wat).unwrap_or_else(|err| panic!("{err}\n{wat}"));
+  wasmparser::validate(&binary).unwrap_or_else(|err| panic!("{err}\n{wat}"));
+}
+
+#[test]
+fn should_emit_loop() {
+  let wat = wat_from_code_str(
+    r#"
+    i: i32 = 0
+    loop:
+      call write_int(i)
+      i: i32 = i + 1
+      if i < 10 goto loop
+    call write_string("done\n")
+    "#,
+    "wasm/should_emit_loop/default",
+  );
+
+  assert_valid(&wat);
+  insta::assert_snapshot!(wat);
+}
+
+#[test]
+fn should_emit_nested_conditionals() {
+  let wat = wat_from_code_str(
+    r#"
+    a: i32 = call read_int()
+    b: i32 = call read_int()
+    if a < b goto less
+    if a == b goto equal
+    call write_string("greater")
+    goto done
+    less:
+      call write_string("less")
+      goto done
+    equal:
+      call write_string("equal")
+    done:
+    "#,
+    "wasm/should_emit_nested_conditionals/default",
+  );
+
+  assert_valid(&wat);
+}
+
+#[test]
+fn should_emit_irreducible_control_flow() {
+  let wat = wat_from_code_str(
+    r#"
+    flag: bool = true
+    x: i32 = 0
+    if flag goto second
+    first:
+      x: i32 = x + 1
+      if flag goto second
+      goto done
+    second:
+      x: i32 = x + 2
+      if flag goto first
+    done:
+    "#,
+    "wasm/should_emit_irreducible_control_flow/default",
+  );
+
+  assert_valid(&wat);
+  assert!(wat.contains("br_table"));
+}
+
+#[test]
+fn should_emit_every_type() {
+  let wat = wat_from_code_str(
+    r#"
+    a: i8 = 100i8 + 100i8
+    b: u16 = 3u16 / 2u16
+    c: i64 = 1i64 - 2i64
+    d: u64 = 18446744073709551615u64 * 1u64
+    e: f32 = 1.5 / .5
+    f: f64 = 2.f64 + 1f64
+    g: bool = e < 2.
+    h: bool = c >= -1i64
+    i: bool = g && h
+    j: bool = d != 0u64
+    k: str = call read_string(16u32)
+    l: u8 = 255u8 + 1u8
+    "#,
+    "wasm/should_emit_every_type/default",
+  );
+
+  assert_valid(&wat);
+}
+
+#[test]
+fn should_read_redeclared_variables_from_their_last_global() {
+  let wat = wat_from_code_str(
+    r#"
+    x: i32 = 1
+    x: f64 = 2.5f64
+    y: i32 = x + 1
+    call write_int(y)
+    "#,
+    "wasm/should_read_redeclared_variables_from_their_last_global/default",
+  );
+
+  assert_valid(&wat);
+  assert!(wat.contains("global.get $x@f64\n    i32.trunc_sat_f64_s"));
+}
+
+#[test]
+fn should_emit_functions() {
+  let wat = wat_from_code_str(
+    r#"
+    func greet()
+    begin
+      call write_string("hello")
+    end
+
+    func answer(x: i32): i32
+    begin
+      a: i32 = 42
+    end
+
+    call greet()
+    value: i32 = call answer(1)
+    call write_int(value)
+    "#,
+    "wasm/should_emit_functions/default",
+  );
+
+  assert_valid(&wat);
+}
+
+#[test]
+fn should_emit_assets() {
+  for asset in ["conditional.etac", "floats.etac", "test.etac", "vars.etac"] {
+    let code = std::fs::read_to_string(format!("assets/{asset}")).unwrap();
+    assert_valid(&wat_from_code_str(&code, asset));
+  }
+}
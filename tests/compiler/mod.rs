@@ -0,0 +1,121 @@
+use celestial_hub_compass::{
+  codegen::Artifact,
+  compiler::{compile, CompileOptions},
+  diagnostics::codes,
+  lints,
+};
+
+#[test]
+fn should_compile_to_an_artifact() {
+  let output = compile(
+    r#"call write_string("hi\n")"#,
+    "compiler/ok.etac",
+    &CompileOptions::target("wat"),
+  );
+
+  assert!(!output.has_errors());
+  assert!(output.diagnostics.is_empty());
+  assert_eq!(output.ast.map(|ast| ast.len()), Some(1));
+  assert!(matches!(output.artifact, Some(Artifact::Wat(_))));
+}
+
+#[test]
+fn should_only_check_without_a_target() {
+  let output = compile("_a: i32 = 1", "compiler/check.etac", &Default::default());
+
+  assert!(output.ast.is_some());
+  assert!(output.artifact.is_none());
+  assert!(output.diagnostics.is_empty());
+}
+
+#[test]
+fn should_return_every_error_of_the_front_end() {
+  let output = compile(
+    "x: i32 = 1\n_y: bool = x\n_z: i32 = w + 1\n",
+    "compiler/errors.etac",
+    &CompileOptions::target("mips"),
+  );
+
+  let codes: Vec<_> = output
+    .diagnostics
+    .iter()
+    .map(|diagnostic| diagnostic.code.as_deref())
+    .collect();
+  assert_eq!(
+    codes,
+    [Some(codes::MISMATCHED_TYPES), Some(codes::UNKNOWN_VARIABLE)]
+  );
+  assert_eq!(output.errors(), 2);
+  assert!(output.ast.is_none());
+  assert!(output.artifact.is_none());
+}
+
+#[test]
+fn should_return_invalid_tokens() {
+  let output = compile(
+    "_a: i32 = 1 $ 2",
+    "compiler/invalid.etac",
+    &Default::default(),
+  );
+
+  assert_eq!(output.diagnostics.len(), 1);
+  assert_eq!(
+    output.diagnostics[0].code.as_deref(),
+    Some(codes::INVALID_TOKEN)
+  );
+  assert_eq!(output.diagnostics[0].spans[0].location, 12..13);
+}
+
+#[test]
+fn should_return_the_error_of_the_backend() {
+  let output = compile(
+    "_a: f32 = 1.5",
+    "compiler/backend.etac",
+    &CompileOptions::target("mips"),
+  );
+
+  assert!(output.has_errors());
+  assert!(output.ast.is_some());
+  assert!(output.artifact.is_none());
+  assert_eq!(
+    output.diagnostics[0].code.as_deref(),
+    Some(codes::UNSUPPORTED_TYPE)
+  );
+}
+
+#[test]
+fn should_report_unknown_targets() {
+  let output = compile(
+    "_a: i32 = 1",
+    "compiler/target.etac",
+    &CompileOptions::target("z80"),
+  );
+
+  assert_eq!(output.diagnostics[0].message, "Unknown target `z80`");
+}
+
+#[test]
+fn should_lint_what_parses_next_to_the_errors() {
+  let output = compile(
+    "_a: i32 = 1 $ 2\nb: i32 = 3\n_c: bool = b\nunused: i32 = 4\n",
+    "compiler/lints.etac",
+    &CompileOptions::target("mips"),
+  );
+
+  let codes: Vec<_> = output
+    .diagnostics
+    .iter()
+    .map(|diagnostic| diagnostic.code.as_deref())
+    .collect();
+  assert_eq!(
+    codes,
+    [
+      Some(codes::INVALID_TOKEN),
+      Some(codes::MISMATCHED_TYPES),
+      Some(lints::UNUSED_VARIABLE.name),
+    ]
+  );
+  assert_eq!(output.errors(), 2);
+  assert!(output.ast.is_none());
+  assert!(output.artifact.is_none());
+}
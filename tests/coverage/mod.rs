@@ -0,0 +1,84 @@
+use celestial_hub_compass::{
+  coverage::Coverage, interpreter::Interpreter, runtime::Io, utils::statements_from_code_str,
+};
+
+const PROGRAM: &str = "func unused(): i32
+begin
+  call write_int(0)
+end
+
+n: i32 = call read_int()
+if n > 0 goto positive
+call write_string(\"negative\")
+goto done
+positive:
+call write_string(\"positive\")
+done:
+";
+
+fn coverage(source: &str, inputs: &[&str]) -> Coverage {
+  let ast = statements_from_code_str(source, "coverage");
+
+  let mut coverage = Coverage::new(&ast);
+  for input in inputs {
+    let mut output = vec![];
+    Interpreter::new(&ast)
+      .run_observed(
+        &mut Io::new(&mut input.as_bytes(), &mut output),
+        &mut coverage,
+      )
+      .unwrap();
+  }
+
+  coverage
+}
+
+#[test]
+fn should_export_lcov() {
+  assert_eq!(
+    coverage(PROGRAM, &["5\n"]).lcov(PROGRAM, "sum.etac"),
+    "TN:
+SF:sum.etac
+FN:6,main
+FN:1,unused
+FNDA:1,main
+FNDA:0,unused
+FNF:2
+FNH:1
+BRDA:7,0,0,1
+BRDA:7,0,1,0
+BRF:2
+BRH:1
+DA:3,0
+DA:6,1
+DA:7,1
+DA:8,0
+DA:9,0
+DA:10,1
+DA:11,1
+DA:12,1
+LF:8
+LH:5
+end_of_record
+"
+  );
+}
+
+#[test]
+fn should_add_up_runs() {
+  let coverage = coverage(PROGRAM, &["5\n", "-5\n", "7\n"]);
+
+  assert_eq!(coverage.functions[0].calls, 3);
+  assert_eq!(coverage.functions[1].calls, 0);
+  assert_eq!(
+    (coverage.branches[0].taken, coverage.branches[0].not_taken),
+    (2, 1)
+  );
+  // Only the body of `unused` never ran
+  let missed: Vec<_> = coverage
+    .statements
+    .iter()
+    .filter(|statement| statement.hits == 0)
+    .collect();
+  assert_eq!(missed.len(), 1);
+}
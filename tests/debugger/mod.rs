@@ -0,0 +1,110 @@
+use celestial_hub_compass::debugger::debug;
+
+const PROGRAM: &str = "func greet(): i32
+begin
+  g: i32 = 5
+  call write_int(g)
+end
+
+x: i32 = 1
+y: i32 = call greet()
+loop:
+  x: i32 = x + 1
+  if x < 3 goto loop
+call write_int(x)
+";
+
+fn session(source: &str, commands: &str) -> String {
+  let mut output = vec![];
+  debug(
+    source,
+    &mut commands.as_bytes(),
+    &mut "".as_bytes(),
+    &mut output,
+  )
+  .unwrap();
+  String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn should_stop_at_breakpoints() {
+  assert_eq!(
+    session(
+      PROGRAM,
+      "break 3\nbreak loop\nbreak nowhere\ncontinue\nbacktrace\nprint\nfinish\n\n\ndelete loop\ncontinue\n"
+    ),
+    "main:7  x: i32 = 1
+(debug) Breakpoint 1 at line 3
+(debug) Breakpoint 2 at label `loop`
+(debug) There is no label `nowhere`, breakpoints take a line number or a label
+(debug) Breakpoint 1 at line 3
+greet:3  g: i32 = 5
+(debug) #0 greet at line 3
+#1 main at line 8
+(debug) x: i32 = 1
+(debug) 5
+Breakpoint 2 at label `loop`
+main:9  loop:
+(debug) `finish` needs a function to return from
+(debug) `finish` needs a function to return from
+(debug) Deleted the breakpoint at label `loop`
+(debug) 3
+Program finished
+"
+  );
+}
+
+#[test]
+fn should_step_into_or_over_calls() {
+  assert_eq!(
+    session(PROGRAM, "next\nstep\nstep\nbacktrace\nnext\nnext\nquit\n"),
+    "main:7  x: i32 = 1
+(debug) main:8  y: i32 = call greet()
+(debug) greet:3  g: i32 = 5
+(debug) greet:4  call write_int(g)
+(debug) #0 greet at line 4
+#1 main at line 8
+(debug) 5
+main:9  loop:
+(debug) main:10  x: i32 = x + 1
+(debug) "
+  );
+}
+
+#[test]
+fn should_show_watches_with_their_types() {
+  assert_eq!(
+    session(
+      PROGRAM,
+      "watch x + 1\nbreak 10\ncontinue\nprint x > 1\nprint x + 1.5\ncontinue\nprint y\nquit\n"
+    ),
+    "main:7  x: i32 = 1
+(debug) x + 1: unknown variable `x`
+(debug) Breakpoint 1 at line 10
+(debug) 5
+Breakpoint 1 at line 10
+main:10  x: i32 = x + 1
+  x + 1: i32 = 2
+(debug) x > 1: bool = false
+(debug) error: expected `i32`
+  x + 1.5
+  ^
+error: found `f32`
+  x + 1.5
+      ^^^
+help: Cannot perform the operation `+` with `i32` and `f32`, you can either try to cast the value to `i32` or change the variable type to `f32`
+(debug) Breakpoint 1 at line 10
+main:10  x: i32 = x + 1
+  x + 1: i32 = 3
+(debug) y: i32 = 0
+(debug) "
+  );
+}
+
+#[test]
+fn should_report_where_the_program_failed() {
+  assert_eq!(
+    session("a: i32 = 0\n\nb: i32 = 1 / a\n", "continue\n"),
+    "main:1  a: i32 = 0\n(debug) Program failed at line 3: division by zero\n"
+  );
+}
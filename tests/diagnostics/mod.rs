@@ -0,0 +1,307 @@
+use celestial_hub_compass::{
+  ast::context::Context,
+  compiler::{self, CompileOptions},
+  diagnostics::{
+    check,
+    codes::{explain, CODES},
+    render, sarif,
+    suggestions::{did_you_mean, distance, similar},
+    Diagnostic, Format, Severity,
+  },
+  lexer::Lexer,
+  parser::parse_program,
+};
+
+const PROGRAM: &str = "x: i32 = 1
+y: bool = x
+z: i32 = w + 1
+";
+
+// The errors of a source, without the lint warnings reported next to them
+fn errors(source: &str, filename: &str) -> Vec<Diagnostic> {
+  check(source, filename)
+    .into_iter()
+    .filter(|diagnostic| diagnostic.severity == Severity::Error)
+    .collect()
+}
+
+#[test]
+fn reports_every_error_with_its_spans() {
+  let diagnostics = errors(PROGRAM, "bad.etac");
+
+  assert_eq!(diagnostics.len(), 2);
+  assert_eq!(diagnostics[0].message, "Mismatched types");
+  assert_eq!(diagnostics[0].severity, Severity::Error);
+  assert_eq!(diagnostics[0].spans.len(), 2);
+  assert!(diagnostics[0].help.is_some());
+  assert_eq!(diagnostics[1].message, "Unknown variable");
+
+  let json = diagnostics[0].to_json(PROGRAM, "bad.etac");
+  assert_eq!(json["code"], "E0004");
+  assert_eq!(json["spans"][0]["primary"], true);
+  assert_eq!(json["spans"][0]["line_start"], 2);
+  assert_eq!(json["spans"][0]["column_start"], 4);
+  assert_eq!(json["spans"][0]["label"], "expected `bool`");
+  assert_eq!(json["spans"][1]["primary"], false);
+  assert_eq!(json["spans"][1]["column_start"], 11);
+}
+
+#[test]
+fn writes_one_json_object_per_line() {
+  let source = "a: i32 = 1\nb: u8 = @\n";
+  let diagnostics = errors(source, "tok.etac");
+  let output = render(Format::Json, &diagnostics, source, "tok.etac");
+
+  let lines: Vec<serde_json::Value> = output
+    .lines()
+    .map(|line| serde_json::from_str(line).unwrap())
+    .collect();
+  assert_eq!(lines.len(), 1);
+  assert_eq!(lines[0]["message"], "Invalid token");
+  assert_eq!(lines[0]["file"], "tok.etac");
+  assert_eq!(lines[0]["spans"][0]["byte_start"], 19);
+}
+
+#[test]
+fn writes_a_sarif_log() {
+  let mut diagnostics = errors(PROGRAM, "bad.etac");
+  diagnostics.push(Diagnostic::error("Type `i64` is not supported"));
+  let log = sarif(&diagnostics, PROGRAM, "bad.etac");
+
+  assert_eq!(log["version"], "2.1.0");
+  let run = &log["runs"][0];
+  assert_eq!(run["tool"]["driver"]["name"], "compass");
+  assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "E0004");
+  assert_eq!(run["tool"]["driver"]["rules"][1]["id"], "E0005");
+
+  let results = run["results"].as_array().unwrap();
+  assert_eq!(results.len(), 3);
+  assert_eq!(results[0]["ruleId"], "E0004");
+  assert_eq!(results[0]["level"], "error");
+  let region = &results[0]["locations"][0]["physicalLocation"]["region"];
+  assert_eq!(region["startLine"], 2);
+  assert_eq!(
+    results[0]["relatedLocations"][0]["message"]["text"],
+    "found variable x which is `i32`"
+  );
+  assert!(results[2].get("ruleId").is_none());
+  assert_eq!(
+    results[2]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+    "bad.etac"
+  );
+}
+
+// The ETAC code blocks of an explanation
+fn examples(explanation: &str) -> Vec<String> {
+  explanation
+    .split("```etac\n")
+    .skip(1)
+    .map(|block| block.split("```").next().unwrap().to_string())
+    .collect()
+}
+
+// The diagnostics of a source, of the front end and of the mips backend
+fn compile(source: &str, filepath: &str) -> Vec<Diagnostic> {
+  compiler::compile(source, filepath, &CompileOptions::target("mips")).diagnostics
+}
+
+#[test]
+fn explanations_show_a_wrong_and_a_corrected_example() {
+  for (code, explanation) in CODES {
+    let examples = examples(explanation);
+    assert_eq!(examples.len(), 2, "{code} has two examples");
+
+    let wrong = compile(&examples[0], "wrong.etac");
+    assert!(
+      wrong
+        .iter()
+        .any(|diagnostic| diagnostic.code.as_deref() == Some(*code)),
+      "the wrong example of {code} reports it, not {wrong:?}"
+    );
+    let corrected = compile(&examples[1], "corrected.etac");
+    assert!(
+      corrected
+        .iter()
+        .all(|diagnostic| diagnostic.severity != Severity::Error),
+      "the corrected example of {code} compiles, not {corrected:?}"
+    );
+  }
+}
+
+#[test]
+fn finds_explanations_by_code() {
+  assert_eq!(explain("E0004"), explain("4"));
+  assert_eq!(explain("e0004"), explain("E0004"));
+  assert!(explain("E0004")
+    .unwrap()
+    .starts_with("A value has a different type"));
+  assert_eq!(explain("E9999"), None);
+  assert_eq!(explain("unknown"), None);
+}
+
+#[test]
+fn suggests_the_closest_names() {
+  assert_eq!(distance("cont", "count"), 1);
+  assert_eq!(distance("write_it", "write_int"), 1);
+  assert_eq!(distance("", "abc"), 3);
+
+  assert_eq!(
+    similar("cont", ["count", "total", "cont2"]),
+    ["cont2", "count"]
+  );
+  assert_eq!(similar("x", ["y", "xs"]), ["xs", "y"]);
+  assert_eq!(similar("count", ["total"]), Vec::<&str>::new());
+
+  let (help, fix) = did_you_mean("cont", ["count", "total"], 3..7);
+  assert_eq!(help.as_deref(), Some("Did you mean `count`?"));
+  assert_eq!(
+    fix.map(|fix| (fix.location, fix.replacement)),
+    Some((3..7, "count".to_string()))
+  );
+
+  let (help, fix) = did_you_mean("x", ["y", "xs"], 0..1);
+  assert_eq!(help.as_deref(), Some("Did you mean one of `xs`, `y`?"));
+  assert_eq!(fix, None);
+}
+
+#[test]
+fn attaches_fix_its_to_misspelt_names() {
+  let source = "count: i32 = 1\ntotal: i32 = cont + 1\ncall write_it(total)\n";
+  let diagnostics = errors(source, "typo.etac");
+
+  assert_eq!(diagnostics.len(), 2);
+  assert_eq!(
+    diagnostics[0].help.as_deref(),
+    Some("Did you mean `count`?")
+  );
+  let json = diagnostics[0].to_json(source, "typo.etac");
+  assert_eq!(json["fix"]["replacement"], "count");
+  assert_eq!(json["fix"]["byte_start"], 28);
+  assert_eq!(json["fix"]["column_start"], 14);
+
+  let log = sarif(&diagnostics, source, "typo.etac");
+  let replacement =
+    &log["runs"][0]["results"][1]["fixes"][0]["artifactChanges"][0]["replacements"][0];
+  assert_eq!(replacement["insertedContent"]["text"], "write_int");
+  assert_eq!(replacement["deletedRegion"]["startLine"], 3);
+  assert_eq!(replacement["deletedRegion"]["startColumn"], 6);
+}
+
+#[test]
+fn attaches_fix_its_to_misspelt_types_and_labels() {
+  assert_eq!(
+    literal_error("a: i33 = 1"),
+    (
+      "E0017".into(),
+      "`i33` is not a type".into(),
+      "Did you mean `i32`?".into(),
+      Some("i32".into())
+    )
+  );
+  // Arguments and returns too
+  assert_eq!(literal_error("func f(n: i23)\nbegin\nend\n").0, "E0017");
+  assert_eq!(literal_error("func f(): boool\nbegin\nend\n").0, "E0017");
+  assert_eq!(
+    literal_error("loop:\ngoto lopp\n"),
+    (
+      "E0018".into(),
+      "unknown label `lopp`".into(),
+      "Did you mean `loop`?".into(),
+      Some("loop".into())
+    )
+  );
+
+  // Labels belong to their function
+  assert_eq!(
+    literal_error("done:\nfunc f()\nbegin\n  goto done\nend\n").2,
+    "Define it with `done:`, in the same function as the jump"
+  );
+}
+
+// The code, label, help and fix of the only error of a source
+fn literal_error(source: &str) -> (String, String, String, Option<String>) {
+  let diagnostics = errors(source, "literal.etac");
+  assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+
+  let diagnostic = &diagnostics[0];
+  (
+    diagnostic.code.clone().unwrap(),
+    diagnostic.spans[0].label.clone(),
+    diagnostic.help.clone().unwrap(),
+    diagnostic.fix.as_ref().map(|fix| fix.replacement.clone()),
+  )
+}
+
+#[test]
+fn reports_malformed_literals() {
+  assert_eq!(
+    literal_error("a: u8 = 300u8"),
+    (
+      "E0013".into(),
+      "`300u8` does not fit in `u8`".into(),
+      "`u8` holds values from 0 to 255, write `300u16` to use `u16` instead".into(),
+      Some("300u16".into())
+    )
+  );
+  assert_eq!(
+    literal_error("a: i64 = 99999999999999999999i64").2,
+    "`i64` holds values from -9223372036854775808 to 9223372036854775807"
+  );
+  assert_eq!(
+    literal_error("call write_string(\"hi)"),
+    (
+      "E0014".into(),
+      "this string is never closed".into(),
+      "Close it with a `\"`, strings cannot contain one".into(),
+      None
+    )
+  );
+  assert_eq!(
+    literal_error("a: i32 = 2 × 3"),
+    (
+      "E0015".into(),
+      "`×` is not ASCII".into(),
+      "Did you mean `*`?".into(),
+      Some("*".into())
+    )
+  );
+  assert_eq!(
+    literal_error("a: u16 = 300u7"),
+    (
+      "E0016".into(),
+      "`u7` is not a type".into(),
+      "Did you mean `u8`?".into(),
+      Some("u8".into())
+    )
+  );
+  assert_eq!(literal_error("a: f32 = 1.5f16").1, "`f16` is not a type");
+  assert_eq!(
+    literal_error("a: i32 = 0xZZ"),
+    (
+      "E0001".into(),
+      "`0xZZ` is not a valid literal".into(),
+      "Write hexadecimal digits after `0x`, such as `0xff`".into(),
+      None
+    )
+  );
+  assert_eq!(
+    literal_error("a: i32 = 0b101"),
+    (
+      "E0001".into(),
+      "`0b101` is not a valid literal".into(),
+      "Only hexadecimal literals have a prefix, write `5` instead".into(),
+      Some("5".into())
+    )
+  );
+}
+
+#[test]
+fn parses_invalid_tokens_without_panicking() {
+  let source = "a: u8 = 300u8\nb: i32 = 1";
+  let lexer = Lexer::unchecked(source, "literal.etac");
+  let errors = parse_program(&mut Context::new(0), lexer).unwrap_err();
+
+  let diagnostic = Diagnostic::from(&errors[0]);
+  assert_eq!(diagnostic.message, "Integer literal out of range");
+  assert_eq!(diagnostic.spans[0].location, 8..13);
+}
@@ -0,0 +1,157 @@
+use celestial_hub_compass::{
+  codegen::mips::{target, MipsCodegen},
+  compiler::{self, CompileOptions},
+  differential::{check, interpret, reduce, Verdict},
+  generator::{generate_for, Config},
+};
+
+const INPUT: &str = "7\nabc\n";
+
+fn assert_agree(code: &str) {
+  match check(code, INPUT) {
+    Verdict::Agree => {}
+    verdict => panic!("expected the engines to agree on\n{code}\ngot {verdict:?}"),
+  }
+}
+
+#[test]
+fn should_agree_on_assets() {
+  let mut agreed = 0;
+
+  for entry in std::fs::read_dir("assets").unwrap() {
+    let path = entry.unwrap().path();
+    let code = std::fs::read_to_string(&path).unwrap();
+
+    // Only the assets that are valid programs with types the target supports are compared
+    let output = compiler::compile(&code, "differential", &CompileOptions::default());
+    match &output.ast {
+      Some(ast) if !output.has_errors() && target::INFO.check(ast).is_ok() => {}
+      _ => continue,
+    }
+
+    match check(&code, INPUT) {
+      Verdict::Agree => agreed += 1,
+      Verdict::Skipped(reason) => panic!("{path:?} was skipped: {reason}"),
+      Verdict::Mismatch(mismatch) => panic!("{path:?} runs differently:\n{mismatch}"),
+    }
+  }
+
+  assert!(agreed >= 3, "only {agreed} assets could be compared");
+}
+
+#[test]
+fn should_agree_on_calls_and_jumps() {
+  assert_agree(
+    r#"
+    a: i32 = 3
+    b: i32 = 5
+    flag: bool = a < b
+    if flag goto skip
+    call write_int(a)
+    skip:
+    call write_int(b)
+    "#,
+  );
+
+  assert_agree(
+    r#"
+    func f(): i32
+    begin
+      x: i32 = 2
+      call write_int(x)
+    end
+
+    func g(): i32
+    begin
+      y: i32 = call f()
+    end
+
+    z: i32 = call g()
+    call write_int(z)
+    "#,
+  );
+
+  assert_agree(
+    r#"
+    s: str = call read_string(8u32)
+    call write_string(s)
+    "#,
+  );
+}
+
+#[test]
+fn should_agree_on_generated_programs() {
+  let config = Config::for_target(&target::INFO);
+
+  for seed in 0..64 {
+    let (_, program) = generate_for(&MipsCodegen, seed, &config).unwrap();
+    assert_agree(&program.source);
+  }
+}
+
+#[test]
+fn should_only_skip_what_the_target_rejects_up_front() {
+  assert!(matches!(
+    check("x: f32 = 1.5\ncall write_int(1)\n", INPUT),
+    Verdict::Skipped(_)
+  ));
+
+  // `str` is supported, but comparing it with a literal is not
+  let code = "s: str = \"a\"\nif s == \"b\" goto done\ncall write_string(s)\ndone:\n";
+  match check(code, INPUT) {
+    Verdict::Mismatch(mismatch) => {
+      assert!(
+        mismatch.differences[0].starts_with("mips could not run it"),
+        "{mismatch}"
+      );
+    }
+    verdict => panic!("expected a mismatch, got {verdict:?}"),
+  }
+}
+
+#[test]
+fn should_agree_on_narrow_integers() {
+  assert_agree(
+    r#"
+    a: u8 = 250u8
+    b: u8 = a + 10u8
+    c: u16 = 65000u16
+    d: u16 = c * 2u16
+    e: i8 = 120i8
+    f: i8 = e + 10i8
+    g: i16 = -32000i16
+    h: i16 = g - 1000i16
+    call write_int(1)
+    "#,
+  );
+}
+
+#[test]
+fn should_minimize_mismatches() {
+  // An engine that disagrees on every program computing `y`, which needs the `x` it reads
+  let diverges = |candidate: &str| {
+    let output = compiler::compile(candidate, "minimize", &CompileOptions::default());
+    match (output.has_errors(), output.ast) {
+      (false, Some(ast)) => {
+        interpret(&ast, INPUT).is_ok_and(|outcome| outcome.variables.contains_key("y"))
+      }
+      _ => false,
+    }
+  };
+
+  let code = r#"
+    x: u8 = 250u8
+    w: i32 = 1
+    call write_int(w)
+    y: u8 = x + 10u8
+    z: i32 = w + w
+  "#;
+
+  assert_eq!(
+    reduce(code, diverges)
+      .lines()
+      .map(str::trim)
+      .collect::<Vec<_>>(),
+    vec!["x: u8 = 250u8", "y: u8 = x + 10u8"]
+  );
+}
@@ -0,0 +1,63 @@
+use celestial_hub_compass::{formatter::format, generator::generate};
+
+#[test]
+fn should_align_labels_and_comments() {
+  let source = std::fs::read_to_string("assets/fibonacci.tac").unwrap();
+
+  assert_eq!(
+    format(&source).unwrap(),
+    r#"# Initialize variables
+t0 = 0  # t0 stores 'a', initialize to 0
+t1 = 1  # t1 stores 'b', initialize to 1
+t2 = 0  # t2 stores 'i', initialize to 0
+t3 = 0  # t3 stores 'result', initialize to 0
+t4 = n  # t4 stores 'n', get value from argument
+
+# Start of loop
+loop:
+  if t2 >= t4 goto end  # If 'i' >= 'n', exit loop
+  t3 = t0               # 'result' = 'a'
+  t5 = t0 + t1          # Compute next Fibonacci number
+  t0 = t1               # Shift 'a'
+  t1 = t5               # Shift 'b'
+  t6 = t2 + 1           # Increment loop counter
+  t2 = t6               # Update 'i'
+  goto loop
+# End of loop
+
+end:  # 'result' is in t3
+"#
+  );
+}
+
+#[test]
+fn should_indent_function_bodies() {
+  let source = "\n\n\tfunc  answer( n :i32 ):i32\nbegin\n\t\tx: i32=call read_int( )   # read it\n      done:\n\t# nothing left\nend\n\n\n\ncall write_int( 42 )\n\n";
+
+  assert_eq!(
+    format(source).unwrap(),
+    "func answer(n: i32): i32\nbegin\n  x: i32 = call read_int()  # read it\n  done:\n    # nothing left\nend\n\ncall write_int(42)\n"
+  );
+}
+
+#[test]
+fn should_be_idempotent() {
+  let mut sources: Vec<String> = std::fs::read_dir("assets")
+    .unwrap()
+    .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+    .collect();
+  sources.extend((0..20).map(|seed| generate(seed).source));
+
+  for source in sources {
+    let formatted = format(&source).unwrap();
+    assert_eq!(format(&formatted).unwrap(), formatted);
+  }
+}
+
+#[test]
+fn should_reject_invalid_tokens() {
+  assert_eq!(
+    format("a: i32 = $").unwrap_err(),
+    "Invalid token `$` at 9..10"
+  );
+}
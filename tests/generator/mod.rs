@@ -0,0 +1,141 @@
+use celestial_hub_compass::{
+  ast::{Statement, VarType},
+  codegen::{
+    mips::simulator::{simulate, Exit, DEFAULT_MAX_STEPS},
+    target::{self, TARGETS},
+    Artifact,
+  },
+  diagnostics,
+  generator::{generate, generate_for, Config, Generator, TYPES},
+  interpreter::Interpreter,
+  runtime::Io,
+  utils::parse_code_str,
+};
+
+fn parse(code: &str, seed: u64) -> Vec<Statement> {
+  let name = format!("generator/{seed}");
+  parse_code_str(code, &name).unwrap_or_else(|errors| {
+    panic!(
+      "seed {seed} generated an invalid program:\n{}\n{code}",
+      diagnostics::plain(code, &errors)
+    )
+  })
+}
+
+#[test]
+fn should_generate_the_same_program_for_a_seed() {
+  assert_eq!(generate(7), generate(7));
+  assert_ne!(generate(7).source, generate(8).source);
+}
+
+#[test]
+fn should_generate_terminating_programs() {
+  for seed in 0..100 {
+    let program = generate(seed);
+    let ast = parse(&program.source, seed);
+
+    let mut interpreter = Interpreter::new(&ast);
+    interpreter.max_steps = program.max_steps;
+
+    let mut output = vec![];
+    interpreter
+      .run(&mut Io::new(&mut "42\nhello\n".as_bytes(), &mut output))
+      .unwrap_or_else(|error| panic!("seed {seed} failed: {error}\n{}", program.source));
+    assert!(interpreter.steps <= program.max_steps);
+  }
+}
+
+#[test]
+fn should_cover_the_language() {
+  let source: String = (0..20).map(|seed| generate(seed).source).collect();
+
+  for var_type in TYPES {
+    assert!(
+      source.contains(&format!(": {var_type} = ")),
+      "no `{var_type}`"
+    );
+  }
+
+  for pattern in [
+    " + ",
+    " - ",
+    " * ",
+    " / ",
+    " < ",
+    " > ",
+    " <= ",
+    " >= ",
+    " == ",
+    " != ",
+    " && ",
+    " || ",
+    "goto ",
+    "if true goto",
+    "if false goto",
+    "func ",
+    "call read_int()",
+    "call read_string(",
+    "call write_int(",
+    "call write_string(",
+    "0x",
+  ] {
+    assert!(source.contains(pattern), "no `{pattern}`");
+  }
+}
+
+#[test]
+fn should_only_use_the_configured_types() {
+  let config = Config {
+    types: vec![VarType::Bool],
+    ..Config::default()
+  };
+  let program = Generator::new(3, config).program();
+
+  // Loop counters are always `i32`
+  for var_type in TYPES
+    .into_iter()
+    .filter(|var_type| !matches!(var_type, VarType::Bool | VarType::I32))
+  {
+    assert!(!program.source.contains(&format!(": {var_type}")));
+  }
+}
+
+#[test]
+fn should_compile_generated_programs() {
+  for seed in 0..30 {
+    let program = generate(seed);
+
+    for name in ["bytecode", "wat"] {
+      target::generate(name, parse(&program.source, seed))
+        .unwrap_or_else(|error| panic!("seed {seed} does not compile for {name}: {error}"));
+    }
+  }
+}
+
+#[test]
+fn should_generate_programs_each_target_compiles() {
+  for target in TARGETS {
+    let name = target.info().name;
+    let config = Config::for_target(target.info());
+
+    for seed in 0..20 {
+      let (used, program) = generate_for(target, seed, &config)
+        .unwrap_or_else(|| panic!("no program from seed {seed} for {name}"));
+      // The configuration alone keeps most programs within the target
+      assert!(used < seed + 3, "{name} skipped seeds {seed} to {used}");
+
+      let artifact = target::generate(name, parse(&program.source, used))
+        .unwrap_or_else(|error| panic!("seed {used} does not compile for {name}: {error}"));
+      if let Artifact::Mips(program) = artifact {
+        let mut output = vec![];
+        let report = simulate(
+          &program,
+          DEFAULT_MAX_STEPS,
+          &mut Io::new(&mut "42\nhello\n".as_bytes(), &mut output),
+        )
+        .unwrap();
+        assert_eq!(report.exit, Exit::Exited(0), "seed {used} on {name}");
+      }
+    }
+  }
+}
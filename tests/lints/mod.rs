@@ -0,0 +1,158 @@
+use celestial_hub_compass::{
+  compiler::{compile, CompileOptions},
+  diagnostics::{Diagnostic, Severity},
+  lints::{Level, Levels},
+};
+
+// The code and severity of each diagnostic, and the source of its primary span
+fn lint(source: &str, levels: Levels) -> Vec<(String, Severity, &str)> {
+  let options = CompileOptions {
+    lints: levels,
+    ..CompileOptions::target("mips")
+  };
+  let diagnostics: Vec<Diagnostic> = compile(source, "lints.etac", &options).diagnostics;
+
+  diagnostics
+    .into_iter()
+    .map(|diagnostic| {
+      let location = diagnostic.spans[0].location.clone();
+      (
+        diagnostic.code.unwrap_or_default(),
+        diagnostic.severity,
+        &source[location],
+      )
+    })
+    .collect()
+}
+
+fn levels(levels: &[(&str, Level)]) -> Levels {
+  let mut all = Levels::default();
+  for (name, level) in levels {
+    all.set(name, *level).unwrap();
+  }
+  all
+}
+
+const PROGRAM: &str = "g: i32 = 1
+unused: i32 = 2
+_unused: i32 = 3
+call write_int(g)
+if true goto done
+goto done
+call write_int(g)
+never:
+done:
+if 1 < 2 goto done
+
+func f()
+begin
+  g: i32 = 5
+  call write_int(g)
+end
+";
+
+#[test]
+fn should_warn_about_suspicious_code() {
+  assert_eq!(
+    lint(PROGRAM, Levels::default()),
+    [
+      ("unused-variable".into(), Severity::Warning, "unused"),
+      (
+        "constant-condition".into(),
+        Severity::Warning,
+        "true goto done"
+      ),
+      (
+        "unreachable-statement".into(),
+        Severity::Warning,
+        "goto done"
+      ),
+      ("unused-label".into(), Severity::Warning, "never"),
+      (
+        "constant-condition".into(),
+        Severity::Warning,
+        "1 < 2 goto done"
+      ),
+      ("shadowed-variable".into(), Severity::Note, "g"),
+    ]
+  );
+}
+
+#[test]
+fn should_not_block_code_generation_with_warnings() {
+  let output = compile(PROGRAM, "lints.etac", &CompileOptions::target("mips"));
+
+  assert!(!output.has_errors());
+  assert!(output.artifact.is_some());
+}
+
+#[test]
+fn should_deny_warnings() {
+  let levels = levels(&[("unused-label", Level::Allow), ("warnings", Level::Deny)]);
+  let options = CompileOptions {
+    lints: levels.clone(),
+    ..CompileOptions::target("mips")
+  };
+  let output = compile(PROGRAM, "lints.etac", &options);
+
+  assert!(output.artifact.is_none());
+  assert_eq!(output.errors(), 5);
+  assert!(lint(PROGRAM, levels)
+    .iter()
+    .all(|(code, severity, _)| code != "unused-label" && *severity == Severity::Error));
+}
+
+#[test]
+fn should_set_levels_in_order() {
+  let source = "x: i32 = 1";
+
+  let warned = levels(&[("unused-variable", Level::Allow), ("warnings", Level::Warn)]);
+  assert!(lint(source, warned).is_empty());
+
+  let denied = levels(&[("warnings", Level::Deny), ("unused-variable", Level::Warn)]);
+  assert_eq!(lint(source, denied)[0].1, Severity::Warning);
+
+  assert!(Levels::default()
+    .set("unused-varible", Level::Allow)
+    .is_err());
+}
+
+#[test]
+fn should_follow_pragmas() {
+  let source = "# compass: allow(unused-variable)
+a: i32 = 1
+goto stop
+b: i32 = 2 # compass: allow(unreachable-statement)
+stop:
+goto stop
+c: i32 = 3 # compass: deny(unreachable-statement)
+";
+
+  assert_eq!(
+    lint(source, Levels::default()),
+    [("unreachable-statement".into(), Severity::Error, "c")]
+  );
+
+  // Pragmas win over the command line
+  let denied = levels(&[("warnings", Level::Deny)]);
+  assert_eq!(lint(source, denied).len(), 1);
+}
+
+#[test]
+fn should_report_unknown_lints_in_pragmas() {
+  let source = "_a: i32 = 1 # compass: allow(unused-varible)";
+  let output = compile(source, "lints.etac", &Default::default());
+
+  assert_eq!(output.diagnostics.len(), 1);
+  let diagnostic = &output.diagnostics[0];
+  assert_eq!(diagnostic.message, "Unknown lint `unused-varible`");
+  assert_eq!(diagnostic.severity, Severity::Warning);
+  assert_eq!(
+    diagnostic.help.as_deref(),
+    Some("Did you mean `unused-variable`?")
+  );
+  assert_eq!(
+    &source[diagnostic.spans[0].location.clone()],
+    "unused-varible"
+  );
+}
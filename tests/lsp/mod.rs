@@ -0,0 +1,241 @@
+use celestial_hub_compass::lsp::{analysis::Analysis, serve, Server};
+use serde_json::{json, Value};
+
+const PROGRAM: &str = "func double(n: i32): i32
+begin
+  _x: i32 = 2
+end
+
+x: i32 = call read_int()
+y: i32 = call double(x)
+goto done
+done:
+call write_int(y)
+";
+
+fn open(server: &mut Server, text: &str) -> Vec<Value> {
+  server.handle(&json!({
+    "jsonrpc": "2.0",
+    "method": "textDocument/didOpen",
+    "params": {
+      "textDocument": { "uri": "file:///main.etac", "languageId": "etac", "version": 1, "text": text },
+    },
+  }))
+}
+
+fn request(server: &mut Server, method: &str, line: u64, character: u64) -> Value {
+  let replies = server.handle(&json!({
+    "jsonrpc": "2.0",
+    "id": 1,
+    "method": method,
+    "params": {
+      "textDocument": { "uri": "file:///main.etac" },
+      "position": { "line": line, "character": character },
+    },
+  }));
+
+  replies[0]["result"].clone()
+}
+
+#[test]
+fn should_publish_type_errors() {
+  let mut server = Server::new();
+
+  let replies = open(&mut server, "a: i32 = 1\n\n_b: f32 = a\n");
+  assert_eq!(replies.len(), 1);
+  assert_eq!(replies[0]["method"], "textDocument/publishDiagnostics");
+
+  let diagnostics = replies[0]["params"]["diagnostics"].as_array().unwrap();
+  assert_eq!(diagnostics.len(), 1);
+  assert_eq!(
+    diagnostics[0]["range"],
+    json!({ "start": { "line": 2, "character": 4 }, "end": { "line": 2, "character": 7 } })
+  );
+  assert_eq!(diagnostics[0]["severity"], 1);
+  assert_eq!(
+    diagnostics[0]["message"],
+    "Mismatched types: expected `f32`\nhelp: You can either try to cast the value to `f32` or change the variable type to `i32`"
+  );
+  assert_eq!(
+    diagnostics[0]["relatedInformation"],
+    json!([{
+      "location": {
+        "uri": "file:///main.etac",
+        "range": { "start": { "line": 2, "character": 10 }, "end": { "line": 2, "character": 11 } },
+      },
+      "message": "found variable a which is `i32`",
+    }])
+  );
+
+  let replies = open(&mut server, PROGRAM);
+  assert_eq!(replies[0]["params"]["diagnostics"], json!([]));
+}
+
+#[test]
+fn should_publish_lints_with_their_severity() {
+  let mut server = Server::new();
+
+  let replies = open(
+    &mut server,
+    "unused: i32 = 1\ng: i32 = 2\ncall write_int(g)\n",
+  );
+  let diagnostics = replies[0]["params"]["diagnostics"].as_array().unwrap();
+  assert_eq!(diagnostics.len(), 1);
+  assert_eq!(diagnostics[0]["code"], "unused-variable");
+  assert_eq!(diagnostics[0]["severity"], 2);
+  assert_eq!(
+    diagnostics[0]["range"],
+    json!({ "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 6 } })
+  );
+}
+
+#[test]
+fn should_report_invalid_tokens() {
+  let analysis = Analysis::new("_a: i32 = 1\nb: i32 = $\n");
+
+  assert_eq!(analysis.diagnostics.len(), 1);
+  assert_eq!(analysis.diagnostics[0].message, "Invalid token");
+  assert_eq!(analysis.diagnostics[0].spans[0].location, 21..22);
+}
+
+#[test]
+fn should_hover_types_and_signatures() {
+  let mut server = Server::new();
+  open(&mut server, PROGRAM);
+
+  let hover = |server: &mut Server, line, character| {
+    request(server, "textDocument/hover", line, character)["contents"]["value"].clone()
+  };
+
+  assert_eq!(hover(&mut server, 6, 21), "```etac\nx: i32\n```");
+  assert_eq!(
+    hover(&mut server, 6, 16),
+    "```etac\nfunc double(n: i32): i32\n```"
+  );
+  assert_eq!(
+    hover(&mut server, 9, 5),
+    "```etac\nfunc write_int(number: i32)\n```"
+  );
+  assert_eq!(
+    request(&mut server, "textDocument/hover", 7, 6),
+    Value::Null
+  );
+}
+
+#[test]
+fn should_go_to_definitions() {
+  let analysis = Analysis::new(PROGRAM);
+  let at = |text: &str, occurrence: usize| PROGRAM.match_indices(text).nth(occurrence).unwrap().0;
+
+  // Labels, variables and functions, from their uses and from themselves
+  assert_eq!(
+    analysis.definition(at("done", 0)),
+    Some(at("done", 1)..at("done", 1) + 4)
+  );
+  assert_eq!(
+    analysis.definition(at("done", 1)),
+    Some(at("done", 1)..at("done", 1) + 4)
+  );
+  assert_eq!(
+    analysis.definition(at("(y)", 0) + 1),
+    Some(at("y:", 0)..at("y:", 0) + 1)
+  );
+  assert_eq!(
+    analysis.definition(at("double", 1)),
+    Some(at("double", 0)..at("double", 0) + 6)
+  );
+
+  // Builtins have no source
+  assert_eq!(analysis.definition(at("read_int", 0)), None);
+}
+
+#[test]
+fn should_complete_builtins() {
+  let mut server = Server::new();
+  open(&mut server, PROGRAM);
+
+  let completions = request(&mut server, "textDocument/completion", 9, 5);
+  let labels: Vec<&str> = completions
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|item| item["label"].as_str().unwrap())
+    .collect();
+
+  assert_eq!(
+    labels,
+    ["write_string", "write_int", "read_int", "read_string"]
+  );
+  assert_eq!(completions[3]["detail"], "func read_string(size: u32): str");
+}
+
+#[test]
+fn should_offer_quick_fixes_for_misspelt_names() {
+  let mut server = Server::new();
+  open(&mut server, "count: i32 = 1\ntotal: i32 = cont + 1\n");
+
+  let replies = server.handle(&json!({
+    "jsonrpc": "2.0",
+    "id": 1,
+    "method": "textDocument/codeAction",
+    "params": {
+      "textDocument": { "uri": "file:///main.etac" },
+      "range": { "start": { "line": 1, "character": 14 }, "end": { "line": 1, "character": 14 } },
+      "context": { "diagnostics": [] },
+    },
+  }));
+
+  assert_eq!(
+    replies[0]["result"],
+    json!([{
+      "title": "Replace with `count`",
+      "kind": "quickfix",
+      "isPreferred": true,
+      "edit": {
+        "changes": {
+          "file:///main.etac": [{
+            "range": { "start": { "line": 1, "character": 13 }, "end": { "line": 1, "character": 17 } },
+            "newText": "count",
+          }],
+        },
+      },
+    }])
+  );
+}
+
+#[test]
+fn should_serve_framed_messages() {
+  let messages = [
+    json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+    json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+    json!({ "jsonrpc": "2.0", "id": 2, "method": "workspace/symbol", "params": {} }),
+    json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }),
+    json!({ "jsonrpc": "2.0", "method": "exit" }),
+    json!({ "jsonrpc": "2.0", "id": 4, "method": "shutdown" }),
+  ];
+  let input: String = messages
+    .iter()
+    .map(|message| {
+      let content = message.to_string();
+      format!("Content-Length: {}\r\n\r\n{content}", content.len())
+    })
+    .collect();
+
+  let mut output = vec![];
+  serve(&mut input.as_bytes(), &mut output).unwrap();
+
+  let mut output = output.as_slice();
+  let mut replies = vec![];
+  while let Some(reply) = celestial_hub_compass::lsp::read_message(&mut output).unwrap() {
+    replies.push(reply);
+  }
+
+  // Nothing is answered after `exit`, nor to notifications
+  assert_eq!(replies.len(), 3);
+  assert_eq!(replies[0]["result"]["capabilities"]["hoverProvider"], true);
+  assert_eq!(replies[1]["error"]["code"], -32601);
+  assert_eq!(
+    replies[2],
+    json!({ "jsonrpc": "2.0", "id": 3, "result": null })
+  );
+}
@@ -1 +1,16 @@
 pub mod ast;
+pub mod bytecode;
+pub mod codegen;
+pub mod compiler;
+pub mod coverage;
+pub mod debugger;
+pub mod diagnostics;
+pub mod differential;
+pub mod formatter;
+pub mod generator;
+pub mod lints;
+pub mod lsp;
+pub mod profiler;
+pub mod repl;
+pub mod testing;
+pub mod tracer;
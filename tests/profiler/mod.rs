@@ -0,0 +1,72 @@
+use celestial_hub_compass::{
+  interpreter::Interpreter,
+  profiler::Profiler,
+  runtime::{Io, RuntimeError},
+  utils::statements_from_code_str,
+};
+
+const PROGRAM: &str = "func greet(): i32
+begin
+  call write_string(\"hi\")
+end
+
+i: i32 = 0
+loop:
+  i: i32 = i + 1
+  r: i32 = call greet()
+  if i < 3 goto loop
+done: i32 = i
+";
+
+fn profile(source: &str, max_steps: u64) -> (Profiler<'_>, Result<(), RuntimeError>) {
+  let ast = statements_from_code_str(source, "profile");
+
+  let mut profiler = Profiler::new(source, &ast);
+  let mut output = vec![];
+  let mut interpreter = Interpreter::new(&ast);
+  interpreter.max_steps = max_steps;
+  let result =
+    interpreter.run_observed(&mut Io::new(&mut "".as_bytes(), &mut output), &mut profiler);
+
+  (profiler, result)
+}
+
+#[test]
+fn should_count_functions_labels_and_blocks() {
+  let (profiler, result) = profile(PROGRAM, 1_000);
+  result.unwrap();
+
+  assert_eq!(
+    profiler.table(),
+    "     count  statements  kind      name
+         3          13  label     main:loop
+         3          12  block     main:7 (loop)
+         3           3  function  greet
+         3           3  block     greet:3
+         1          14  function  main
+         1           1  block     main:11
+         1           1  block     main:6
+"
+  );
+}
+
+#[test]
+fn should_fold_stacks_of_functions_and_labels() {
+  let (profiler, _) = profile(PROGRAM, 1_000);
+
+  assert_eq!(
+    profiler.folded(),
+    "main 1
+main;loop 13
+main;loop;greet 3
+"
+  );
+}
+
+#[test]
+fn should_profile_programs_that_never_end() {
+  let (profiler, result) = profile("loop:\n  goto loop\n", 100);
+
+  assert_eq!(result, Err(RuntimeError::StepLimitExceeded(100)));
+  assert_eq!(profiler.folded(), "main;loop 100\n");
+}
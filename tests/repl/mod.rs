@@ -0,0 +1,104 @@
+use celestial_hub_compass::{
+  repl::{is_complete, run, Repl},
+  runtime::Io,
+};
+
+fn eval(repl: &mut Repl, input: &str) -> Result<String, String> {
+  repl.eval(input, &mut Io::new(&mut "".as_bytes(), &mut vec![]))
+}
+
+#[test]
+fn should_show_values_and_types() {
+  let mut repl = Repl::new();
+
+  assert_eq!(eval(&mut repl, "x: i32 = 40").unwrap(), "x: i32 = 40");
+  assert_eq!(eval(&mut repl, "y: i32 = x + 2").unwrap(), "y: i32 = 42");
+  assert_eq!(eval(&mut repl, "y > x").unwrap(), "it: bool = true");
+  assert_eq!(eval(&mut repl, "y * 2").unwrap(), "it: i32 = 84");
+  assert_eq!(eval(&mut repl, ":type it").unwrap(), "it: i32");
+  assert_eq!(eval(&mut repl, ":type 1.5f64").unwrap(), "1.5f64: f64");
+  assert_eq!(
+    eval(&mut repl, ":type read_string").unwrap(),
+    "func read_string(size: u32): str"
+  );
+}
+
+#[test]
+fn should_keep_the_session_after_errors() {
+  let mut repl = Repl::new();
+  eval(&mut repl, "x: i32 = 1").unwrap();
+
+  assert_eq!(
+    eval(&mut repl, "y: i32 = x z: f32 = x").unwrap_err(),
+    "error: expected `f32`
+  y: i32 = x z: f32 = x
+                ^^^
+error: found variable x which is `i32`
+  y: i32 = x z: f32 = x
+                      ^
+help: You can either try to cast the value to `f32` or change the variable type to `i32`"
+  );
+  assert_eq!(
+    eval(&mut repl, "y").unwrap_err(),
+    "error: unknown variable `y`\n  y\n  ^\nhelp: Did you mean `x`?"
+  );
+  assert_eq!(eval(&mut repl, "x").unwrap(), "it: i32 = 1");
+}
+
+#[test]
+fn should_show_the_code_of_the_last_input() {
+  let mut repl = Repl::new();
+  assert_eq!(eval(&mut repl, ":asm").unwrap_err(), "Nothing was run yet");
+
+  eval(&mut repl, "x: i32 = 1").unwrap();
+  eval(&mut repl, r#"s: str = "hi" call write_string(s)"#).unwrap();
+
+  assert_eq!(
+    eval(&mut repl, ":asm").unwrap(),
+    "str_0: .asciiz \"hi\"\n\tla $t1, str_0\n\tli $v0, 4\n\tmove $a0, $t1\n\tsyscall"
+  );
+
+  let ast = eval(&mut repl, ":ast").unwrap();
+  assert!(ast.starts_with("[\n    VariableDeclaration("));
+  assert!(ast.contains("name: \"write_string\""));
+  assert!(!ast.contains("name: \"x\""));
+}
+
+#[test]
+fn should_wait_for_the_end_of_functions() {
+  assert!(is_complete("x: i32 = 1"));
+  assert!(!is_complete("func f(): i32"));
+  assert!(!is_complete(
+    "func f(): i32\nbegin\n  if true goto end\n  end:"
+  ));
+  assert!(is_complete("func f(): i32\nbegin\n  end:\nend"));
+}
+
+#[test]
+fn should_run_a_session() {
+  let input = "func answer(): i32
+begin
+  x: i32 = 1
+end
+n: i32 = call answer()
+call write_int(n)
+call read_int()
+7
+:quit
+x: i32 = 1
+";
+
+  let mut output = vec![];
+  run(&mut input.as_bytes(), &mut output).unwrap();
+
+  let output = String::from_utf8(output).unwrap();
+  let session = output.split_once('\n').unwrap().1;
+  assert_eq!(
+    session,
+    "compass> ....> ....> ....> func answer(): i32
+compass> n: i32 = 0
+compass> 0
+compass> it: i32 = 7
+compass> "
+  );
+}
@@ -0,0 +1,65 @@
+use celestial_hub_compass::testing::{check, diff, expectations, Expectations, Mode};
+
+#[test]
+fn should_read_annotations() {
+  assert_eq!(
+    expectations(
+      "# A comment: not an annotation
+# stdin: 1
+# stdin: 2
+# expect-stdout: 3
+# mode: sim
+x: i32 = 1
+"
+    ),
+    Ok(Expectations {
+      mode: Mode::Sim,
+      stdin: "1\n2\n".to_string(),
+      stdout: Some("3\n".to_string()),
+      error: None,
+    })
+  );
+  assert_eq!(
+    expectations("# expect-stderr: 1\n"),
+    Err("line 1: unknown annotation `expect-stderr`".to_string())
+  );
+}
+
+#[test]
+fn should_check_output_and_errors() {
+  assert_eq!(
+    check(
+      "# stdin: 20
+# expect-stdout: 21
+n: i32 = call read_int()
+n: i32 = n + 1
+call write_int(n)
+"
+    ),
+    Ok(())
+  );
+  // The parser reports the undeclared `x`, case does not matter
+  assert_eq!(
+    check("# expect-error: Unknown Variable\ncall write_int(x)\n"),
+    Ok(())
+  );
+  assert_eq!(
+    check("# expect-error: division by zero\nx: i32 = 1 / 0\n"),
+    Ok(())
+  );
+  assert_eq!(
+    check("# expect-error: division by zero\nx: i32 = 1 / 1\n"),
+    Err(vec![
+      "expected an error containing `division by zero`, but it finished".to_string()
+    ])
+  );
+}
+
+#[test]
+fn should_show_differences_of_the_output() {
+  assert_eq!(
+    check("# expect-stdout: 1\n# expect-stdout: 2\ncall write_int(1)\n"),
+    Err(vec!["stdout differs:\n  1\n- 2".to_string()])
+  );
+  assert_eq!(diff("a\nb\nc", "a\nx\nc\nd"), "  a\n- b\n+ x\n  c\n+ d");
+}
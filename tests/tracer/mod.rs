@@ -0,0 +1,72 @@
+use celestial_hub_compass::{
+  interpreter::Interpreter,
+  runtime::Io,
+  tracer::{TraceFormat, Tracer},
+  utils::statements_from_code_str,
+};
+
+const PROGRAM: &str = "func greet(): i32
+begin
+  call write_string(\"hi\")
+end
+
+i: i32 = 0
+loop:
+  i: i32 = i + 1
+  if i < 2 goto loop
+r: i32 = call greet()
+";
+
+fn trace(source: &str, format: TraceFormat) -> String {
+  let ast = statements_from_code_str(source, "trace");
+
+  let mut trace = vec![];
+  let mut output = vec![];
+  Interpreter::new(&ast)
+    .run_observed(
+      &mut Io::new(&mut "".as_bytes(), &mut output),
+      &mut Tracer::new(source, &mut trace, format),
+    )
+    .unwrap();
+
+  String::from_utf8(trace).unwrap()
+}
+
+#[test]
+fn should_trace_statements_with_the_variables_they_read() {
+  assert_eq!(
+    trace(PROGRAM, TraceFormat::Text),
+    "       2  main:6  i: i32 = 0
+       3  main:7  loop:
+       4  main:8  i: i32 = i + 1  # i: i32 = 0
+       5  main:9  if i < 2 goto loop  # i: i32 = 1
+       6  main:7  loop:
+       7  main:8  i: i32 = i + 1  # i: i32 = 1
+       8  main:9  if i < 2 goto loop  # i: i32 = 2
+       9  main:10  r: i32 = call greet()
+      10  greet:3  call write_string(\"hi\")
+"
+  );
+}
+
+#[test]
+fn should_trace_as_json_lines() {
+  let trace = trace(PROGRAM, TraceFormat::Json);
+  let records: Vec<serde_json::Value> = trace
+    .lines()
+    .map(|line| serde_json::from_str(line).unwrap())
+    .collect();
+
+  assert_eq!(records.len(), 9);
+  assert_eq!(
+    records[2],
+    serde_json::json!({
+      "step": 4,
+      "function": "main",
+      "line": 8,
+      "statement": "i: i32 = i + 1",
+      "variables": [{ "name": "i", "type": "i32", "value": 0 }],
+    })
+  );
+  assert_eq!(records[8]["function"], "greet");
+}